@@ -0,0 +1,9 @@
+-- Add migration script here
+ALTER TABLE
+    users
+ADD
+    COLUMN title VARCHAR(100) NOT NULL DEFAULT '',
+ADD
+    COLUMN status_text VARCHAR(100) NOT NULL DEFAULT '',
+ADD
+    COLUMN timezone VARCHAR(64) NOT NULL DEFAULT 'UTC';
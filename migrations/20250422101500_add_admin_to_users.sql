@@ -0,0 +1,7 @@
+-- Add migration script here
+ALTER TABLE
+    users
+ADD
+    COLUMN is_admin BOOLEAN NOT NULL DEFAULT FALSE,
+ADD
+    COLUMN must_reset_password BOOLEAN NOT NULL DEFAULT FALSE;
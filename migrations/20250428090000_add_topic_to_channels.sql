@@ -0,0 +1,5 @@
+-- Add migration script here
+ALTER TABLE
+    channels
+ADD
+    COLUMN ch_topic VARCHAR(250) NOT NULL DEFAULT '';
@@ -0,0 +1,18 @@
+-- Add migration script here
+ALTER TABLE
+    channels
+ADD
+    COLUMN pins_admin_only BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE IF NOT EXISTS pinned_messages (
+    id BIGSERIAL PRIMARY KEY,
+    channel_id BIGINT NOT NULL,
+    message_id BIGINT NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    pinned_by BIGINT NOT NULL,
+    pinned_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    CONSTRAINT unique_pinned_message UNIQUE (message_id)
+);
+
+-- List the pins of a channel, newest first
+CREATE INDEX idx_pinned_messages_channel_pinned ON pinned_messages(channel_id, pinned_at DESC);
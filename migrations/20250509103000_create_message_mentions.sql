@@ -0,0 +1,16 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS message_mentions (
+    id BIGSERIAL PRIMARY KEY,
+    message_id BIGINT NOT NULL REFERENCES messages(id) ON DELETE CASCADE,
+    -- 'user','channel','here'
+    mention_type VARCHAR(20) NOT NULL,
+    user_id BIGINT, -- Set only for 'user' mentions
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    CONSTRAINT check_mention_user CHECK ((mention_type = 'user') = (user_id IS NOT NULL))
+);
+
+-- Load the mentions of a page of messages
+CREATE INDEX idx_message_mentions_message_id ON message_mentions(message_id);
+-- Find messages mentioning a user
+CREATE INDEX idx_message_mentions_user_id ON message_mentions(user_id);
@@ -0,0 +1,13 @@
+-- Add migration script here
+ALTER TABLE
+    messages
+ADD
+    COLUMN rich_text JSONB NOT NULL DEFAULT '[]'::jsonb,
+ADD
+    COLUMN plain_text TEXT NOT NULL DEFAULT '';
+
+-- Existing messages keep an empty AST; their text is the best plain rendering we have.
+UPDATE
+    messages
+SET
+    plain_text = COALESCE(text_content, '');
@@ -0,0 +1,12 @@
+-- Add migration script here
+ALTER TABLE
+    messages
+ADD
+    COLUMN unfurls JSONB NOT NULL DEFAULT '[]'::jsonb;
+
+-- Unfurl cache shared by all messages; card is NULL when the url could not be unfurled
+CREATE TABLE IF NOT EXISTS link_unfurls (
+    url TEXT PRIMARY KEY,
+    card JSONB,
+    fetched_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,23 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS incoming_webhooks (
+    id BIGSERIAL PRIMARY KEY,
+    channel_id BIGINT NOT NULL,
+    creator_id BIGINT NOT NULL,
+    -- the user messages are posted as
+    bot_user_id BIGINT NOT NULL,
+    name VARCHAR(80) NOT NULL,
+    -- sha256 of the token; the token itself is only shown once
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    revoked_at TIMESTAMPTZ,
+    last_used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_incoming_webhooks_channel_id ON incoming_webhooks(channel_id);
+
+ALTER TABLE
+    messages
+ADD
+    COLUMN sender_override JSONB,
+ADD
+    COLUMN attachments JSONB NOT NULL DEFAULT '[]';
@@ -0,0 +1,40 @@
+-- Add migration script here
+ALTER TABLE
+    users
+ADD
+    COLUMN is_bot BOOLEAN NOT NULL DEFAULT FALSE,
+ADD
+    COLUMN owner_id BIGINT;
+
+-- incoming webhooks already post as bot users
+UPDATE
+    users
+SET
+    is_bot = TRUE,
+    owner_id = w.creator_id
+FROM
+    incoming_webhooks w
+WHERE
+    users.id = w.bot_user_id;
+
+CREATE INDEX idx_users_owner_id ON users(owner_id) WHERE owner_id IS NOT NULL;
+
+CREATE TABLE IF NOT EXISTS api_tokens (
+    id BIGSERIAL PRIMARY KEY,
+    -- the bot the token authenticates as
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(80) NOT NULL,
+    -- sha256 of the token; the token itself is only shown once
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    -- the first characters, so owners can tell tokens apart
+    token_prefix VARCHAR(16) NOT NULL,
+    -- 'messages:read','messages:write','channels:read','channels:write','users:read'
+    scopes TEXT[] NOT NULL,
+    created_by BIGINT NOT NULL,
+    expires_at TIMESTAMPTZ,
+    last_used_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_api_tokens_user_id ON api_tokens(user_id);
@@ -0,0 +1,14 @@
+-- Add migration script here
+-- Workspace-wide commands answered by an external HTTP endpoint
+CREATE TABLE IF NOT EXISTS slash_commands (
+    id BIGSERIAL PRIMARY KEY,
+    -- without the leading slash
+    name VARCHAR(32) NOT NULL UNIQUE,
+    description VARCHAR(250) NOT NULL DEFAULT '',
+    usage_hint VARCHAR(100) NOT NULL DEFAULT '',
+    url VARCHAR(2048) NOT NULL,
+    secret VARCHAR(128) NOT NULL,
+    creator_id BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
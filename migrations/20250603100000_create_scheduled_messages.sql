@@ -0,0 +1,30 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS scheduled_messages (
+    id BIGSERIAL PRIMARY KEY,
+    channel_id BIGINT NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    sender_id BIGINT NOT NULL,
+    parent_msg_id BIGINT,
+    content_type message_content_type NOT NULL DEFAULT 'text',
+    text_content TEXT NOT NULL,
+    media_url VARCHAR(2048),
+    media_metadata JSONB NOT NULL DEFAULT 'null',
+    send_at TIMESTAMPTZ NOT NULL,
+    -- 'pending','sending','sent','failed','cancelled'
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',
+    -- the posted message, once sent
+    message_id BIGINT,
+    last_error TEXT,
+    -- a claimed message is reclaimed once its lease runs out, so a scheduler that died
+    -- mid-send doesn't leave it in 'sending' for good
+    claimed_at TIMESTAMPTZ,
+    attempts INT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- The scheduler's queue
+CREATE INDEX idx_scheduled_messages_due ON scheduled_messages(send_at) WHERE status = 'pending';
+-- Expired leases
+CREATE INDEX idx_scheduled_messages_claimed ON scheduled_messages(claimed_at) WHERE status = 'sending';
+-- List a user's scheduled messages by send time
+CREATE INDEX idx_scheduled_messages_sender ON scheduled_messages(sender_id, send_at);
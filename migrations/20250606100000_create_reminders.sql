@@ -0,0 +1,52 @@
+-- Add migration script here
+-- Direct message channels: private channels between exactly two users
+ALTER TABLE
+    channels
+ADD
+    COLUMN is_direct BOOLEAN NOT NULL DEFAULT FALSE,
+ADD
+    -- 'smaller_user_id:larger_user_id', so each pair has one channel
+    COLUMN dm_key VARCHAR(64) UNIQUE;
+
+-- The system bot that delivers reminders; it cannot log in. A human account already named
+-- 'slacbot' could send as the system, so refuse to continue until it is renamed.
+DO $$
+BEGIN
+    IF EXISTS (
+        SELECT
+            1
+        FROM
+            users
+        WHERE
+            username = 'slacbot'
+            AND NOT is_bot
+    ) THEN RAISE EXCEPTION 'username slacbot is reserved for the system bot; rename that user and rerun migrations';
+    END IF;
+END $$;
+
+INSERT INTO
+    users (username, password_hash, display_name, is_bot)
+VALUES
+    ('slacbot', '!', 'Slacbot', TRUE) ON CONFLICT (username) DO NOTHING;
+
+CREATE TABLE IF NOT EXISTS reminders (
+    id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- the message to be reminded about, if any
+    message_id BIGINT REFERENCES messages(id) ON DELETE SET NULL,
+    text VARCHAR(1000) NOT NULL DEFAULT '',
+    remind_at TIMESTAMPTZ NOT NULL,
+    -- 'pending','delivered','completed'
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',
+    -- the direct message that delivered it
+    dm_message_id BIGINT,
+    delivered_at TIMESTAMPTZ,
+    completed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- The reminder worker's queue
+CREATE INDEX idx_reminders_due ON reminders(remind_at) WHERE status = 'pending';
+-- List a user's reminders by time
+CREATE INDEX idx_reminders_user ON reminders(user_id, remind_at);
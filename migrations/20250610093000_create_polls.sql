@@ -0,0 +1,32 @@
+-- Add migration script here
+ALTER TYPE message_content_type ADD VALUE IF NOT EXISTS 'poll';
+
+-- A poll is a message whose text is the question
+CREATE TABLE IF NOT EXISTS polls (
+    message_id BIGINT PRIMARY KEY REFERENCES messages(id) ON DELETE CASCADE,
+    channel_id BIGINT NOT NULL,
+    creator_id BIGINT NOT NULL,
+    options TEXT[] NOT NULL,
+    allow_multiple BOOLEAN NOT NULL DEFAULT FALSE,
+    -- votes are counted but voters are not shown
+    is_anonymous BOOLEAN NOT NULL DEFAULT FALSE,
+    closes_at TIMESTAMPTZ,
+    closed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Polls the scheduler still has to close
+CREATE INDEX idx_polls_closes_at ON polls(closes_at) WHERE closed_at IS NULL AND closes_at IS NOT NULL;
+
+CREATE TABLE IF NOT EXISTS poll_votes (
+    id BIGSERIAL PRIMARY KEY,
+    message_id BIGINT NOT NULL REFERENCES polls(message_id) ON DELETE CASCADE,
+    -- position in polls.options, from 0
+    option_index INT NOT NULL,
+    user_id BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    CONSTRAINT unique_poll_vote UNIQUE (message_id, option_index, user_id)
+);
+
+CREATE INDEX idx_poll_votes_user ON poll_votes(message_id, user_id);
@@ -0,0 +1,108 @@
+use axum::http::Method;
+use sha2::{Digest, Sha256};
+
+/// API tokens start with this, which tells them apart from JWTs in the bearer header.
+pub const API_TOKEN_PREFIX: &str = "slac_";
+const TOKEN_LEN: usize = 40;
+// How much of a token is kept in clear to tell tokens apart.
+const SHOWN_PREFIX_LEN: usize = 12;
+
+pub const SCOPE_MESSAGES_READ: &str = "messages:read";
+pub const SCOPE_MESSAGES_WRITE: &str = "messages:write";
+pub const SCOPE_CHANNELS_READ: &str = "channels:read";
+pub const SCOPE_CHANNELS_WRITE: &str = "channels:write";
+pub const SCOPE_USERS_READ: &str = "users:read";
+
+pub const API_SCOPES: [&str; 5] = [
+    SCOPE_MESSAGES_READ,
+    SCOPE_MESSAGES_WRITE,
+    SCOPE_CHANNELS_READ,
+    SCOPE_CHANNELS_WRITE,
+    SCOPE_USERS_READ,
+];
+
+/// A new random token and the prefix shown in token lists.
+pub fn generate_token() -> (String, String) {
+    let token = format!("{}{}", API_TOKEN_PREFIX, nanoid::nanoid!(TOKEN_LEN));
+    let prefix = token[..SHOWN_PREFIX_LEN].to_string();
+    (token, prefix)
+}
+
+// Tokens are random enough that an unsalted hash is sufficient, and it keeps them searchable.
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// The scope an API token needs for a request, or None when the route only accepts a login
+/// session: account settings, token and webhook management, and administration.
+pub fn required_scope(method: &Method, path: &str) -> Option<&'static str> {
+    let segments: Vec<&str> = path
+        .trim_start_matches("/api/v1/")
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect();
+    let read = *method == Method::GET;
+    let pick = |read_scope, write_scope| Some(if read { read_scope } else { write_scope });
+
+    match segments.as_slice() {
+        ["messages", ..] => pick(SCOPE_MESSAGES_READ, SCOPE_MESSAGES_WRITE),
+        ["users", "me", "mentions" | "saved", ..] => {
+            pick(SCOPE_MESSAGES_READ, SCOPE_MESSAGES_WRITE)
+        }
+        ["users", "me", ..] => None,
+        ["users", ..] if read => Some(SCOPE_USERS_READ),
+        ["channels", _, "webhooks" | "incoming-webhooks", ..] => None,
+        ["channels", _, "messages" | "pins", ..] => pick(SCOPE_MESSAGES_READ, SCOPE_MESSAGES_WRITE),
+        ["channels", ..] => pick(SCOPE_CHANNELS_READ, SCOPE_CHANNELS_WRITE),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_required_scope() {
+        let scope = |method: Method, path: &str| required_scope(&method, path);
+
+        assert_eq!(
+            scope(Method::POST, "/api/v1/channels/3/messages"),
+            Some(SCOPE_MESSAGES_WRITE)
+        );
+        assert_eq!(
+            scope(Method::GET, "/api/v1/channels/3/pins"),
+            Some(SCOPE_MESSAGES_READ)
+        );
+        assert_eq!(
+            scope(Method::DELETE, "/api/v1/messages/9"),
+            Some(SCOPE_MESSAGES_WRITE)
+        );
+        assert_eq!(
+            scope(Method::GET, "/api/v1/channels/search"),
+            Some(SCOPE_CHANNELS_READ)
+        );
+        assert_eq!(
+            scope(Method::POST, "/api/v1/channels/3/members"),
+            Some(SCOPE_CHANNELS_WRITE)
+        );
+        assert_eq!(
+            scope(Method::GET, "/api/v1/users/4"),
+            Some(SCOPE_USERS_READ)
+        );
+
+        assert_eq!(scope(Method::POST, "/api/v1/users/me/password"), None);
+        assert_eq!(scope(Method::GET, "/api/v1/channels/3/webhooks"), None);
+        assert_eq!(scope(Method::POST, "/api/v1/bots/5/tokens"), None);
+        assert_eq!(scope(Method::GET, "/api/v1/admin/users"), None);
+    }
+
+    #[test]
+    fn test_generate_token() {
+        let (token, prefix) = generate_token();
+        assert!(token.starts_with(API_TOKEN_PREFIX));
+        assert!(token.starts_with(&prefix));
+        assert_ne!(hash_token(&token), hash_token(&generate_token().0));
+        assert_eq!(hash_token(&token).len(), 64);
+    }
+}
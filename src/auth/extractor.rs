@@ -0,0 +1,319 @@
+use std::{
+    convert::Infallible,
+    net::{IpAddr, SocketAddr},
+};
+
+use axum::{
+    extract::{ConnectInfo, FromRequestParts, OriginalUri, Query},
+    http::{Method, header, request::Parts},
+};
+use axum_extra::{
+    TypedHeader,
+    headers::{Authorization, authorization::Bearer},
+};
+use serde::Deserialize;
+
+use crate::{
+    auth::api_token::{API_TOKEN_PREFIX, hash_token, required_scope},
+    dto::user::User,
+    errors::AppError,
+    models::{api_token::ApiTokenStore, user::UserRepository},
+    service::audit::ClientInfo,
+    state::AppState,
+};
+
+/// The active user that the request's bearer token was issued to. The token is either a
+/// login JWT or a bot's API token, which must carry the scope the route requires.
+#[derive(Debug)]
+pub struct CurrentUser(pub User);
+
+impl FromRequestParts<AppState> for CurrentUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let TypedHeader(Authorization(bearer)) =
+            TypedHeader::<Authorization<Bearer>>::from_request_parts(parts, state)
+                .await
+                .map_err(|_| AppError::Unauthorized("missing bearer token".to_string()))?;
+
+        authenticate(parts, state, bearer.token()).await
+    }
+}
+
+async fn authenticate(
+    parts: &Parts,
+    state: &AppState,
+    token: &str,
+) -> Result<CurrentUser, AppError> {
+    if token.starts_with(API_TOKEN_PREFIX) {
+        return authenticate_api_token(parts, state, token).await;
+    }
+
+    let claims = state
+        .dk
+        .verify(token)
+        .map_err(|_| AppError::Unauthorized("invalid token".to_string()))?;
+
+    // tokens outlive profile changes and deactivation, so load the current row.
+    let user_repo = UserRepository::new(&state.pool);
+    let user = match user_repo.get_by_id(claims.id).await? {
+        Some(user) if user.is_active => User::from(user),
+        _ => return Err(AppError::Unauthorized("user is not active".to_string())),
+    };
+    check_password_reset(&user, &parts.method, request_path(parts))?;
+    Ok(CurrentUser(user))
+}
+
+const CHANGE_PASSWORD_PATH: &str = "/api/v1/users/me/password";
+
+// After an admin reset the temporary password only gets the user as far as choosing a
+// new one.
+fn check_password_reset(user: &User, method: &Method, path: &str) -> Result<(), AppError> {
+    if user.must_reset_password && !(method == Method::POST && path == CHANGE_PASSWORD_PATH) {
+        return Err(AppError::PermissionDenied(
+            "password must be changed before continuing".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// nested routers only see the rest of the path.
+fn request_path(parts: &Parts) -> &str {
+    parts
+        .extensions
+        .get::<OriginalUri>()
+        .map_or(parts.uri.path(), |OriginalUri(uri)| uri.path())
+}
+
+#[derive(Deserialize)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+/// A `CurrentUser` for websocket upgrades. Browsers can't set headers on those, so the
+/// token may also come as the `token` query parameter.
+#[derive(Debug)]
+pub struct SocketUser(pub User);
+
+impl FromRequestParts<AppState> for SocketUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let bearer = TypedHeader::<Authorization<Bearer>>::from_request_parts(parts, state)
+            .await
+            .ok()
+            .map(|TypedHeader(Authorization(bearer))| bearer.token().to_string());
+        let token = match bearer {
+            Some(token) => token,
+            None => Query::<TokenQuery>::try_from_uri(&parts.uri)
+                .ok()
+                .and_then(|Query(q)| q.token)
+                .ok_or_else(|| AppError::Unauthorized("missing bearer token".to_string()))?,
+        };
+
+        let CurrentUser(user) = authenticate(parts, state, &token).await?;
+        Ok(SocketUser(user))
+    }
+}
+
+async fn authenticate_api_token(
+    parts: &Parts,
+    state: &AppState,
+    token: &str,
+) -> Result<CurrentUser, AppError> {
+    let token_store = ApiTokenStore::new(&state.pool);
+    let api_token = token_store
+        .get_valid_by_hash(&hash_token(token))
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("invalid token".to_string()))?;
+
+    match required_scope(&parts.method, request_path(parts)) {
+        Some(scope) if api_token.scopes.iter().any(|s| s == scope) => {}
+        Some(scope) => {
+            return Err(AppError::PermissionDenied(format!(
+                "token lacks the {} scope",
+                scope
+            )));
+        }
+        None => {
+            return Err(AppError::PermissionDenied(
+                "api tokens cannot be used here".to_string(),
+            ));
+        }
+    }
+
+    let user_repo = UserRepository::new(&state.pool);
+    let user = match user_repo.get_by_id(api_token.user_id).await? {
+        Some(user) if user.is_active => user,
+        _ => return Err(AppError::Unauthorized("user is not active".to_string())),
+    };
+    token_store.touch(api_token.id).await?;
+    Ok(CurrentUser(User::from(user)))
+}
+
+/// A `CurrentUser` with the system-level admin flag set.
+#[derive(Debug)]
+pub struct AdminUser(pub User);
+
+impl FromRequestParts<AppState> for AdminUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let CurrentUser(user) = CurrentUser::from_request_parts(parts, state).await?;
+        require_admin(&user)?;
+        Ok(AdminUser(user))
+    }
+}
+
+fn require_admin(user: &User) -> Result<(), AppError> {
+    if !user.is_admin {
+        return Err(AppError::PermissionDenied(
+            "admin privileges required".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+impl FromRequestParts<AppState> for ClientInfo {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let header_str = |name| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        let forwarded = header_str(header::HeaderName::from_static("x-forwarded-for"));
+        let ip = peer.map(|peer| {
+            client_ip(
+                peer,
+                forwarded.as_deref(),
+                &state.config.server.trusted_proxies,
+            )
+        });
+
+        Ok(ClientInfo {
+            ip: ip.map(|ip| ip.to_string()),
+            user_agent: header_str(header::USER_AGENT),
+            peer_ip: peer.map(|peer| peer.to_string()),
+        })
+    }
+}
+
+// Anyone can send X-Forwarded-For, so it is only read when the peer is one of our proxies.
+// Each proxy appends the address it got the request from: walking back from the end, the
+// first address that isn't a trusted proxy is the client.
+fn client_ip(peer: IpAddr, forwarded: Option<&str>, trusted_proxies: &[IpAddr]) -> IpAddr {
+    if !trusted_proxies.contains(&peer) {
+        return peer;
+    }
+
+    let mut client = peer;
+    for hop in forwarded.unwrap_or("").rsplit(',') {
+        match hop.trim().parse::<IpAddr>() {
+            Ok(ip) => client = ip,
+            Err(_) => break,
+        }
+        if !trusted_proxies.contains(&client) {
+            break;
+        }
+    }
+    client
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(is_admin: bool, must_reset_password: bool) -> User {
+        User {
+            id: 1,
+            username: "john".to_string(),
+            display_name: "John".to_string(),
+            title: "".to_string(),
+            status_text: "".to_string(),
+            timezone: "UTC".to_string(),
+            avatar_url: "".to_string(),
+            is_active: true,
+            is_admin,
+            must_reset_password,
+            is_bot: false,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_require_admin() {
+        assert!(require_admin(&user(true, false)).is_ok());
+        assert!(matches!(
+            require_admin(&user(false, false)),
+            Err(AppError::PermissionDenied(_))
+        ));
+    }
+
+    #[test]
+    fn test_check_password_reset() {
+        let reset = user(true, true);
+        assert!(check_password_reset(&reset, &Method::POST, CHANGE_PASSWORD_PATH).is_ok());
+        for (method, path) in [
+            (Method::GET, CHANGE_PASSWORD_PATH),
+            (Method::GET, "/api/v1/channels"),
+            (Method::POST, "/api/v1/channels/3/messages"),
+            (Method::GET, "/api/v1/admin/users"),
+        ] {
+            assert!(matches!(
+                check_password_reset(&reset, &method, path),
+                Err(AppError::PermissionDenied(_))
+            ));
+        }
+
+        // changing the password clears the flag, which lifts the restriction.
+        let changed = user(true, false);
+        assert!(check_password_reset(&changed, &Method::GET, "/api/v1/admin/users").is_ok());
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        let proxies = [ip("10.0.0.1"), ip("10.0.0.2")];
+
+        // a client talking to us directly can't pick its own address.
+        assert_eq!(
+            client_ip(ip("203.0.113.9"), Some("1.2.3.4"), &proxies),
+            ip("203.0.113.9")
+        );
+        assert_eq!(
+            client_ip(ip("10.0.0.1"), Some("1.2.3.4, 198.51.100.7"), &proxies),
+            ip("198.51.100.7")
+        );
+        assert_eq!(
+            client_ip(ip("10.0.0.1"), Some("198.51.100.7, 10.0.0.2"), &proxies),
+            ip("198.51.100.7")
+        );
+        assert_eq!(client_ip(ip("10.0.0.1"), None, &proxies), ip("10.0.0.1"));
+        assert_eq!(
+            client_ip(ip("10.0.0.1"), Some("garbage"), &proxies),
+            ip("10.0.0.1")
+        );
+    }
+}
@@ -2,27 +2,42 @@ use jwt_simple::{claims::Claims, prelude::*};
 
 use crate::dto::user::User;
 
-const JWT_DURATION: u64 = 64 * 64 * 24 * 7;
+pub mod api_token;
+pub mod extractor;
+
+// used until the config sets `auth.token_ttl_secs`.
+const JWT_DURATION: u64 = 60 * 60 * 24 * 7;
 const JWT_ISS: &str = "slac-app";
 const JWT_AUD: &str = "slac-users";
 
 #[derive(Clone)]
-pub struct EncodingKey(Ed25519KeyPair);
+pub struct EncodingKey {
+    key: Ed25519KeyPair,
+    ttl: Duration,
+}
 
 #[derive(Debug, Clone)]
 pub struct DecodingKey(Ed25519PublicKey);
 
 impl EncodingKey {
     pub fn load(pem: &str) -> Result<Self, jwt_simple::Error> {
-        Ok(Self(Ed25519KeyPair::from_pem(pem)?))
+        Ok(Self {
+            key: Ed25519KeyPair::from_pem(pem)?,
+            ttl: Duration::from_secs(JWT_DURATION),
+        })
+    }
+
+    // How long the tokens it signs stay valid.
+    pub fn with_ttl(mut self, ttl: std::time::Duration) -> Self {
+        self.ttl = ttl.into();
+        self
     }
 
     pub fn sign(&self, user: User) -> Result<String, jwt_simple::Error> {
-        let claims: JWTClaims<User> =
-            Claims::with_custom_claims(user, Duration::from_secs(JWT_DURATION));
+        let claims: JWTClaims<User> = Claims::with_custom_claims(user, self.ttl);
 
         let claims = claims.with_issuer(JWT_ISS).with_audience(JWT_AUD);
-        self.0.sign(claims)
+        self.key.sign(claims)
     }
 }
 
@@ -59,8 +74,14 @@ mod tests {
             id: 1,
             username: "john".to_string(),
             display_name: "John".to_string(),
+            title: "".to_string(),
+            status_text: "".to_string(),
+            timezone: "UTC".to_string(),
             avatar_url: "".to_string(),
             is_active: true,
+            is_admin: false,
+            must_reset_password: false,
+            is_bot: false,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
         };
@@ -0,0 +1,519 @@
+use std::{
+    env, fmt, fs,
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    time::Duration,
+};
+
+use axum::http::HeaderValue;
+use serde::Deserialize;
+use sqlx::postgres::PgPoolOptions;
+use thiserror::Error;
+
+use crate::{
+    auth::{DecodingKey, EncodingKey},
+    ratelimit::Quota,
+    telemetry::REDACTED,
+};
+
+/// Names the config file; without it `slac.toml` is read when present.
+pub const CONFIG_PATH_VAR: &str = "SLAC_CONFIG";
+const DEFAULT_PATH: &str = "slac.toml";
+
+// `SLAC_SERVER__BIND=0.0.0.0:8080` overrides `bind` in `[server]`.
+const ENV_PREFIX: &str = "SLAC_";
+const ENV_SEPARATOR: &str = "__";
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("read {path}: {source}")]
+    Read {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("parse {origin}: {message}")]
+    Parse { origin: String, message: String },
+    #[error("invalid config: {}", .0.join("; "))]
+    Invalid(Vec<String>),
+    #[error("load key {path}: {source}")]
+    Key {
+        path: String,
+        source: jwt_simple::Error,
+    },
+}
+
+/// Everything the server reads at startup. Each section and field has a default, so a
+/// file only needs what differs.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub auth: AuthConfig,
+    pub cors: CorsConfig,
+    pub limits: LimitsConfig,
+    pub rate_limits: RateLimitConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind: SocketAddr,
+    // Prometheus scrapes `/metrics` here; keep it off the public network.
+    pub metrics_bind: SocketAddr,
+    // how long open sockets get to close once requests have drained.
+    pub shutdown_timeout_secs: u64,
+    // reverse proxies whose X-Forwarded-For is believed; other peers are the client.
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: SocketAddr::from(([0, 0, 0, 0], 6869)),
+            metrics_bind: SocketAddr::from(([127, 0, 0, 1], 6870)),
+            shutdown_timeout_secs: 10,
+            trusted_proxies: vec![],
+        }
+    }
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    // usually left to `DATABASE_URL`, since it carries the password.
+    pub url: String,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout_secs: u64,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            max_connections: 10,
+            min_connections: 0,
+            acquire_timeout_secs: 30,
+        }
+    }
+}
+
+impl DatabaseConfig {
+    pub fn pool_options(&self) -> PgPoolOptions {
+        PgPoolOptions::new()
+            .max_connections(self.max_connections)
+            .min_connections(self.min_connections)
+            .acquire_timeout(Duration::from_secs(self.acquire_timeout_secs))
+    }
+}
+
+impl fmt::Debug for DatabaseConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DatabaseConfig")
+            .field("url", &REDACTED)
+            .field("max_connections", &self.max_connections)
+            .field("min_connections", &self.min_connections)
+            .field("acquire_timeout_secs", &self.acquire_timeout_secs)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    pub private_key_path: PathBuf,
+    pub public_key_path: PathBuf,
+    pub token_ttl_secs: u64,
+    pub min_password_len: usize,
+    pub max_password_len: usize,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            private_key_path: PathBuf::from("private_key.pem"),
+            public_key_path: PathBuf::from("public_key.pem"),
+            token_ttl_secs: 60 * 60 * 24 * 7,
+            min_password_len: 8,
+            max_password_len: 20,
+        }
+    }
+}
+
+impl AuthConfig {
+    pub fn token_ttl(&self) -> Duration {
+        Duration::from_secs(self.token_ttl_secs)
+    }
+
+    pub fn load_keys(&self) -> Result<(EncodingKey, DecodingKey), ConfigError> {
+        let private_pem = read(&self.private_key_path)?;
+        let ek = EncodingKey::load(&private_pem)
+            .map_err(|source| ConfigError::Key {
+                path: self.private_key_path.display().to_string(),
+                source,
+            })?
+            .with_ttl(self.token_ttl());
+
+        let public_pem = read(&self.public_key_path)?;
+        let dk = DecodingKey::load(&public_pem).map_err(|source| ConfigError::Key {
+            path: self.public_key_path.display().to_string(),
+            source,
+        })?;
+        Ok((ek, dk))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    // `["*"]` allows any origin; otherwise full origins like `https://chat.example.com`.
+    // Empty, the default, allows none: the web client must be served from the same origin.
+    pub allowed_origins: Vec<String>,
+    pub max_age_secs: u64,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: vec![],
+            max_age_secs: 3600,
+        }
+    }
+}
+
+impl CorsConfig {
+    pub fn allows_any(&self) -> bool {
+        self.allowed_origins.iter().any(|o| o == "*")
+    }
+
+    pub fn origins(&self) -> Vec<HeaderValue> {
+        self.allowed_origins
+            .iter()
+            .filter_map(|o| HeaderValue::from_str(o).ok())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    // the largest request body accepted, uploads included.
+    pub max_body_bytes: usize,
+    // events a socket may fall behind by before it is closed.
+    pub socket_buffer: usize,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: 2 * 1024 * 1024,
+            socket_buffer: 100,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    pub api: Quota,
+    pub login: Quota,
+    pub socket: Quota,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            api: Quota::API,
+            login: Quota::LOGIN,
+            socket: Quota::SOCKET,
+        }
+    }
+}
+
+impl Config {
+    /// Reads the config file, applies `SLAC_*` overrides and `DATABASE_URL` from the
+    /// environment (`.env` included), then validates the result.
+    pub fn load() -> Result<Self, ConfigError> {
+        let (path, required) = match env::var(CONFIG_PATH_VAR) {
+            Ok(path) => (path, true),
+            Err(_) => (DEFAULT_PATH.to_string(), false),
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if !required && e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(source) => return Err(ConfigError::Read { path, source }),
+        };
+
+        let config = Self::from_sources(&path, &text, env::vars())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn from_sources(
+        origin: &str,
+        text: &str,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, ConfigError> {
+        let mut table: toml::Table =
+            text.parse()
+                .map_err(|e: toml::de::Error| ConfigError::Parse {
+                    origin: origin.to_string(),
+                    message: e.to_string(),
+                })?;
+
+        for (name, raw) in vars {
+            let path = if name == "DATABASE_URL" {
+                vec!["database".to_string(), "url".to_string()]
+            } else {
+                match name.strip_prefix(ENV_PREFIX).and_then(env_path) {
+                    Some(path) => path,
+                    None => continue,
+                }
+            };
+            set_path(&mut table, &path, env_value(&raw));
+        }
+
+        toml::Value::Table(table)
+            .try_into()
+            .map_err(|e: toml::de::Error| ConfigError::Parse {
+                origin: format!("{} with environment overrides", origin),
+                message: e.message().to_string(),
+            })
+    }
+
+    /// Checks what serde can't, and reports every problem at once.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = vec![];
+
+        if self.server.metrics_bind == self.server.bind {
+            problems.push("server.metrics_bind must differ from bind".to_string());
+        }
+
+        if self.database.url.is_empty() {
+            problems.push("database.url is empty, set DATABASE_URL".to_string());
+        }
+        if self.database.max_connections == 0 {
+            problems.push("database.max_connections must be at least 1".to_string());
+        }
+        if self.database.min_connections > self.database.max_connections {
+            problems.push("database.min_connections exceeds max_connections".to_string());
+        }
+
+        let auth = &self.auth;
+        if auth.token_ttl_secs == 0 {
+            problems.push("auth.token_ttl_secs must be at least 1".to_string());
+        }
+        // requests are validated against 8 before the service ever sees them.
+        if auth.min_password_len < 8 || auth.min_password_len > auth.max_password_len {
+            problems.push(
+                "auth.min_password_len must be at least 8 and at most max_password_len".to_string(),
+            );
+        }
+        for path in [&auth.private_key_path, &auth.public_key_path] {
+            if !path.is_file() {
+                problems.push(format!("key file {} does not exist", path.display()));
+            }
+        }
+
+        if self.cors.allows_any() && self.cors.allowed_origins.len() > 1 {
+            problems.push("cors.allowed_origins mixes \"*\" with other origins".to_string());
+        }
+        for origin in self.cors.allowed_origins.iter().filter(|o| *o != "*") {
+            let valid = (origin.starts_with("http://") || origin.starts_with("https://"))
+                && !origin.ends_with('/')
+                && HeaderValue::from_str(origin).is_ok();
+            if !valid {
+                problems.push(format!(
+                    "cors origin {:?} must look like https://host[:port]",
+                    origin
+                ));
+            }
+        }
+
+        if self.limits.max_body_bytes == 0 {
+            problems.push("limits.max_body_bytes must be at least 1".to_string());
+        }
+        if self.limits.socket_buffer == 0 {
+            problems.push("limits.socket_buffer must be at least 1".to_string());
+        }
+
+        let quotas = [
+            ("api", &self.rate_limits.api),
+            ("login", &self.rate_limits.login),
+            ("socket", &self.rate_limits.socket),
+        ];
+        for (name, quota) in quotas {
+            if quota.burst == 0 || quota.per_second.is_nan() || quota.per_second <= 0.0 {
+                problems.push(format!(
+                    "rate_limits.{} needs a burst and per_second above 0",
+                    name
+                ));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+}
+
+fn read(path: &PathBuf) -> Result<String, ConfigError> {
+    fs::read_to_string(path).map_err(|source| ConfigError::Read {
+        path: path.display().to_string(),
+        source,
+    })
+}
+
+// Only `SECTION__KEY` names are overrides; other `SLAC_*` variables, such as
+// `SLAC_CONFIG`, belong to something else.
+fn env_path(name: &str) -> Option<Vec<String>> {
+    let path: Vec<String> = name
+        .split(ENV_SEPARATOR)
+        .map(|part| part.to_lowercase())
+        .collect();
+    if path.len() < 2 || path.iter().any(|part| part.is_empty()) {
+        return None;
+    }
+    Some(path)
+}
+
+// Values are read as TOML, so numbers, booleans and lists keep their type; anything that
+// doesn't parse, like a bare url, is taken as a string.
+fn env_value(raw: &str) -> toml::Value {
+    format!("v = {}", raw)
+        .parse::<toml::Table>()
+        .ok()
+        .and_then(|mut t| t.remove("v"))
+        .unwrap_or_else(|| toml::Value::String(raw.to_string()))
+}
+
+fn set_path(table: &mut toml::Table, path: &[String], value: toml::Value) {
+    let Some((last, parents)) = path.split_last() else {
+        return;
+    };
+
+    let mut current = table;
+    for key in parents {
+        let entry = current
+            .entry(key.clone())
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        if !entry.is_table() {
+            *entry = toml::Value::Table(toml::Table::new());
+        }
+        let toml::Value::Table(next) = entry else {
+            return;
+        };
+        current = next;
+    }
+    current.insert(last.clone(), value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_file_and_env_overrides() {
+        let text = r#"
+            [server]
+            bind = "127.0.0.1:7000"
+
+            [database]
+            max_connections = 20
+
+            [rate_limits.login]
+            burst = 3
+            per_second = 0.1
+        "#;
+        let config = Config::from_sources(
+            "slac.toml",
+            text,
+            vars(&[
+                ("DATABASE_URL", "postgres://u:p@localhost/slac"),
+                ("SLAC_SERVER__BIND", "0.0.0.0:8080"),
+                ("SLAC_AUTH__TOKEN_TTL_SECS", "3600"),
+                (
+                    "SLAC_CORS__ALLOWED_ORIGINS",
+                    r#"["https://chat.example.com"]"#,
+                ),
+                ("SLAC_CONFIG", "slac.toml"),
+                ("SLAC_HOME", "/opt/slac"),
+                ("SLAC_SERVER__", "ignored"),
+                ("HOME", "/root"),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(config.server.bind, "0.0.0.0:8080".parse().unwrap());
+        assert_eq!(config.database.url, "postgres://u:p@localhost/slac");
+        assert_eq!(config.database.max_connections, 20);
+        assert_eq!(config.database.min_connections, 0);
+        assert_eq!(config.auth.token_ttl(), Duration::from_secs(3600));
+        assert_eq!(
+            config.cors.allowed_origins,
+            vec!["https://chat.example.com"]
+        );
+        assert_eq!(config.rate_limits.login.burst, 3);
+        assert_eq!(config.rate_limits.api.burst, Quota::API.burst);
+        assert!(!format!("{:?}", config).contains("u:p@"));
+    }
+
+    #[test]
+    fn test_unknown_keys_are_rejected() {
+        let err = Config::from_sources("slac.toml", "[server]\nport = 80\n", vec![]).unwrap_err();
+        assert!(err.to_string().contains("port"), "{}", err);
+
+        let err = Config::from_sources("slac.toml", "", vars(&[("SLAC_SERVER__PROT", "80")]))
+            .unwrap_err();
+        assert!(err.to_string().contains("prot"), "{}", err);
+    }
+
+    // The problems other than missing key files, which depend on where the test runs.
+    fn problems(config: &Config) -> Vec<String> {
+        match config.validate() {
+            Ok(()) => vec![],
+            Err(ConfigError::Invalid(problems)) => problems
+                .into_iter()
+                .filter(|p| !p.starts_with("key file"))
+                .collect(),
+            Err(e) => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut config = Config::default();
+        config.database.url = "postgres://localhost/slac".to_string();
+        assert!(problems(&config).is_empty(), "{:?}", problems(&config));
+
+        config.database.min_connections = 50;
+        config.auth.min_password_len = 4;
+        config.cors.allowed_origins = vec!["chat.example.com".to_string()];
+        config.rate_limits.api.per_second = 0.0;
+        assert_eq!(
+            problems(&config),
+            vec![
+                "database.min_connections exceeds max_connections",
+                "auth.min_password_len must be at least 8 and at most max_password_len",
+                "cors origin \"chat.example.com\" must look like https://host[:port]",
+                "rate_limits.api needs a burst and per_second above 0",
+            ]
+        );
+
+        config.auth.private_key_path = PathBuf::from("/nonexistent/private_key.pem");
+        let ConfigError::Invalid(all) = config.validate().unwrap_err() else {
+            panic!("expected invalid config");
+        };
+        assert!(all.contains(&"key file /nonexistent/private_key.pem does not exist".to_string()));
+    }
+}
@@ -0,0 +1,57 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::dto::{channel::Channel, user::User};
+use crate::telemetry::REDACTED;
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ListUsersReq {
+    #[validate(length(max = 100))]
+    pub q: Option<String>,
+    #[validate(range(min = 0))]
+    pub offset: i64,
+    #[validate(range(min = 1, max = 100))]
+    pub limit: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListUsersResp {
+    pub users: Vec<User>,
+    pub total: i64,
+    pub has_more: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AdminUserResp {
+    pub user: User,
+}
+
+#[derive(Serialize)]
+pub struct ResetPasswordResp {
+    pub user: User,
+    pub temporary_password: String,
+}
+
+impl fmt::Debug for ResetPasswordResp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResetPasswordResp")
+            .field("user", &self.user)
+            .field("temporary_password", &REDACTED)
+            .finish()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChannelMembership {
+    pub channel: Channel,
+    pub member_role: String,
+    pub joined_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListMembershipsResp {
+    pub memberships: Vec<ChannelMembership>,
+}
@@ -0,0 +1,24 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::models::audit::AuditEvent;
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ListAuditEventsReq {
+    pub actor_id: Option<i64>,
+    #[validate(length(max = 64))]
+    pub action: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    #[validate(range(min = 0))]
+    pub offset: i64,
+    #[validate(range(min = 1, max = 200))]
+    pub limit: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListAuditEventsResp {
+    pub events: Vec<AuditEvent>,
+    pub has_more: bool,
+}
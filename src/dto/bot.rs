@@ -0,0 +1,85 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{dto::user::User, models::api_token::ApiToken as ApiTokenDao, telemetry::REDACTED};
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateBotReq {
+    #[validate(length(min = 3, max = 32))]
+    pub username: String,
+
+    #[validate(length(min = 1, max = 20))]
+    pub display_name: String,
+
+    #[validate(url)]
+    pub avatar_url: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListBotsResp {
+    pub bots: Vec<User>,
+}
+
+// The token itself is only ever returned once, by `CreateApiTokenResp`.
+#[derive(Debug, Serialize)]
+pub struct ApiToken {
+    pub id: i64,
+    pub user_id: i64,
+    pub name: String,
+    pub token_prefix: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<ApiTokenDao> for ApiToken {
+    fn from(token: ApiTokenDao) -> Self {
+        Self {
+            id: token.id,
+            user_id: token.user_id,
+            name: token.name,
+            token_prefix: token.token_prefix,
+            scopes: token.scopes,
+            expires_at: token.expires_at,
+            last_used_at: token.last_used_at,
+            created_at: token.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateApiTokenReq {
+    #[validate(length(min = 1, max = 80))]
+    pub name: String,
+
+    #[validate(length(min = 1))]
+    pub scopes: Vec<String>,
+
+    // None never expires.
+    #[validate(range(min = 1, max = 365))]
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct CreateApiTokenResp {
+    pub api_token: ApiToken,
+    pub token: String,
+}
+
+impl fmt::Debug for CreateApiTokenResp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CreateApiTokenResp")
+            .field("api_token", &self.api_token)
+            .field("token", &REDACTED)
+            .finish()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListApiTokensResp {
+    pub tokens: Vec<ApiToken>,
+}
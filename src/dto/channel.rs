@@ -1,3 +1,4 @@
+use crate::dto::message::Message;
 use crate::models::channel::{Channel as ChanDao, ChannelMembers};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -8,9 +9,12 @@ pub struct Channel {
     pub id: i64,
     pub ch_name: String,
     pub ch_description: String,
+    pub ch_topic: String,
     pub creator_id: i64,
     pub is_private: bool,
     pub is_archived: bool,
+    pub pins_admin_only: bool,
+    pub is_direct: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -21,18 +25,38 @@ pub struct CreateChannelRequest {
     pub ch_name: String,
     #[validate(length(min = 8))]
     pub ch_desc: String,
-    pub creator_id: i64,
     pub is_private: bool,
 }
 
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateChannelReq {
+    #[validate(length(min = 5, max = 50))]
+    pub ch_name: Option<String>,
+    #[validate(length(min = 8))]
+    pub ch_desc: Option<String>,
+    #[validate(length(max = 250))]
+    pub ch_topic: Option<String>,
+    pub is_private: Option<bool>,
+    pub pins_admin_only: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UpdateChanResp {
+    pub channel: Channel,
+    pub system_msgs: Vec<Message>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct CreateChannelResp {
     pub channel: Channel,
+    pub system_msg: Message,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct ListChanReq {
     pub creator_id: i64,
+    #[serde(default)]
+    pub include_archived: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -45,31 +69,38 @@ pub struct ListUserChannels {
     pub user_id: i64,
     pub offset: i64,
     pub limit: i64,
+    #[serde(default)]
+    pub include_archived: bool,
 }
 
-#[derive(Debug, Serialize)]
-pub struct GetChanResp {
-    pub channel: Option<Channel>,
+#[derive(Debug, Deserialize, Validate)]
+pub struct ListUserChannelsQuery {
+    #[serde(default)]
+    pub include_archived: bool,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct JoinChanReq {
-    pub user_id: i64,
+#[derive(Debug, Deserialize, Validate)]
+pub struct SearchChanReq {
+    #[validate(length(min = 1, max = 100))]
+    pub q: String,
+    pub limit: Option<i64>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct LeaveChanReq {
-    pub user_id: i64,
+#[derive(Debug, Serialize)]
+pub struct GetChanResp {
+    pub channel: Option<Channel>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct JoinChanResp {
     pub chan_members: ChannelMembers,
+    pub system_msg: Message,
 }
 
 #[derive(Debug, Serialize)]
 pub struct LeaveChanResp {
     pub chan_members: Vec<ChannelMembers>,
+    pub system_msg: Option<Message>,
 }
 
 #[derive(Debug, Serialize)]
@@ -77,15 +108,40 @@ pub struct ListChanMembersResp {
     pub chan_members_list: Vec<ChannelMembers>,
 }
 
+#[derive(Debug, Deserialize, Validate)]
+pub struct AddMemberReq {
+    pub user_id: i64,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ChangeMemberRoleReq {
+    #[validate(length(min = 1, max = 16))]
+    pub member_role: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangeMemberRoleResp {
+    pub chan_member: ChannelMembers,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArchiveChanResp {
+    pub channel: Channel,
+    pub system_msg: Message,
+}
+
 impl From<ChanDao> for Channel {
     fn from(ch: ChanDao) -> Self {
         Self {
             id: ch.id,
             ch_name: ch.ch_name,
             ch_description: ch.ch_description,
+            ch_topic: ch.ch_topic,
             creator_id: ch.creator_id,
             is_private: ch.is_private,
             is_archived: ch.is_archived,
+            pins_admin_only: ch.pins_admin_only,
+            is_direct: ch.is_direct,
             created_at: ch.created_at,
             updated_at: ch.updated_at,
         }
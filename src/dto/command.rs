@@ -0,0 +1,101 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{dto::message::Message, models::command::SlashCommand, telemetry::REDACTED};
+
+/// A command as offered to clients for autocompletion.
+#[derive(Debug, Serialize)]
+pub struct CommandInfo {
+    // None for built-in commands.
+    pub id: Option<i64>,
+    pub name: String,
+    pub usage_hint: String,
+    pub description: String,
+    pub is_builtin: bool,
+}
+
+impl From<SlashCommand> for CommandInfo {
+    fn from(cmd: SlashCommand) -> Self {
+        Self {
+            id: Some(cmd.id),
+            name: cmd.name,
+            usage_hint: cmd.usage_hint,
+            description: cmd.description,
+            is_builtin: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListCommandsResp {
+    pub commands: Vec<CommandInfo>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct RegisterCommandReq {
+    #[validate(length(min = 1, max = 32))]
+    pub name: String,
+    #[serde(default)]
+    #[validate(length(max = 250))]
+    pub description: String,
+    #[serde(default)]
+    #[validate(length(max = 100))]
+    pub usage_hint: String,
+    #[validate(url, length(max = 2048))]
+    pub url: String,
+}
+
+#[derive(Serialize)]
+pub struct RegisterCommandResp {
+    pub command: CommandInfo,
+    /// Signs every request to the command's url, like outgoing webhooks.
+    pub secret: String,
+}
+
+impl fmt::Debug for RegisterCommandResp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RegisterCommandResp")
+            .field("command", &self.command)
+            .field("secret", &REDACTED)
+            .finish()
+    }
+}
+
+/// What sending a message answers when its text was a slash command.
+#[derive(Debug, Serialize)]
+pub struct CommandResp {
+    pub command: String,
+    // Only the invoker sees it; it is also pushed to their sockets.
+    pub response: Option<String>,
+    // Set when the command posted to the channel.
+    pub message: Option<Message>,
+}
+
+/// The body posted to an external command's url.
+#[derive(Debug, Serialize)]
+pub struct ExternalCommandReq<'a> {
+    pub command: &'a str,
+    pub text: &'a str,
+    pub user_id: i64,
+    pub username: &'a str,
+    pub channel_id: i64,
+    pub channel_name: &'a str,
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseType {
+    #[default]
+    Ephemeral,
+    InChannel,
+}
+
+/// What an external command may answer with; an empty body answers nothing.
+#[derive(Debug, Deserialize)]
+pub struct ExternalCommandResp {
+    pub text: String,
+    #[serde(default)]
+    pub response_type: ResponseType,
+}
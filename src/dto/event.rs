@@ -0,0 +1,57 @@
+use serde::Serialize;
+
+use crate::dto::{
+    channel::Channel,
+    message::{Message, PinnedMessage, WebSocketMessage},
+    poll::Poll,
+};
+
+/// Everything the server pushes down a user's websocket, tagged by `type`.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerEvent {
+    Message(WebSocketMessage),
+    SystemMessage {
+        message: Message,
+    },
+    ChannelUpdated {
+        channel: Channel,
+    },
+    ChannelArchived {
+        channel: Channel,
+    },
+    ChannelUnarchived {
+        channel: Channel,
+    },
+    Mentioned {
+        message: Message,
+    },
+    // A message posted over HTTP, e.g. by an incoming webhook.
+    MessageCreated {
+        message: Message,
+    },
+    MessageUpdated {
+        message: Message,
+    },
+    MessagePinned {
+        channel_id: i64,
+        pin: PinnedMessage,
+    },
+    MessageUnpinned {
+        channel_id: i64,
+        message_id: i64,
+        unpinned_by: i64,
+    },
+    // A vote changed a poll's tally, or the poll closed.
+    PollUpdated {
+        poll: Poll,
+    },
+    // The answer to a slash command, shown only to the user who ran it.
+    Ephemeral {
+        channel_id: i64,
+        text: String,
+    },
+    Error {
+        message: String,
+    },
+}
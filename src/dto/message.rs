@@ -1,14 +1,18 @@
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
+use validator::Validate;
 
 use crate::dto::SimpleUser;
 use crate::models::message::Message as MessageDao;
 use crate::models::message::MessageContentType as MessageCTDao;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Validate)]
 pub struct MediaMetadata {
+    #[validate(range(min = 1, max = 16384))]
     width: u32,
+    #[validate(range(min = 1, max = 16384))]
     height: u32,
+    #[validate(length(min = 1, max = 16))]
     format: String,
 }
 
@@ -20,6 +24,186 @@ pub enum MessageContentType {
     Video,
     File,
     System,
+    Poll,
+}
+
+/// The machine-readable payload of a `System` message, stored in `media_metadata`
+/// so clients can render localized text instead of the English fallback.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SystemEvent {
+    ChannelCreated {
+        actor_id: i64,
+        ch_name: String,
+    },
+    MemberJoined {
+        user_id: i64,
+    },
+    MemberLeft {
+        user_id: i64,
+    },
+    MemberAdded {
+        actor_id: i64,
+        user_id: i64,
+    },
+    MemberRemoved {
+        actor_id: i64,
+        user_id: i64,
+    },
+    ChannelRenamed {
+        actor_id: i64,
+        from: String,
+        to: String,
+    },
+    ChannelUpdated {
+        actor_id: i64,
+        changes: serde_json::Value,
+    },
+    ChannelArchived {
+        actor_id: i64,
+    },
+    ChannelUnarchived {
+        actor_id: i64,
+    },
+    MessagePinned {
+        actor_id: i64,
+        message_id: i64,
+    },
+    MessageUnpinned {
+        actor_id: i64,
+        message_id: i64,
+    },
+    // `actor_id` is None when the poll reached its close time.
+    PollClosed {
+        actor_id: Option<i64>,
+        message_id: i64,
+        question: String,
+        options: Vec<PollTally>,
+    },
+}
+
+/// How many votes an option of a closed poll got.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PollTally {
+    pub text: String,
+    pub votes: i64,
+}
+
+impl SystemEvent {
+    // English text kept in `text_content` for clients that don't render system events.
+    pub fn fallback_text(&self) -> String {
+        match self {
+            SystemEvent::ChannelCreated { actor_id, ch_name } => {
+                format!("<@{}> created #{}", actor_id, ch_name)
+            }
+            SystemEvent::MemberJoined { user_id } => {
+                format!("<@{}> joined the channel", user_id)
+            }
+            SystemEvent::MemberLeft { user_id } => format!("<@{}> left the channel", user_id),
+            SystemEvent::MemberAdded { actor_id, user_id } => {
+                format!("<@{}> added <@{}> to the channel", actor_id, user_id)
+            }
+            SystemEvent::MemberRemoved { actor_id, user_id } => {
+                format!("<@{}> removed <@{}> from the channel", actor_id, user_id)
+            }
+            SystemEvent::ChannelRenamed { actor_id, from, to } => {
+                format!(
+                    "<@{}> renamed the channel from #{} to #{}",
+                    actor_id, from, to
+                )
+            }
+            SystemEvent::ChannelUpdated { actor_id, changes } => {
+                let fields: Vec<&str> = changes
+                    .as_object()
+                    .map(|m| {
+                        m.keys()
+                            .map(|k| match k.as_str() {
+                                "is_private" => "visibility",
+                                "pins_admin_only" => "pin permissions",
+                                k => k.trim_start_matches("ch_"),
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                format!("<@{}> updated the channel {}", actor_id, fields.join(", "))
+            }
+            SystemEvent::ChannelArchived { actor_id } => {
+                format!("<@{}> archived the channel", actor_id)
+            }
+            SystemEvent::ChannelUnarchived { actor_id } => {
+                format!("<@{}> unarchived the channel", actor_id)
+            }
+            SystemEvent::MessagePinned { actor_id, .. } => {
+                format!("<@{}> pinned a message", actor_id)
+            }
+            SystemEvent::MessageUnpinned { actor_id, .. } => {
+                format!("<@{}> unpinned a message", actor_id)
+            }
+            SystemEvent::PollClosed {
+                actor_id,
+                question,
+                options,
+                ..
+            } => {
+                let results: Vec<String> = options
+                    .iter()
+                    .map(|o| format!("{}: {}", o.text, o.votes))
+                    .collect();
+                let closed = match actor_id {
+                    Some(actor_id) => format!("<@{}> closed the poll", actor_id),
+                    None => "The poll closed".to_string(),
+                };
+                format!("{} \"{}\": {}", closed, question, results.join(", "))
+            }
+        }
+    }
+}
+
+/// The name and avatar an incoming webhook asked its message to be shown with, instead of
+/// its bot user's profile.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SenderOverride {
+    pub username: Option<String>,
+    pub avatar_url: Option<String>,
+}
+
+/// A card shown under a message posted by an integration.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Validate)]
+pub struct Attachment {
+    #[validate(length(max = 250))]
+    pub title: Option<String>,
+    #[validate(url)]
+    pub title_link: Option<String>,
+    #[validate(length(max = 3000))]
+    pub text: Option<String>,
+    // a css color such as `#36a64f`
+    #[validate(length(max = 16))]
+    pub color: Option<String>,
+    #[validate(url)]
+    pub image_url: Option<String>,
+    #[serde(default)]
+    #[validate(length(max = 20), nested)]
+    pub fields: Vec<AttachmentField>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Validate)]
+pub struct AttachmentField {
+    #[validate(length(max = 250))]
+    pub title: String,
+    #[validate(length(max = 2000))]
+    pub value: String,
+    // whether the field is narrow enough to sit next to another one
+    #[serde(default)]
+    pub short: bool,
+}
+
+/// A resolved `@` mention in a message's text.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Mention {
+    User { user_id: i64, username: String },
+    Channel,
+    Here,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -32,21 +216,46 @@ pub struct Message {
     pub text_content: String,
     pub media_url: Option<String>,
     pub media_metadata: serde_json::Value,
+    /// The `service::markdown` block AST of `text_content`.
+    #[serde(default)]
+    pub rich_text: serde_json::Value,
+    #[serde(default)]
+    pub plain_text: String,
+    /// `unfurl::UnfurlCard`s for the links in the text, filled in after the message is sent.
+    #[serde(default)]
+    pub unfurls: serde_json::Value,
+    #[serde(default)]
+    pub mentions: Vec<Mention>,
+    /// A `SenderOverride` for messages posted by incoming webhooks.
+    #[serde(default)]
+    pub sender_override: Option<serde_json::Value>,
+    /// `Attachment` cards posted by integrations.
+    #[serde(default)]
+    pub attachments: serde_json::Value,
     pub created_at: chrono::DateTime<Utc>,
     pub updated_at: chrono::DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Deserialize, PartialEq, Validate)]
 pub struct SendMessageReq {
     pub sender_id: Option<i64>,
     pub parent_msg_id: Option<i64>,
     pub content_type: MessageContentType,
+    #[validate(length(max = 4000))]
     pub text_content: String,
+    #[validate(url, length(max = 2048))]
     pub media_url: Option<String>,
+    #[validate(nested)]
     pub media_metadata: Option<MediaMetadata>,
+    // Only set server-side, by incoming webhooks.
+    #[serde(skip)]
+    pub sender_override: Option<SenderOverride>,
+    #[serde(skip)]
+    #[validate(nested)]
+    pub attachments: Vec<Attachment>,
 }
 
-#[derive(Debug, Serialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, PartialEq)]
 pub struct WebSocketMessage {
     pub sender: SimpleUser,
     pub parent_msg_id: Option<i64>,
@@ -56,15 +265,18 @@ pub struct WebSocketMessage {
     pub media_metadata: Option<MediaMetadata>,
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Deserialize, PartialEq, Validate)]
 pub struct UpdateMessageReq {
     pub id: i64,
     pub chan_id: i64,
     pub sender_id: Option<i64>,
     pub parent_msg_id: Option<i64>,
     pub content_type: MessageContentType,
+    #[validate(length(max = 4000))]
     pub text_content: String,
+    #[validate(url, length(max = 2048))]
     pub media_url: Option<String>,
+    #[validate(nested)]
     pub media_metadata: Option<MediaMetadata>,
 }
 
@@ -73,9 +285,11 @@ pub struct SendMessageResp {
     pub msg: Message,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
 pub struct ListMessagesReq {
+    #[validate(range(min = 0))]
     pub offset: i64,
+    #[validate(range(min = 1, max = 100))]
     pub limit: i64,
 }
 
@@ -85,9 +299,53 @@ pub struct ListMessagesResp {
     pub has_more: bool,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Validate)]
+pub struct ListMentionsReq {
+    #[serde(default)]
+    #[validate(range(min = 0))]
+    pub offset: i64,
+    #[serde(default = "default_mentions_limit")]
+    #[validate(range(min = 1, max = 100))]
+    pub limit: i64,
+}
+
+fn default_mentions_limit() -> i64 {
+    20
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct PinnedMessage {
+    pub message: Message,
+    pub pinned_by: SimpleUser,
+    pub pinned_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct PinMessageReq {
+    pub message_id: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PinMessageResp {
+    pub pin: PinnedMessage,
+    pub system_msg: Message,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnpinMessageResp {
+    pub message_id: i64,
+    pub system_msg: Message,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListPinsResp {
+    pub pins: Vec<PinnedMessage>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
 pub struct SendMessageInSocket {
     pub channel_id: i64,
+    #[validate(nested)]
     pub msgs: Vec<SendMessageReq>,
 }
 
@@ -102,6 +360,12 @@ impl From<MessageDao> for Message {
             text_content: msg.text_content,
             media_url: msg.media_url,
             media_metadata: msg.media_metadata,
+            rich_text: msg.rich_text,
+            plain_text: msg.plain_text,
+            unfurls: msg.unfurls,
+            mentions: vec![],
+            sender_override: msg.sender_override,
+            attachments: msg.attachments,
             created_at: msg.created_at,
             updated_at: msg.updated_at,
         }
@@ -119,6 +383,11 @@ impl From<Message> for MessageDao {
             text_content: msg.text_content,
             media_url: msg.media_url,
             media_metadata: msg.media_metadata,
+            rich_text: msg.rich_text,
+            plain_text: msg.plain_text,
+            unfurls: msg.unfurls,
+            sender_override: msg.sender_override,
+            attachments: msg.attachments,
             created_at: msg.created_at,
             updated_at: msg.updated_at,
         }
@@ -133,6 +402,7 @@ impl From<MessageCTDao> for MessageContentType {
             MessageCTDao::Video => MessageContentType::Video,
             MessageCTDao::File => MessageContentType::File,
             MessageCTDao::System => MessageContentType::System,
+            MessageCTDao::Poll => MessageContentType::Poll,
         }
     }
 }
@@ -145,6 +415,36 @@ impl From<MessageContentType> for MessageCTDao {
             MessageContentType::Video => MessageCTDao::Video,
             MessageContentType::File => MessageCTDao::File,
             MessageContentType::System => MessageCTDao::System,
+            MessageContentType::Poll => MessageCTDao::Poll,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_system_event_metadata() {
+        let event = SystemEvent::ChannelRenamed {
+            actor_id: 1,
+            from: "learn-rust".to_string(),
+            to: "learn-rust-2025".to_string(),
+        };
+
+        let metadata = serde_json::to_value(&event).unwrap();
+        assert_eq!(
+            metadata,
+            json!({"event": "channel_renamed", "actor_id": 1, "from": "learn-rust", "to": "learn-rust-2025"})
+        );
+        assert_eq!(
+            serde_json::from_value::<SystemEvent>(metadata).unwrap(),
+            event
+        );
+        assert_eq!(
+            event.fallback_text(),
+            "<@1> renamed the channel from #learn-rust to #learn-rust-2025"
+        );
+    }
+}
@@ -1,12 +1,37 @@
 use serde::{Deserialize, Serialize};
 
+use crate::models::user::User as UserDao;
+
+pub mod admin;
+pub mod audit;
+pub mod bot;
 pub mod channel;
+pub mod command;
+pub mod event;
 pub mod message;
+pub mod poll;
+pub mod reminder;
+pub mod saved;
+pub mod scheduled;
 pub mod user;
+pub mod webhook;
 
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
 pub struct SimpleUser {
     pub id: i64,
     pub avatar_url: String,
     pub display_name: String,
+    #[serde(default)]
+    pub is_bot: bool,
+}
+
+impl From<UserDao> for SimpleUser {
+    fn from(user: UserDao) -> Self {
+        Self {
+            id: user.id,
+            avatar_url: user.avatar_url,
+            display_name: user.display_name,
+            is_bot: user.is_bot,
+        }
+    }
 }
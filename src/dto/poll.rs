@@ -0,0 +1,72 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::dto::message::Message;
+
+/// The options and settings of a poll, stored in its message's `media_metadata` so message
+/// lists can render it without fetching the poll.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PollSpec {
+    pub options: Vec<String>,
+    pub allow_multiple: bool,
+    pub is_anonymous: bool,
+    pub closes_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct PollOption {
+    pub index: i32,
+    pub text: String,
+    pub votes: i64,
+    // empty for anonymous polls.
+    pub voters: Vec<i64>,
+}
+
+/// A poll with its current tally.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Poll {
+    pub message_id: i64,
+    pub channel_id: i64,
+    pub creator_id: i64,
+    pub question: String,
+    pub options: Vec<PollOption>,
+    pub allow_multiple: bool,
+    pub is_anonymous: bool,
+    pub closes_at: Option<DateTime<Utc>>,
+    pub closed_at: Option<DateTime<Utc>>,
+    pub is_closed: bool,
+    pub total_voters: i64,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreatePollReq {
+    #[validate(length(min = 1, max = 300))]
+    pub question: String,
+    #[validate(length(min = 2, max = 10))]
+    pub options: Vec<String>,
+    #[serde(default)]
+    pub allow_multiple: bool,
+    #[serde(default)]
+    pub is_anonymous: bool,
+    pub closes_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreatePollResp {
+    pub message: Message,
+    pub poll: Poll,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PollResp {
+    pub poll: Poll,
+    /// The option indexes the requesting user voted for, also for anonymous polls.
+    pub my_votes: Vec<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClosePollResp {
+    pub poll: Poll,
+    pub system_msg: Message,
+}
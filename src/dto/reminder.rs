@@ -0,0 +1,77 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::models::reminder::Reminder as ReminderDao;
+
+#[derive(Debug, Serialize)]
+pub struct Reminder {
+    pub id: i64,
+    pub message_id: Option<i64>,
+    pub text: String,
+    pub remind_at: DateTime<Utc>,
+    pub status: String,
+    pub dm_message_id: Option<i64>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<ReminderDao> for Reminder {
+    fn from(r: ReminderDao) -> Self {
+        Self {
+            id: r.id,
+            message_id: r.message_id,
+            text: r.text,
+            remind_at: r.remind_at,
+            status: r.status,
+            dm_message_id: r.dm_message_id,
+            delivered_at: r.delivered_at,
+            completed_at: r.completed_at,
+            created_at: r.created_at,
+        }
+    }
+}
+
+/// A reminder about a message, a note, or both. The time is either `remind_at` or a phrase
+/// such as "tomorrow at 9" in `when`, read in the user's timezone.
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateReminderReq {
+    pub message_id: Option<i64>,
+    #[serde(default)]
+    #[validate(length(max = 1000))]
+    pub text: String,
+    #[validate(length(min = 1, max = 100))]
+    pub when: Option<String>,
+    pub remind_at: Option<DateTime<Utc>>,
+}
+
+// Without a time, snoozes for 20 minutes.
+#[derive(Debug, Deserialize, Validate)]
+pub struct SnoozeReminderReq {
+    #[validate(length(min = 1, max = 100))]
+    pub when: Option<String>,
+    pub remind_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ListRemindersReq {
+    // the open ones, pending or delivered, unless given.
+    pub status: Option<String>,
+    #[serde(default)]
+    #[validate(range(min = 0))]
+    pub offset: i64,
+    #[serde(default = "default_limit")]
+    #[validate(range(min = 1, max = 100))]
+    pub limit: i64,
+}
+
+fn default_limit() -> i64 {
+    20
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListRemindersResp {
+    pub reminders: Vec<Reminder>,
+    pub has_more: bool,
+}
@@ -0,0 +1,49 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::dto::message::Message;
+
+#[derive(Debug, Serialize)]
+pub struct SavedItem {
+    pub message: Message,
+    pub remind_at: Option<DateTime<Utc>>,
+    pub is_completed: bool,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub saved_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SaveItemReq {
+    pub message_id: i64,
+    pub remind_at: Option<DateTime<Utc>>,
+}
+
+// Replaces the reminder and completion state of a saved item.
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateSavedItemReq {
+    pub remind_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub is_completed: bool,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ListSavedItemsReq {
+    pub is_completed: Option<bool>,
+    #[serde(default)]
+    #[validate(range(min = 0))]
+    pub offset: i64,
+    #[serde(default = "default_limit")]
+    #[validate(range(min = 1, max = 100))]
+    pub limit: i64,
+}
+
+fn default_limit() -> i64 {
+    20
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListSavedItemsResp {
+    pub items: Vec<SavedItem>,
+    pub has_more: bool,
+}
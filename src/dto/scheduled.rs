@@ -0,0 +1,92 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    dto::message::{MediaMetadata, MessageContentType},
+    models::scheduled::ScheduledMessage as ScheduledMessageDao,
+};
+
+#[derive(Debug, Serialize)]
+pub struct ScheduledMessage {
+    pub id: i64,
+    pub channel_id: i64,
+    pub sender_id: i64,
+    pub parent_msg_id: Option<i64>,
+    pub content_type: MessageContentType,
+    pub text_content: String,
+    pub media_url: Option<String>,
+    pub media_metadata: serde_json::Value,
+    pub send_at: DateTime<Utc>,
+    pub status: String,
+    pub message_id: Option<i64>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<ScheduledMessageDao> for ScheduledMessage {
+    fn from(msg: ScheduledMessageDao) -> Self {
+        Self {
+            id: msg.id,
+            channel_id: msg.channel_id,
+            sender_id: msg.sender_id,
+            parent_msg_id: msg.parent_msg_id,
+            content_type: msg.content_type.into(),
+            text_content: msg.text_content,
+            media_url: msg.media_url,
+            media_metadata: msg.media_metadata,
+            send_at: msg.send_at,
+            status: msg.status,
+            message_id: msg.message_id,
+            last_error: msg.last_error,
+            created_at: msg.created_at,
+            updated_at: msg.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ScheduleMessageReq {
+    pub channel_id: i64,
+    pub parent_msg_id: Option<i64>,
+    pub content_type: MessageContentType,
+    #[validate(length(max = 4000))]
+    pub text_content: String,
+    #[validate(url)]
+    pub media_url: Option<String>,
+    #[validate(nested)]
+    pub media_metadata: Option<MediaMetadata>,
+    pub send_at: DateTime<Utc>,
+}
+
+// Fields left out keep their value.
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateScheduledMessageReq {
+    #[validate(length(max = 4000))]
+    pub text_content: Option<String>,
+    pub send_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ListScheduledMessagesReq {
+    pub channel_id: Option<i64>,
+    // 'pending' unless asked for the sent, failed or cancelled ones.
+    pub status: Option<String>,
+    #[serde(default)]
+    #[validate(range(min = 0))]
+    pub offset: i64,
+    #[serde(default = "default_limit")]
+    #[validate(range(min = 1, max = 100))]
+    pub limit: i64,
+}
+
+fn default_limit() -> i64 {
+    20
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListScheduledMessagesResp {
+    pub messages: Vec<ScheduledMessage>,
+    pub has_more: bool,
+}
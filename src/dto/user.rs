@@ -1,8 +1,11 @@
+use std::fmt;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use validator::Validate;
 
 use crate::models::user::User as UserDao;
+use crate::telemetry::REDACTED;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct User {
@@ -10,12 +13,20 @@ pub struct User {
     pub username: String,
     pub avatar_url: String,
     pub display_name: String,
+    pub title: String,
+    pub status_text: String,
+    pub timezone: String,
     pub is_active: bool,
+    pub is_admin: bool,
+    pub must_reset_password: bool,
+    // tokens issued before bots existed lack the claim.
+    #[serde(default)]
+    pub is_bot: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize, Validate)]
+#[derive(Deserialize, Validate)]
 pub struct RegisterRequest {
     #[validate(length(min = 6, max = 20))]
     pub username: String,
@@ -30,12 +41,23 @@ pub struct RegisterRequest {
     pub display_name: String,
 }
 
+impl fmt::Debug for RegisterRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RegisterRequest")
+            .field("username", &self.username)
+            .field("password", &REDACTED)
+            .field("avatar", &self.avatar)
+            .field("display_name", &self.display_name)
+            .finish()
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct RegisterResponse {
     pub user: User,
 }
 
-#[derive(Debug, Deserialize, Validate)]
+#[derive(Deserialize, Validate)]
 pub struct LoginReq {
     #[validate(length(min = 1))]
     pub username: String,
@@ -43,12 +65,70 @@ pub struct LoginReq {
     pub password: String,
 }
 
-#[derive(Debug, Serialize)]
+impl fmt::Debug for LoginReq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoginReq")
+            .field("username", &self.username)
+            .field("password", &REDACTED)
+            .finish()
+    }
+}
+
+#[derive(Serialize)]
 pub struct LoginResp {
     pub user: User,
     pub token: String,
 }
 
+impl fmt::Debug for LoginResp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoginResp")
+            .field("user", &self.user)
+            .field("token", &REDACTED)
+            .finish()
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateProfileReq {
+    #[validate(length(min = 1, max = 20))]
+    pub display_name: Option<String>,
+
+    #[validate(url)]
+    pub avatar: Option<String>,
+
+    #[validate(length(max = 100))]
+    pub title: Option<String>,
+
+    #[validate(length(max = 100))]
+    pub status_text: Option<String>,
+
+    #[validate(length(min = 1, max = 64))]
+    pub timezone: Option<String>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct ChangePasswordReq {
+    #[validate(length(min = 1))]
+    pub current_password: String,
+    #[validate(length(min = 8))]
+    pub new_password: String,
+}
+
+impl fmt::Debug for ChangePasswordReq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChangePasswordReq")
+            .field("current_password", &REDACTED)
+            .field("new_password", &REDACTED)
+            .finish()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeactivateResp {
+    pub user: User,
+}
+
 impl From<UserDao> for User {
     fn from(user: UserDao) -> Self {
         Self {
@@ -56,7 +136,13 @@ impl From<UserDao> for User {
             username: user.username,
             avatar_url: user.avatar_url,
             display_name: user.display_name,
+            title: user.title,
+            status_text: user.status_text,
+            timezone: user.timezone,
             is_active: user.is_active,
+            is_admin: user.is_admin,
+            must_reset_password: user.must_reset_password,
+            is_bot: user.is_bot,
             created_at: user.created_at,
             updated_at: user.updated_at,
         }
@@ -0,0 +1,212 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    dto::message::{Attachment, Message},
+    models::webhook::{
+        DeliveryAttempt, IncomingWebhook as IncomingDao, OutgoingWebhook,
+        WebhookDelivery as DeliveryDao,
+    },
+    telemetry::REDACTED,
+};
+
+/// The event types a webhook can subscribe to, as sent in `X-Slac-Event`.
+pub const WEBHOOK_EVENT_TYPES: [&str; 5] = [
+    "message_created",
+    "message_edited",
+    "message_deleted",
+    "member_joined",
+    "member_left",
+];
+
+/// The channel activity posted to outgoing webhooks.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum WebhookEvent {
+    MessageCreated { message: Message },
+    MessageEdited { message: Message },
+    MessageDeleted { message: Message },
+    MemberJoined { user_id: i64 },
+    MemberLeft { user_id: i64 },
+}
+
+impl WebhookEvent {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            WebhookEvent::MessageCreated { .. } => "message_created",
+            WebhookEvent::MessageEdited { .. } => "message_edited",
+            WebhookEvent::MessageDeleted { .. } => "message_deleted",
+            WebhookEvent::MemberJoined { .. } => "member_joined",
+            WebhookEvent::MemberLeft { .. } => "member_left",
+        }
+    }
+}
+
+/// The JSON body of a delivery: `{"event": .., "data": .., "channel_id": .., "occurred_at": ..}`.
+#[derive(Debug, Serialize)]
+pub struct WebhookPayload<'a> {
+    #[serde(flatten)]
+    pub event: &'a WebhookEvent,
+    pub channel_id: i64,
+    pub occurred_at: DateTime<Utc>,
+}
+
+// The secret is only ever returned once, by `CreateWebhookResp`.
+#[derive(Debug, Serialize)]
+pub struct Webhook {
+    pub id: i64,
+    pub channel_id: i64,
+    pub creator_id: i64,
+    pub url: String,
+    pub event_types: Vec<String>,
+    pub is_active: bool,
+    pub consecutive_failures: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<OutgoingWebhook> for Webhook {
+    fn from(hook: OutgoingWebhook) -> Self {
+        Self {
+            id: hook.id,
+            channel_id: hook.channel_id,
+            creator_id: hook.creator_id,
+            url: hook.url,
+            event_types: hook.event_types,
+            is_active: hook.is_active,
+            consecutive_failures: hook.consecutive_failures,
+            created_at: hook.created_at,
+            updated_at: hook.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateWebhookReq {
+    #[validate(url, length(max = 2048))]
+    pub url: String,
+    #[validate(length(min = 1))]
+    pub event_types: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct CreateWebhookResp {
+    pub webhook: Webhook,
+    /// Signs every delivery, see `webhook::sign`.
+    pub secret: String,
+}
+
+impl fmt::Debug for CreateWebhookResp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CreateWebhookResp")
+            .field("webhook", &self.webhook)
+            .field("secret", &REDACTED)
+            .finish()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListWebhooksResp {
+    pub webhooks: Vec<Webhook>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebhookDelivery {
+    #[serde(flatten)]
+    pub delivery: DeliveryDao,
+    pub log: Vec<DeliveryAttempt>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ListDeliveriesReq {
+    #[serde(default)]
+    #[validate(range(min = 0))]
+    pub offset: i64,
+    #[serde(default = "default_limit")]
+    #[validate(range(min = 1, max = 100))]
+    pub limit: i64,
+}
+
+fn default_limit() -> i64 {
+    20
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListDeliveriesResp {
+    pub deliveries: Vec<WebhookDelivery>,
+    pub has_more: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IncomingWebhook {
+    pub id: i64,
+    pub channel_id: i64,
+    pub creator_id: i64,
+    pub bot_user_id: i64,
+    pub name: String,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<IncomingDao> for IncomingWebhook {
+    fn from(hook: IncomingDao) -> Self {
+        Self {
+            id: hook.id,
+            channel_id: hook.channel_id,
+            creator_id: hook.creator_id,
+            bot_user_id: hook.bot_user_id,
+            name: hook.name,
+            last_used_at: hook.last_used_at,
+            created_at: hook.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateIncomingWebhookReq {
+    #[validate(length(min = 1, max = 80))]
+    pub name: String,
+    #[validate(url)]
+    pub avatar_url: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct CreateIncomingWebhookResp {
+    pub webhook: IncomingWebhook,
+    /// Anyone holding the token can post to the channel; it cannot be shown again.
+    pub token: String,
+    pub path: String,
+}
+
+impl fmt::Debug for CreateIncomingWebhookResp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CreateIncomingWebhookResp")
+            .field("webhook", &self.webhook)
+            .field("token", &REDACTED)
+            .field("path", &REDACTED)
+            .finish()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListIncomingWebhooksResp {
+    pub webhooks: Vec<IncomingWebhook>,
+}
+
+/// What an integration posts to `/hooks/{token}`.
+#[derive(Debug, Deserialize, Validate)]
+pub struct IncomingWebhookReq {
+    #[serde(default)]
+    #[validate(length(max = 4000))]
+    pub text: String,
+    #[validate(length(min = 1, max = 80))]
+    pub username: Option<String>,
+    #[validate(url)]
+    pub avatar_url: Option<String>,
+    #[serde(default)]
+    #[validate(length(max = 10), nested)]
+    pub attachments: Vec<Attachment>,
+}
@@ -1,55 +1,297 @@
+use std::borrow::Cow;
+use tracing::error;
+
 use axum::Json;
-use axum::http::StatusCode;
+use axum::http::{StatusCode, header};
 use axum::response::{IntoResponse, Response};
 use serde::Serialize;
+use sqlx::{error::ErrorKind, postgres::PgDatabaseError};
 use thiserror::Error;
+use validator::{ValidationErrors, ValidationErrorsKind};
+
+use crate::request_id;
 
 #[derive(Debug, Error)]
 pub enum AppError {
     #[error("sql error: {0}")]
-    SqlxError(#[from] sqlx::Error),
+    SqlxError(sqlx::Error),
     #[error("{0} not found")]
     NotFound(String),
     #[error("{0}")]
     InvalidArgument(String),
 
+    #[error("invalid request: {}", field_names(.0))]
+    Validation(Vec<FieldError>),
+
     #[error("{0}")]
     AlreadyExists(String),
 
+    // a foreign key points at a row that does not exist.
+    #[error("{0}")]
+    InvalidReference(String),
+
     #[error("{0}")]
     Unauthorized(String),
 
+    #[error("{0}")]
+    PermissionDenied(String),
+
+    // how many seconds to wait before retrying.
+    #[error("too many requests, retry in {0}s")]
+    RateLimited(u64),
+
+    // the server can't take the request right now, e.g. while shutting down.
+    #[error("{0}")]
+    Unavailable(String),
+
     #[error("generate token failed: {0}")]
     GenerateTokenError(#[from] jwt_simple::Error),
 
     #[error("failed to generate hash: {0}")]
     PasswordHashError(#[from] argon2::password_hash::Error),
+
+    #[error("encode metrics failed: {0}")]
+    MetricsError(#[from] prometheus::Error),
+}
+
+/// Why one field of a request was rejected.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct FieldError {
+    pub field: String,
+    pub code: String,
+    pub message: String,
+}
+
+fn field_names(errors: &[FieldError]) -> String {
+    errors
+        .iter()
+        .map(|e| e.field.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl AppError {
+    /// The stable, machine-readable code clients can match on.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::NotFound(_) => "not_found",
+            AppError::InvalidArgument(_) => "invalid_argument",
+            AppError::Validation(_) => "validation_failed",
+            AppError::AlreadyExists(_) => "already_exists",
+            AppError::InvalidReference(_) => "invalid_reference",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::PermissionDenied(_) => "permission_denied",
+            AppError::RateLimited(_) => "rate_limited",
+            AppError::Unavailable(_) => "unavailable",
+            AppError::SqlxError(_)
+            | AppError::GenerateTokenError(_)
+            | AppError::PasswordHashError(_)
+            | AppError::MetricsError(_) => "internal",
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::InvalidArgument(_)
+            | AppError::Validation(_)
+            | AppError::InvalidReference(_) => StatusCode::BAD_REQUEST,
+            AppError::AlreadyExists(_) => StatusCode::CONFLICT,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::PermissionDenied(_) => StatusCode::FORBIDDEN,
+            AppError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            AppError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::SqlxError(_)
+            | AppError::GenerateTokenError(_)
+            | AppError::PasswordHashError(_)
+            | AppError::MetricsError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// What a client may be told; internal errors may carry SQL or key material.
+    pub fn public_message(&self) -> String {
+        if self.code() == "internal" {
+            "internal server error".to_string()
+        } else {
+            self.to_string()
+        }
+    }
+}
+
+// Constraint violations are the client's doing, so they get a 4xx instead of a 500.
+impl From<sqlx::Error> for AppError {
+    fn from(err: sqlx::Error) -> Self {
+        let sqlx::Error::Database(db_err) = &err else {
+            return AppError::SqlxError(err);
+        };
+
+        // Postgres names the offending columns in its detail, e.g. "Key (ch_name)=(x) ...".
+        let columns = db_err
+            .try_downcast_ref::<PgDatabaseError>()
+            .and_then(|e| e.detail())
+            .and_then(|d| d.strip_prefix("Key ("))
+            .and_then(|d| d.split_once(")="))
+            .map(|(columns, _)| columns.to_string());
+        let target = columns
+            .or_else(|| db_err.constraint().map(|c| c.to_string()))
+            .unwrap_or_else(|| "value".to_string());
+
+        match db_err.kind() {
+            ErrorKind::UniqueViolation => {
+                AppError::AlreadyExists(format!("{} already exists", target))
+            }
+            ErrorKind::ForeignKeyViolation => {
+                AppError::InvalidReference(format!("{} refers to a missing record", target))
+            }
+            ErrorKind::CheckViolation | ErrorKind::NotNullViolation => {
+                AppError::InvalidArgument(format!("{} is not allowed", target))
+            }
+            _ => AppError::SqlxError(err),
+        }
+    }
+}
+
+impl From<ValidationErrors> for AppError {
+    fn from(errors: ValidationErrors) -> Self {
+        let mut fields = vec![];
+        collect_field_errors("", &errors, &mut fields);
+        fields.sort_by(|a, b| a.field.cmp(&b.field));
+        AppError::Validation(fields)
+    }
+}
+
+// Nested structs and lists get dotted paths like `attachments[0].title`.
+fn collect_field_errors(prefix: &str, errors: &ValidationErrors, out: &mut Vec<FieldError>) {
+    for (field, kind) in errors.errors() {
+        let path = if prefix.is_empty() {
+            field.to_string()
+        } else {
+            format!("{}.{}", prefix, field)
+        };
+        match kind {
+            ValidationErrorsKind::Field(errs) => out.extend(errs.iter().map(|e| {
+                FieldError {
+                    field: path.clone(),
+                    code: e.code.to_string(),
+                    message: e
+                        .message
+                        .as_ref()
+                        .map_or_else(|| describe(&e.code, &e.params), Cow::to_string),
+                }
+            })),
+            ValidationErrorsKind::Struct(inner) => collect_field_errors(&path, inner, out),
+            ValidationErrorsKind::List(items) => {
+                for (i, inner) in items {
+                    collect_field_errors(&format!("{}[{}]", path, i), inner, out);
+                }
+            }
+        }
+    }
+}
+
+fn describe(
+    code: &str,
+    params: &std::collections::HashMap<Cow<'static, str>, serde_json::Value>,
+) -> String {
+    let (min, max) = (params.get("min"), params.get("max"));
+    match (code, min, max) {
+        ("length", Some(min), Some(max)) => format!("length must be between {} and {}", min, max),
+        ("length", Some(min), None) => format!("length must be at least {}", min),
+        ("length", None, Some(max)) => format!("length must be at most {}", max),
+        ("range", Some(min), Some(max)) => format!("must be between {} and {}", min, max),
+        ("range", Some(min), None) => format!("must be at least {}", min),
+        ("range", None, Some(max)) => format!("must be at most {}", max),
+        _ => format!("is not a valid {}", code),
+    }
 }
 
 #[derive(Serialize)]
 struct ErrorResponse {
+    code: &'static str,
     error: String,
-    code: u16,
+    status: u16,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    details: Vec<FieldError>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let status_code = match self {
-            AppError::NotFound(_) => StatusCode::NOT_FOUND,
-            AppError::SqlxError(_) => StatusCode::INTERNAL_SERVER_ERROR,
-            AppError::PasswordHashError(_) => StatusCode::INTERNAL_SERVER_ERROR,
-            AppError::InvalidArgument(_) => StatusCode::BAD_REQUEST,
-            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
-            AppError::GenerateTokenError(_) => StatusCode::INTERNAL_SERVER_ERROR,
-            AppError::AlreadyExists(_) => StatusCode::CONFLICT,
-        };
+        let status_code = self.status();
+
+        // clients get a request id to quote, while the details stay in the server log.
+        let request_id = status_code
+            .is_server_error()
+            .then(request_id::current)
+            .flatten();
+        if status_code.is_server_error() {
+            error!(
+                request_id = request_id.as_deref().unwrap_or("-"),
+                error = %self,
+                "request failed"
+            );
+        }
 
-        // Create a JSON error response
         let error_response = ErrorResponse {
-            error: format!("{:?}", self),
-            code: status_code.as_u16(),
+            code: self.code(),
+            error: self.public_message(),
+            status: status_code.as_u16(),
+            details: match &self {
+                AppError::Validation(fields) => fields.clone(),
+                _ => vec![],
+            },
+            request_id,
+        };
+
+        let mut resp = (status_code, Json(error_response)).into_response();
+        if let AppError::RateLimited(secs) = self {
+            resp.headers_mut()
+                .insert(header::RETRY_AFTER, header::HeaderValue::from(secs));
+        }
+        resp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use validator::Validate;
+
+    #[derive(Validate)]
+    struct Req {
+        #[validate(length(min = 1, max = 3))]
+        name: String,
+        #[validate(range(min = 1))]
+        limit: i64,
+    }
+
+    #[test]
+    fn test_validation_details() {
+        let err = AppError::from(
+            Req {
+                name: "".to_string(),
+                limit: 0,
+            }
+            .validate()
+            .unwrap_err(),
+        );
+
+        assert_eq!(err.code(), "validation_failed");
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(err.to_string(), "invalid request: limit, name");
+        let AppError::Validation(fields) = err else {
+            panic!("not a validation error");
         };
+        assert_eq!(fields[0].message, "must be at least 1");
+        assert_eq!(fields[1].code, "length");
+        assert_eq!(fields[1].message, "length must be between 1 and 3");
+    }
 
-        (status_code, Json(error_response)).into_response()
+    #[test]
+    fn test_internal_errors_are_hidden() {
+        let err = AppError::from(sqlx::Error::PoolTimedOut);
+        assert_eq!(err.code(), "internal");
+        assert_eq!(err.status(), StatusCode::INTERNAL_SERVER_ERROR);
     }
 }
@@ -0,0 +1,88 @@
+use axum::{
+    Json,
+    extract::{FromRequest, FromRequestParts, Query, Request},
+    http::request::Parts,
+};
+use serde::de::DeserializeOwned;
+use validator::Validate;
+
+use crate::errors::AppError;
+
+/// A JSON body that has passed its `#[validate(...)]` rules before the handler runs.
+#[derive(Debug)]
+pub struct ValidatedJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state)
+            .await
+            .map_err(|e| AppError::InvalidArgument(e.body_text()))?;
+        value.validate()?;
+        Ok(ValidatedJson(value))
+    }
+}
+
+/// Query parameters that have passed their `#[validate(...)]` rules.
+#[derive(Debug)]
+pub struct ValidatedQuery<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for ValidatedQuery<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(value) = Query::<T>::from_request_parts(parts, state)
+            .await
+            .map_err(|e| AppError::InvalidArgument(e.body_text()))?;
+        value.validate()?;
+        Ok(ValidatedQuery(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dto::channel::CreateChannelRequest;
+    use axum::{body::Body, http::header};
+
+    fn json_request(body: &str) -> Request {
+        Request::builder()
+            .method("POST")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_validated_json() {
+        let req = json_request(
+            r#"{"ch_name": "rust-lang", "ch_desc": "Let's learn rust", "is_private": false}"#,
+        );
+        let ValidatedJson(body) = ValidatedJson::<CreateChannelRequest>::from_request(req, &())
+            .await
+            .unwrap();
+        assert_eq!(body.ch_name, "rust-lang");
+
+        let req = json_request(
+            r#"{"ch_name": "rs", "ch_desc": "Let's learn rust", "is_private": false}"#,
+        );
+        let err = ValidatedJson::<CreateChannelRequest>::from_request(req, &())
+            .await
+            .unwrap_err();
+        assert!(matches!(&err, AppError::Validation(fields) if fields[0].field == "ch_name"));
+
+        let err = ValidatedJson::<CreateChannelRequest>::from_request(json_request("{"), &())
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), "invalid_argument");
+    }
+}
@@ -0,0 +1,151 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::IntoResponse,
+};
+use tracing::{debug, info};
+
+use crate::{
+    auth::extractor::AdminUser,
+    dto::{
+        admin::{AdminUserResp, ListUsersReq},
+        audit::ListAuditEventsReq,
+    },
+    errors::AppError,
+    extract::ValidatedQuery,
+    models::{audit::AuditStore, channel::ChanRepository, user::UserRepository},
+    service::{
+        admin::AdminService,
+        audit::{AuditService, Auditor, ClientInfo},
+    },
+    state::AppState,
+};
+
+pub async fn list_users(
+    State(state): State<AppState>,
+    AdminUser(admin): AdminUser,
+    ValidatedQuery(req): ValidatedQuery<ListUsersReq>,
+) -> Result<impl IntoResponse, AppError> {
+    debug!("admin {} list users: {:?}", admin.id, req);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let admin_service = AdminService::new(&chan_repo, &user_repo);
+
+    let resp = admin_service.list_users(&req).await?;
+    Ok(Json(resp))
+}
+
+pub async fn get_user(
+    State(state): State<AppState>,
+    AdminUser(_): AdminUser,
+    Path(user_id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let admin_service = AdminService::new(&chan_repo, &user_repo);
+
+    let user = admin_service.get_user(user_id).await?;
+    Ok(Json(AdminUserResp { user }))
+}
+
+pub async fn deactivate_user(
+    State(state): State<AppState>,
+    AdminUser(admin): AdminUser,
+    Path(user_id): Path<i64>,
+    client: ClientInfo,
+) -> Result<impl IntoResponse, AppError> {
+    info!("admin {} deactivate user {}", admin.id, user_id);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let audit_store = AuditStore::new(&state.pool);
+    let auditor = Auditor::new(&audit_store, client);
+    let admin_service = AdminService::new(&chan_repo, &user_repo).with_auditor(&auditor);
+
+    let user = admin_service.set_active(admin.id, user_id, false).await?;
+    state.disconnect_user(user.id).await;
+
+    Ok(Json(AdminUserResp { user }))
+}
+
+pub async fn reactivate_user(
+    State(state): State<AppState>,
+    AdminUser(admin): AdminUser,
+    Path(user_id): Path<i64>,
+    client: ClientInfo,
+) -> Result<impl IntoResponse, AppError> {
+    info!("admin {} reactivate user {}", admin.id, user_id);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let audit_store = AuditStore::new(&state.pool);
+    let auditor = Auditor::new(&audit_store, client);
+    let admin_service = AdminService::new(&chan_repo, &user_repo).with_auditor(&auditor);
+
+    let user = admin_service.set_active(admin.id, user_id, true).await?;
+    Ok(Json(AdminUserResp { user }))
+}
+
+pub async fn reset_password(
+    State(state): State<AppState>,
+    AdminUser(admin): AdminUser,
+    Path(user_id): Path<i64>,
+    client: ClientInfo,
+) -> Result<impl IntoResponse, AppError> {
+    info!("admin {} reset password of user {}", admin.id, user_id);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let audit_store = AuditStore::new(&state.pool);
+    let auditor = Auditor::new(&audit_store, client);
+    let admin_service = AdminService::new(&chan_repo, &user_repo).with_auditor(&auditor);
+
+    let resp = admin_service.reset_password(admin.id, user_id).await?;
+    Ok(Json(resp))
+}
+
+pub async fn reset_avatar(
+    State(state): State<AppState>,
+    AdminUser(admin): AdminUser,
+    Path(user_id): Path<i64>,
+    client: ClientInfo,
+) -> Result<impl IntoResponse, AppError> {
+    info!("admin {} reset avatar of user {}", admin.id, user_id);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let audit_store = AuditStore::new(&state.pool);
+    let auditor = Auditor::new(&audit_store, client);
+    let admin_service = AdminService::new(&chan_repo, &user_repo).with_auditor(&auditor);
+
+    let user = admin_service.reset_avatar(admin.id, user_id).await?;
+    Ok(Json(AdminUserResp { user }))
+}
+
+pub async fn list_user_memberships(
+    State(state): State<AppState>,
+    AdminUser(_): AdminUser,
+    Path(user_id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let admin_service = AdminService::new(&chan_repo, &user_repo);
+
+    let resp = admin_service.list_memberships(user_id).await?;
+    Ok(Json(resp))
+}
+
+pub async fn list_audit_events(
+    State(state): State<AppState>,
+    AdminUser(admin): AdminUser,
+    ValidatedQuery(req): ValidatedQuery<ListAuditEventsReq>,
+) -> Result<impl IntoResponse, AppError> {
+    debug!("admin {} list audit events: {:?}", admin.id, req);
+
+    let audit_store = AuditStore::new(&state.pool);
+    let audit_service = AuditService::new(&audit_store);
+
+    let resp = audit_service.list_events(&req).await?;
+    Ok(Json(resp))
+}
@@ -0,0 +1,133 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use tracing::{debug, info};
+
+use crate::{
+    auth::extractor::CurrentUser,
+    dto::bot::{CreateApiTokenReq, CreateBotReq},
+    errors::AppError,
+    extract::ValidatedJson,
+    models::{api_token::ApiTokenStore, user::UserRepository},
+    service::bot::BotService,
+    state::AppState,
+};
+
+pub async fn create_bot(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    ValidatedJson(req): ValidatedJson<CreateBotReq>,
+) -> Result<impl IntoResponse, AppError> {
+    debug!("user {} create bot: {:?}", user.id, req);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let token_store = ApiTokenStore::new(&state.pool);
+    let bot_service = BotService::new(&user_repo, &token_store);
+
+    let resp = bot_service.create_bot(&user, &req).await?;
+    Ok(Json(resp))
+}
+
+pub async fn list_bots(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+) -> Result<impl IntoResponse, AppError> {
+    info!("user {} list bots", user.id);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let token_store = ApiTokenStore::new(&state.pool);
+    let bot_service = BotService::new(&user_repo, &token_store);
+
+    let resp = bot_service.list_bots(&user).await?;
+    Ok(Json(resp))
+}
+
+pub async fn delete_bot(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(bot_id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("user {} delete bot {}", user.id, bot_id);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let token_store = ApiTokenStore::new(&state.pool);
+    let bot_service = BotService::new(&user_repo, &token_store);
+
+    bot_service.delete_bot(&user, bot_id).await?;
+    // the bot may hold open sockets.
+    state.disconnect_user(bot_id).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn create_token(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(bot_id): Path<i64>,
+    ValidatedJson(req): ValidatedJson<CreateApiTokenReq>,
+) -> Result<impl IntoResponse, AppError> {
+    debug!(
+        "user {} create token for bot {}: {:?}",
+        user.id, bot_id, req
+    );
+
+    let user_repo = UserRepository::new(&state.pool);
+    let token_store = ApiTokenStore::new(&state.pool);
+    let bot_service = BotService::new(&user_repo, &token_store);
+
+    let resp = bot_service.create_token(&user, bot_id, &req).await?;
+    Ok(Json(resp))
+}
+
+pub async fn list_tokens(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(bot_id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("user {} list tokens of bot {}", user.id, bot_id);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let token_store = ApiTokenStore::new(&state.pool);
+    let bot_service = BotService::new(&user_repo, &token_store);
+
+    let resp = bot_service.list_tokens(&user, bot_id).await?;
+    Ok(Json(resp))
+}
+
+pub async fn rotate_token(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path((bot_id, token_id)): Path<(i64, i64)>,
+) -> Result<impl IntoResponse, AppError> {
+    info!(
+        "user {} rotate token {} of bot {}",
+        user.id, token_id, bot_id
+    );
+
+    let user_repo = UserRepository::new(&state.pool);
+    let token_store = ApiTokenStore::new(&state.pool);
+    let bot_service = BotService::new(&user_repo, &token_store);
+
+    let resp = bot_service.rotate_token(&user, bot_id, token_id).await?;
+    Ok(Json(resp))
+}
+
+pub async fn revoke_token(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path((bot_id, token_id)): Path<(i64, i64)>,
+) -> Result<impl IntoResponse, AppError> {
+    info!(
+        "user {} revoke token {} of bot {}",
+        user.id, token_id, bot_id
+    );
+
+    let user_repo = UserRepository::new(&state.pool);
+    let token_store = ApiTokenStore::new(&state.pool);
+    let bot_service = BotService::new(&user_repo, &token_store);
+
+    bot_service.revoke_token(&user, bot_id, token_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
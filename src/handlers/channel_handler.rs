@@ -3,44 +3,68 @@ use axum::{
     extract::{Path, State},
     response::IntoResponse,
 };
+use tracing::{debug, info};
 
 use crate::{
-    dto::channel::{CreateChannelRequest, JoinChanReq, ListChanReq, ListUserChannels},
+    auth::extractor::CurrentUser,
+    dto::{
+        channel::{
+            AddMemberReq, ChangeMemberRoleReq, CreateChannelRequest, ListChanReq, ListUserChannels,
+            ListUserChannelsQuery, SearchChanReq, UpdateChannelReq,
+        },
+        event::ServerEvent,
+        webhook::WebhookEvent,
+    },
     errors::AppError,
-    models::{channel::ChanRepository, user::UserRepository},
-    service::channel::ChannelService,
+    extract::{ValidatedJson, ValidatedQuery},
+    handlers::dispatch_webhook,
+    models::{
+        audit::AuditStore, channel::ChanRepository, message::MessageStore, user::UserRepository,
+    },
+    service::{
+        audit::{Auditor, ClientInfo},
+        channel::ChannelService,
+    },
     state::AppState,
 };
 
 pub async fn create_channel(
     State(state): State<AppState>,
-    Json(req): Json<CreateChannelRequest>,
+    CurrentUser(user): CurrentUser,
+    client: ClientInfo,
+    ValidatedJson(req): ValidatedJson<CreateChannelRequest>,
 ) -> Result<impl IntoResponse, AppError> {
-    println!("create channel: {:?}", req);
+    debug!("user {} create channel: {:?}", user.id, req);
 
     let user_repo = UserRepository::new(&state.pool);
 
     let chan_repo = ChanRepository::new(&state.pool);
-    let chan_service = ChannelService::new(&chan_repo, &user_repo);
+    let msg_store = MessageStore::new(&state.pool);
+    let audit_store = AuditStore::new(&state.pool);
+    let auditor = Auditor::new(&audit_store, client);
+    let chan_service =
+        ChannelService::new(&chan_repo, &user_repo, &msg_store).with_auditor(&auditor);
 
-    let resp = chan_service.create_channel(&req).await?;
-    println!("created channel: {:?}", resp.channel);
+    let resp = chan_service.create_channel(user.id, &req).await?;
+    debug!("created channel: {:?}", resp.channel);
+    state.send_system_message(&resp.system_msg).await?;
     Ok(Json(resp))
 }
 
 pub async fn list_channels(
     State(state): State<AppState>,
-    Json(req): Json<ListChanReq>,
+    ValidatedJson(req): ValidatedJson<ListChanReq>,
 ) -> Result<impl IntoResponse, AppError> {
-    println!("list channel req: {:?}", req);
+    debug!("list channel req: {:?}", req);
 
     let user_repo = UserRepository::new(&state.pool);
 
     let chan_repo = ChanRepository::new(&state.pool);
-    let chan_service = ChannelService::new(&chan_repo, &user_repo);
+    let msg_store = MessageStore::new(&state.pool);
+    let chan_service = ChannelService::new(&chan_repo, &user_repo, &msg_store);
 
     let resp = chan_service.list_channels(&req).await?;
-    println!("created channel: {:?}", resp.channels);
+    debug!("created channel: {:?}", resp.channels);
     Ok(Json(resp))
 }
 
@@ -48,45 +72,65 @@ pub async fn get_channel(
     State(state): State<AppState>,
     Path(channel_id): Path<i64>,
 ) -> Result<impl IntoResponse, AppError> {
-    println!("get channel req: {}", channel_id);
+    info!("get channel req: {}", channel_id);
 
     let user_repo = UserRepository::new(&state.pool);
 
     let chan_repo = ChanRepository::new(&state.pool);
-    let chan_service = ChannelService::new(&chan_repo, &user_repo);
+    let msg_store = MessageStore::new(&state.pool);
+    let chan_service = ChannelService::new(&chan_repo, &user_repo, &msg_store);
 
     let resp = chan_service.get_channel(channel_id).await?;
-    println!("get channel response: {:?}", resp);
+    debug!("get channel response: {:?}", resp);
     Ok(Json(resp))
 }
 
 pub async fn join_channel(
     State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
     Path(channel_id): Path<i64>,
-    Json(req): Json<JoinChanReq>,
+    client: ClientInfo,
 ) -> Result<impl IntoResponse, AppError> {
-    println!("join channel req: {:?}", req);
+    debug!("user {} join channel {}", user.id, channel_id);
 
     let user_repo = UserRepository::new(&state.pool);
     let chan_repo = ChanRepository::new(&state.pool);
-    let chan_service = ChannelService::new(&chan_repo, &user_repo);
+    let msg_store = MessageStore::new(&state.pool);
+    let audit_store = AuditStore::new(&state.pool);
+    let auditor = Auditor::new(&audit_store, client);
+    let chan_service =
+        ChannelService::new(&chan_repo, &user_repo, &msg_store).with_auditor(&auditor);
 
-    let resp = chan_service.join_channel(req.user_id, channel_id).await?;
-    println!("join channel response: {:?}", resp);
+    let resp = chan_service.join_channel(user.id, channel_id).await?;
+    debug!("join channel response: {:?}", resp);
+    state.send_system_message(&resp.system_msg).await?;
+    let event = WebhookEvent::MemberJoined { user_id: user.id };
+    dispatch_webhook(&state, channel_id, event).await;
     Ok(Json(resp))
 }
 
 pub async fn leave_channel(
     State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
     Path(channel_id): Path<i64>,
-    Json(req): Json<JoinChanReq>,
+    client: ClientInfo,
 ) -> Result<impl IntoResponse, AppError> {
+    debug!("user {} leave channel {}", user.id, channel_id);
     let user_repo = UserRepository::new(&state.pool);
     let chan_repo = ChanRepository::new(&state.pool);
-    let chan_service = ChannelService::new(&chan_repo, &user_repo);
+    let msg_store = MessageStore::new(&state.pool);
+    let audit_store = AuditStore::new(&state.pool);
+    let auditor = Auditor::new(&audit_store, client);
+    let chan_service =
+        ChannelService::new(&chan_repo, &user_repo, &msg_store).with_auditor(&auditor);
 
-    let resp = chan_service.leave_channel(req.user_id, channel_id).await?;
-    println!("leave channel response: {:?}", resp);
+    let resp = chan_service.leave_channel(user.id, channel_id).await?;
+    debug!("leave channel response: {:?}", resp);
+    if let Some(system_msg) = &resp.system_msg {
+        state.send_system_message(system_msg).await?;
+        let event = WebhookEvent::MemberLeft { user_id: user.id };
+        dispatch_webhook(&state, channel_id, event).await;
+    }
     Ok(Json(resp))
 }
 
@@ -94,34 +138,235 @@ pub async fn list_channel_memebers(
     State(state): State<AppState>,
     Path(channel_id): Path<i64>,
 ) -> Result<impl IntoResponse, AppError> {
-    println!("list channel {} members", channel_id);
+    info!("list channel {} members", channel_id);
 
     let user_repo = UserRepository::new(&state.pool);
     let chan_repo = ChanRepository::new(&state.pool);
-    let chan_service = ChannelService::new(&chan_repo, &user_repo);
+    let msg_store = MessageStore::new(&state.pool);
+    let chan_service = ChannelService::new(&chan_repo, &user_repo, &msg_store);
 
     let resp = chan_service.list_channel_members(channel_id).await?;
-    println!("list members response: {:?}", resp);
+    debug!("list members response: {:?}", resp);
     Ok(Json(resp))
 }
 
 pub async fn list_user_channels(
     State(state): State<AppState>,
     Path(user_id): Path<i64>,
+    ValidatedQuery(query): ValidatedQuery<ListUserChannelsQuery>,
 ) -> Result<impl IntoResponse, AppError> {
-    println!("list user: {} channels", user_id);
+    info!("list user: {} channels", user_id);
 
     let user_repo = UserRepository::new(&state.pool);
     let chan_repo = ChanRepository::new(&state.pool);
-    let chan_service = ChannelService::new(&chan_repo, &user_repo);
+    let msg_store = MessageStore::new(&state.pool);
+    let chan_service = ChannelService::new(&chan_repo, &user_repo, &msg_store);
 
     let resp = chan_service
         .list_user_channels(&ListUserChannels {
             user_id,
             offset: 0,
             limit: 100,
+            include_archived: query.include_archived,
         })
         .await?;
-    println!("list user channels response: {:?}", resp);
+    debug!("list user channels response: {:?}", resp);
+    Ok(Json(resp))
+}
+
+pub async fn change_member_role(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path((channel_id, member_id)): Path<(i64, i64)>,
+    client: ClientInfo,
+    ValidatedJson(req): ValidatedJson<ChangeMemberRoleReq>,
+) -> Result<impl IntoResponse, AppError> {
+    debug!(
+        "user {} change member {} role in channel {}: {:?}",
+        user.id, member_id, channel_id, req
+    );
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let audit_store = AuditStore::new(&state.pool);
+    let auditor = Auditor::new(&audit_store, client);
+    let chan_service =
+        ChannelService::new(&chan_repo, &user_repo, &msg_store).with_auditor(&auditor);
+
+    let resp = chan_service
+        .change_member_role(user.id, channel_id, member_id, &req.member_role)
+        .await?;
+    Ok(Json(resp))
+}
+
+pub async fn search_channels(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    ValidatedQuery(req): ValidatedQuery<SearchChanReq>,
+) -> Result<impl IntoResponse, AppError> {
+    debug!("user {} search channels: {:?}", user.id, req);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let chan_service = ChannelService::new(&chan_repo, &user_repo, &msg_store);
+
+    let resp = chan_service.search_channels(user.id, &req).await?;
+    Ok(Json(resp))
+}
+
+pub async fn archive_channel(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(channel_id): Path<i64>,
+    client: ClientInfo,
+) -> Result<impl IntoResponse, AppError> {
+    set_archived(state, user.id, channel_id, client, true).await
+}
+
+pub async fn unarchive_channel(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(channel_id): Path<i64>,
+    client: ClientInfo,
+) -> Result<impl IntoResponse, AppError> {
+    set_archived(state, user.id, channel_id, client, false).await
+}
+
+async fn set_archived(
+    state: AppState,
+    user_id: i64,
+    channel_id: i64,
+    client: ClientInfo,
+    archived: bool,
+) -> Result<impl IntoResponse, AppError> {
+    info!(
+        "user {} set channel {} archived: {}",
+        user_id, channel_id, archived
+    );
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let audit_store = AuditStore::new(&state.pool);
+    let auditor = Auditor::new(&audit_store, client);
+    let chan_service =
+        ChannelService::new(&chan_repo, &user_repo, &msg_store).with_auditor(&auditor);
+
+    let resp = chan_service
+        .set_archived(user_id, channel_id, archived)
+        .await?;
+
+    let state_event = if archived {
+        ServerEvent::ChannelArchived {
+            channel: resp.channel.clone(),
+        }
+    } else {
+        ServerEvent::ChannelUnarchived {
+            channel: resp.channel.clone(),
+        }
+    };
+    state.send_to_channel(channel_id, &state_event).await?;
+    state.send_system_message(&resp.system_msg).await?;
+
+    Ok(Json(resp))
+}
+
+pub async fn update_channel(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(channel_id): Path<i64>,
+    client: ClientInfo,
+    ValidatedJson(req): ValidatedJson<UpdateChannelReq>,
+) -> Result<impl IntoResponse, AppError> {
+    debug!("user {} update channel {}: {:?}", user.id, channel_id, req);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let audit_store = AuditStore::new(&state.pool);
+    let auditor = Auditor::new(&audit_store, client);
+    let chan_service =
+        ChannelService::new(&chan_repo, &user_repo, &msg_store).with_auditor(&auditor);
+
+    let resp = chan_service
+        .update_channel(user.id, channel_id, &req)
+        .await?;
+
+    if !resp.system_msgs.is_empty() {
+        let event = ServerEvent::ChannelUpdated {
+            channel: resp.channel.clone(),
+        };
+        state.send_to_channel(channel_id, &event).await?;
+    }
+    for system_msg in &resp.system_msgs {
+        state.send_system_message(system_msg).await?;
+    }
+
+    Ok(Json(resp))
+}
+
+pub async fn add_member(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(channel_id): Path<i64>,
+    client: ClientInfo,
+    ValidatedJson(req): ValidatedJson<AddMemberReq>,
+) -> Result<impl IntoResponse, AppError> {
+    info!(
+        "user {} add member {} to channel {}",
+        user.id, req.user_id, channel_id
+    );
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let audit_store = AuditStore::new(&state.pool);
+    let auditor = Auditor::new(&audit_store, client);
+    let chan_service =
+        ChannelService::new(&chan_repo, &user_repo, &msg_store).with_auditor(&auditor);
+
+    let resp = chan_service
+        .add_member(user.id, channel_id, req.user_id)
+        .await?;
+    state.send_system_message(&resp.system_msg).await?;
+    let event = WebhookEvent::MemberJoined { user_id: user.id };
+    dispatch_webhook(&state, channel_id, event).await;
+    Ok(Json(resp))
+}
+
+pub async fn remove_member(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path((channel_id, member_id)): Path<(i64, i64)>,
+    client: ClientInfo,
+) -> Result<impl IntoResponse, AppError> {
+    info!(
+        "user {} remove member {} from channel {}",
+        user.id, member_id, channel_id
+    );
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let audit_store = AuditStore::new(&state.pool);
+    let auditor = Auditor::new(&audit_store, client);
+    let chan_service =
+        ChannelService::new(&chan_repo, &user_repo, &msg_store).with_auditor(&auditor);
+
+    let resp = chan_service
+        .remove_member(user.id, channel_id, member_id)
+        .await?;
+    if let Some(system_msg) = &resp.system_msg {
+        state.send_system_message(system_msg).await?;
+        // the removed member no longer receives channel events, tell them directly.
+        let event = ServerEvent::SystemMessage {
+            message: system_msg.clone(),
+        };
+        state.send_to_users(&[member_id], &event).await;
+        let event = WebhookEvent::MemberLeft { user_id: member_id };
+        dispatch_webhook(&state, channel_id, event).await;
+    }
     Ok(Json(resp))
 }
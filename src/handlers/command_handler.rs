@@ -0,0 +1,100 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use tracing::{debug, info};
+
+use crate::{
+    auth::extractor::{AdminUser, CurrentUser},
+    dto::command::RegisterCommandReq,
+    errors::AppError,
+    extract::ValidatedJson,
+    models::{
+        channel::ChanRepository, command::SlashCommandStore, message::MessageStore,
+        reminder::ReminderStore, user::UserRepository,
+    },
+    service::command::CommandService,
+    state::AppState,
+};
+
+pub async fn list_commands(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+) -> Result<impl IntoResponse, AppError> {
+    info!("user {} list commands", user.id);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let command_store = SlashCommandStore::new(&state.pool);
+    let reminder_store = ReminderStore::new(&state.pool);
+    let command_service = CommandService::new(
+        &chan_repo,
+        &user_repo,
+        &msg_store,
+        &command_store,
+        &reminder_store,
+        &state.ek,
+        &state.dk,
+        &state.webhook_sender,
+    );
+
+    let resp = command_service.list_commands().await?;
+    Ok(Json(resp))
+}
+
+pub async fn register_command(
+    State(state): State<AppState>,
+    AdminUser(admin): AdminUser,
+    ValidatedJson(req): ValidatedJson<RegisterCommandReq>,
+) -> Result<impl IntoResponse, AppError> {
+    debug!("admin {} register command: {:?}", admin.id, req);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let command_store = SlashCommandStore::new(&state.pool);
+    let reminder_store = ReminderStore::new(&state.pool);
+    let command_service = CommandService::new(
+        &chan_repo,
+        &user_repo,
+        &msg_store,
+        &command_store,
+        &reminder_store,
+        &state.ek,
+        &state.dk,
+        &state.webhook_sender,
+    );
+
+    let resp = command_service.register_command(admin.id, &req).await?;
+    Ok(Json(resp))
+}
+
+pub async fn delete_command(
+    State(state): State<AppState>,
+    AdminUser(admin): AdminUser,
+    Path(command_id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("admin {} delete command {}", admin.id, command_id);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let command_store = SlashCommandStore::new(&state.pool);
+    let reminder_store = ReminderStore::new(&state.pool);
+    let command_service = CommandService::new(
+        &chan_repo,
+        &user_repo,
+        &msg_store,
+        &command_store,
+        &reminder_store,
+        &state.ek,
+        &state.dk,
+        &state.webhook_sender,
+    );
+
+    command_service.delete_command(command_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
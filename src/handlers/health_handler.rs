@@ -0,0 +1,78 @@
+use axum::{Json, extract::State, response::IntoResponse};
+use serde_json::json;
+use sqlx::migrate::Migrator;
+use tracing::warn;
+
+use crate::{errors::AppError, models::health::HealthStore, state::AppState};
+
+// The migrations this build expects; `sqlx migrate run` applies them before a deploy.
+static MIGRATOR: Migrator = sqlx::migrate!();
+
+/// Liveness: the process is up and answering.
+pub async fn healthz() -> Result<impl IntoResponse, AppError> {
+    Ok(Json(json!({ "status": "ok" })))
+}
+
+/// Readiness: the database answers and has every migration this build expects. Fails
+/// once shutdown began, so load balancers stop sending traffic while requests drain.
+pub async fn readyz(State(state): State<AppState>) -> Result<impl IntoResponse, AppError> {
+    if state.is_shutting_down() {
+        return Err(AppError::Unavailable("server is shutting down".to_string()));
+    }
+
+    let health_store = HealthStore::new(&state.pool);
+    if let Err(e) = health_store.ping().await {
+        warn!("readiness database check error: {}", e);
+        return Err(AppError::Unavailable("database unavailable".to_string()));
+    }
+
+    let applied = match health_store.applied_migrations().await {
+        Ok(applied) => applied,
+        Err(e) => {
+            warn!("readiness migration check error: {}", e);
+            return Err(AppError::Unavailable("migrations not applied".to_string()));
+        }
+    };
+    let expected: Vec<i64> = MIGRATOR
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration())
+        .map(|m| m.version)
+        .collect();
+    let pending = pending_migrations(&expected, &applied);
+    if !pending.is_empty() {
+        warn!("readiness pending migrations: {:?}", pending);
+        return Err(AppError::Unavailable("migrations pending".to_string()));
+    }
+
+    Ok(Json(json!({ "status": "ready" })))
+}
+
+fn pending_migrations(expected: &[i64], applied: &[i64]) -> Vec<i64> {
+    expected
+        .iter()
+        .filter(|v| !applied.contains(v))
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_migrations() {
+        let expected: Vec<i64> = MIGRATOR.iter().map(|m| m.version).collect();
+        assert!(!expected.is_empty());
+        assert!(pending_migrations(&expected, &expected).is_empty());
+
+        let applied = &expected[..expected.len() - 1];
+        assert_eq!(
+            pending_migrations(&expected, applied),
+            vec![*expected.last().unwrap()]
+        );
+        // versions the database knows but this build doesn't are fine, e.g. mid-rollback.
+        let mut ahead = expected.clone();
+        ahead.push(i64::MAX);
+        assert!(pending_migrations(&expected, &ahead).is_empty());
+    }
+}
@@ -1,14 +1,31 @@
 use axum::{
     Json, debug_handler,
-    extract::{Path, Query, State},
-    response::IntoResponse,
+    extract::{Path, State},
+    response::{IntoResponse, Response},
 };
+use tracing::{debug, info};
 
 use crate::{
-    dto::message::{ListMessagesReq, ListMessagesResp, Message, SendMessageReq},
+    auth::extractor::CurrentUser,
+    dto::{
+        event::ServerEvent,
+        message::{
+            ListMentionsReq, ListMessagesReq, ListMessagesResp, Message, MessageContentType,
+            PinMessageReq, SendMessageReq, UpdateMessageReq,
+        },
+        webhook::WebhookEvent,
+    },
     errors::AppError,
-    models::{channel::ChanRepository, message::MessageStore, user::UserRepository},
-    service::message::MsgService,
+    extract::{ValidatedJson, ValidatedQuery},
+    handlers::{dispatch_webhook, publish_message, run_command, spawn_unfurl},
+    models::{
+        audit::AuditStore, channel::ChanRepository, message::MessageStore, user::UserRepository,
+    },
+    service::{
+        audit::{Auditor, ClientInfo},
+        command::parse_command,
+        message::MsgService,
+    },
     state::AppState,
 };
 
@@ -16,10 +33,10 @@ use crate::{
 pub async fn list_messages(
     State(state): State<AppState>,
     Path(channel_id): Path<i64>,
-    Query(req): Query<ListMessagesReq>,
+    ValidatedQuery(req): ValidatedQuery<ListMessagesReq>,
 ) -> Result<impl IntoResponse, AppError> {
-    println!("list {} messages", channel_id);
-    println!("list messages req: {:?}", req);
+    info!("list {} messages", channel_id);
+    debug!("list messages req: {:?}", req);
 
     let user_repo = UserRepository::new(&state.pool);
     let chan_repo = ChanRepository::new(&state.pool);
@@ -27,55 +44,204 @@ pub async fn list_messages(
     let msg_service = MsgService::new(&chan_repo, &user_repo, &msg_store);
 
     let messages = msg_service.list_messages(channel_id, &req).await?;
-    let messages1: Vec<Message> = messages.into_iter().map(|v| v.into()).collect();
     let resp = ListMessagesResp {
-        msgs: messages1,
+        msgs: messages,
         has_more: false,
     };
-    println!("list msg resp: {:?}", resp);
+    debug!("list msg resp: {:?}", resp);
     Ok(Json(resp))
 }
 
 pub async fn send_message_to_channel(
     State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
     Path(channel_id): Path<i64>,
-    Json(req): Json<SendMessageReq>,
-) -> Result<impl IntoResponse, AppError> {
-    println!("send messages to {}", channel_id);
-    println!("send message req: {:?}", req);
+    ValidatedJson(mut req): ValidatedJson<SendMessageReq>,
+) -> Result<Response, AppError> {
+    info!("user {} send messages to {}", user.id, channel_id);
+    debug!("send message req: {:?}", req);
+
+    // the token says who is posting, whatever the payload claims.
+    req.sender_id = Some(user.id);
+
+    // a slash command is run instead of being posted.
+    if let (MessageContentType::Text, Some(cmd)) =
+        (&req.content_type, parse_command(&req.text_content))
+    {
+        let resp = run_command(&state, user.id, channel_id, &cmd).await?;
+        return Ok(Json(resp).into_response());
+    }
 
     let user_repo = UserRepository::new(&state.pool);
     let chan_repo = ChanRepository::new(&state.pool);
     let msg_store = MessageStore::new(&state.pool);
     let msg_service = MsgService::new(&chan_repo, &user_repo, &msg_store);
 
-    let msg_dao = msg_service.send_message(channel_id, &req).await?;
-    let resp: Message = msg_dao.into();
-    println!("send msg resp: {:?}", resp);
-    Ok(Json(resp))
+    let resp = msg_service.send_message(channel_id, &req).await?;
+    debug!("send msg resp: {:?}", resp);
+    publish_message(&state, &resp).await?;
+    Ok(Json(resp).into_response())
 }
 
 pub async fn get_message(
     State(state): State<AppState>,
     Path(message_id): Path<i64>,
 ) -> Result<impl IntoResponse, AppError> {
-    println!("get message {}", message_id);
+    info!("get message {}", message_id);
 
     let user_repo = UserRepository::new(&state.pool);
     let chan_repo = ChanRepository::new(&state.pool);
     let msg_store = MessageStore::new(&state.pool);
     let msg_service = MsgService::new(&chan_repo, &user_repo, &msg_store);
 
-    let msg_dao = msg_service.get_message(message_id).await?;
-    if msg_dao.is_none() {
+    let msg = msg_service.get_message(message_id).await?;
+    if msg.is_none() {
         return Err(AppError::NotFound("message not found".to_string()));
     }
 
-    let resp: Message = msg_dao.unwrap().into();
-    println!("get msg resp: {:?}", resp);
+    let resp: Message = msg.unwrap();
+    debug!("get msg resp: {:?}", resp);
+    Ok(Json(resp))
+}
+
+pub async fn update_message(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    client: ClientInfo,
+    ValidatedJson(req): ValidatedJson<UpdateMessageReq>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("user {} update message {}", user.id, req.id);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let audit_store = AuditStore::new(&state.pool);
+    let auditor = Auditor::new(&audit_store, client);
+    let msg_service = MsgService::new(&chan_repo, &user_repo, &msg_store).with_auditor(&auditor);
+
+    let resp = msg_service.edit_message(user.id, &req).await?;
+    spawn_unfurl(&state, &resp);
+    let event = WebhookEvent::MessageEdited {
+        message: resp.clone(),
+    };
+    dispatch_webhook(&state, resp.channel_id, event).await;
+    Ok(Json(resp))
+}
+
+pub async fn delete_message(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(message_id): Path<i64>,
+    client: ClientInfo,
+) -> Result<impl IntoResponse, AppError> {
+    info!("user {} delete message {}", user.id, message_id);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let audit_store = AuditStore::new(&state.pool);
+    let auditor = Auditor::new(&audit_store, client);
+    let msg_service = MsgService::new(&chan_repo, &user_repo, &msg_store).with_auditor(&auditor);
+
+    let msg_dao = msg_service
+        .delete_message(user.id, user.is_admin, message_id)
+        .await?;
+    let resp: Message = msg_dao.into();
+    let event = WebhookEvent::MessageDeleted {
+        message: resp.clone(),
+    };
+    dispatch_webhook(&state, resp.channel_id, event).await;
+    Ok(Json(resp))
+}
+
+pub async fn list_my_mentions(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    ValidatedQuery(req): ValidatedQuery<ListMentionsReq>,
+) -> Result<impl IntoResponse, AppError> {
+    debug!("user {} list mentions: {:?}", user.id, req);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let msg_service = MsgService::new(&chan_repo, &user_repo, &msg_store);
+
+    let resp = msg_service.list_mentions(user.id, &req).await?;
+    Ok(Json(resp))
+}
+
+pub async fn list_pins(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(channel_id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("user {} list channel {} pins", user.id, channel_id);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let msg_service = MsgService::new(&chan_repo, &user_repo, &msg_store);
+
+    let resp = msg_service.list_pins(user.id, channel_id).await?;
+    Ok(Json(resp))
+}
+
+pub async fn pin_message(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(channel_id): Path<i64>,
+    ValidatedJson(req): ValidatedJson<PinMessageReq>,
+) -> Result<impl IntoResponse, AppError> {
+    info!(
+        "user {} pin message {} in channel {}",
+        user.id, req.message_id, channel_id
+    );
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let msg_service = MsgService::new(&chan_repo, &user_repo, &msg_store);
+
+    let resp = msg_service
+        .pin_message(user.id, channel_id, req.message_id)
+        .await?;
+
+    let event = ServerEvent::MessagePinned {
+        channel_id,
+        pin: resp.pin.clone(),
+    };
+    state.send_to_channel(channel_id, &event).await?;
+    state.send_system_message(&resp.system_msg).await?;
+
     Ok(Json(resp))
 }
 
-pub async fn update_message() -> Result<impl IntoResponse, AppError> {
-    Ok("Hello, World")
+pub async fn unpin_message(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path((channel_id, message_id)): Path<(i64, i64)>,
+) -> Result<impl IntoResponse, AppError> {
+    info!(
+        "user {} unpin message {} in channel {}",
+        user.id, message_id, channel_id
+    );
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let msg_service = MsgService::new(&chan_repo, &user_repo, &msg_store);
+
+    let resp = msg_service
+        .unpin_message(user.id, channel_id, message_id)
+        .await?;
+
+    let event = ServerEvent::MessageUnpinned {
+        channel_id,
+        message_id,
+        unpinned_by: user.id,
+    };
+    state.send_to_channel(channel_id, &event).await?;
+    state.send_system_message(&resp.system_msg).await?;
+
+    Ok(Json(resp))
 }
@@ -1,33 +1,57 @@
 use sqlx::{Pool, Postgres};
+use tracing::{debug, info, warn};
 
 use crate::{
     dto::{
         SimpleUser,
         channel::ListChanMembersResp,
-        message::{Message, SendMessageReq},
+        command::CommandResp,
+        event::ServerEvent,
+        message::{Message, MessageContentType, SendMessageReq},
+        webhook::WebhookEvent,
     },
     errors::AppError,
-    models::{channel::ChanRepository, message::MessageStore, user::UserRepository},
-    service::{channel::ChannelService, message::MsgService},
+    models::{
+        channel::ChanRepository, command::SlashCommandStore, message::MessageStore,
+        reminder::ReminderStore, unfurl::UnfurlStore, user::UserRepository, webhook::WebhookStore,
+    },
+    service::{
+        channel::ChannelService,
+        command::{CommandService, ParsedCommand},
+        message::MsgService,
+        webhook::WebhookService,
+    },
+    state::AppState,
+    unfurl::{Unfurler, message_links},
 };
 
+pub mod admin_handler;
+pub mod bot_handler;
 pub mod channel_handler;
+pub mod command_handler;
+pub mod health_handler;
 pub mod message_handler;
+pub mod poll_handler;
+pub mod reminder_handler;
+pub mod saved_handler;
+pub mod scheduled_handler;
 pub mod user_handler;
+pub mod webhook_handler;
 pub mod websocket;
 
 pub async fn list_channel_memebers(
     pool: &Pool<Postgres>,
     channel_id: i64,
 ) -> Result<ListChanMembersResp, AppError> {
-    println!("list channel {} members", channel_id);
+    info!("list channel {} members", channel_id);
 
     let user_repo = UserRepository::new(pool);
     let chan_repo = ChanRepository::new(pool);
-    let chan_service = ChannelService::new(&chan_repo, &user_repo);
+    let msg_store = MessageStore::new(pool);
+    let chan_service = ChannelService::new(&chan_repo, &user_repo, &msg_store);
 
     let resp = chan_service.list_channel_members(channel_id).await?;
-    println!("list members response: {:?}", resp);
+    debug!("list members response: {:?}", resp);
     Ok(resp)
 }
 
@@ -36,17 +60,16 @@ pub async fn send_message_to_channel(
     channel_id: i64,
     req: &SendMessageReq,
 ) -> Result<Message, AppError> {
-    println!("send messages to {}", channel_id);
-    println!("send message req: {:?}", req);
+    info!("send messages to {}", channel_id);
+    debug!("send message req: {:?}", req);
 
     let user_repo = UserRepository::new(pool);
     let chan_repo = ChanRepository::new(pool);
     let msg_store = MessageStore::new(pool);
     let msg_service = MsgService::new(&chan_repo, &user_repo, &msg_store);
 
-    let msg_dao = msg_service.send_message(channel_id, req).await?;
-    let msg: Message = msg_dao.into();
-    println!("msg: {:?}", msg);
+    let msg = msg_service.send_message(channel_id, req).await?;
+    debug!("msg: {:?}", msg);
     Ok(msg)
 }
 
@@ -63,9 +86,158 @@ pub async fn list_simple_users(
             id: u.id,
             display_name: u.display_name.clone(),
             avatar_url: u.avatar_url.clone(),
+            is_bot: u.is_bot,
         })
         .collect();
 
-    println!("list members response: {:?}", simple_users);
+    debug!("list members response: {:?}", simple_users);
     Ok(simple_users)
 }
+
+// Unfurling runs after the message was answered; cards arrive as a `message_updated` event.
+pub fn spawn_unfurl(state: &AppState, msg: &Message) {
+    let urls = message_links(&msg.text_content);
+    // an edit that removed every link still has to clear the old cards.
+    let had_cards = msg
+        .unfurls
+        .as_array()
+        .is_some_and(|cards| !cards.is_empty());
+    if urls.is_empty() && !had_cards {
+        return;
+    }
+
+    let state = state.clone();
+    let msg_id = msg.id;
+    tokio::spawn(async move {
+        if let Err(e) = unfurl_message(&state, msg_id, &urls).await {
+            warn!("unfurl message {} error: {}", msg_id, e);
+        }
+    });
+}
+
+async fn unfurl_message(state: &AppState, msg_id: i64, urls: &[String]) -> Result<(), AppError> {
+    let unfurl_store = UnfurlStore::new(&state.pool);
+    let unfurler = Unfurler::new(state.fetcher.as_ref(), &unfurl_store);
+    let cards = unfurler.unfurl_all(urls).await;
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let msg_service = MsgService::new(&chan_repo, &user_repo, &msg_store);
+
+    // the message may have been deleted meanwhile.
+    if let Some(message) = msg_service.set_unfurls(msg_id, &cards).await? {
+        let channel_id = message.channel_id;
+        let event = ServerEvent::MessageUpdated { message };
+        state.send_to_channel(channel_id, &event).await?;
+    }
+    Ok(())
+}
+
+/// Fans out a message that was posted other than through a socket: to the channel, to the
+/// people it mentions, to the unfurler and to the channel's webhooks.
+pub async fn publish_message(state: &AppState, msg: &Message) -> Result<(), AppError> {
+    state.metrics.messages_sent.inc();
+    let event = ServerEvent::MessageCreated {
+        message: msg.clone(),
+    };
+    state.send_to_channel(msg.channel_id, &event).await?;
+    state.notify_mentions(msg).await?;
+    spawn_unfurl(state, msg);
+    let event = WebhookEvent::MessageCreated {
+        message: msg.clone(),
+    };
+    dispatch_webhook(state, msg.channel_id, event).await;
+    Ok(())
+}
+
+// Only queues the deliveries, the webhook worker sends them; a failure to queue is logged
+// rather than failing the request that caused the event.
+pub async fn dispatch_webhook(state: &AppState, channel_id: i64, event: WebhookEvent) {
+    let chan_repo = ChanRepository::new(&state.pool);
+    let webhook_store = WebhookStore::new(&state.pool);
+    let webhook_service = WebhookService::new(&chan_repo, &webhook_store);
+
+    if let Err(e) = webhook_service.enqueue(channel_id, &event).await {
+        warn!(
+            "queue webhook {} for channel {} error: {}",
+            event.kind(),
+            channel_id,
+            e
+        );
+    }
+}
+
+/// Runs a slash command typed into `channel_id` and fans out what it did: the answer goes to
+/// the invoker's sockets only, channel changes and posts go to the channel as usual.
+pub async fn run_command(
+    state: &AppState,
+    invoker_id: i64,
+    channel_id: i64,
+    cmd: &ParsedCommand,
+) -> Result<CommandResp, AppError> {
+    info!(
+        "user {} run /{} in channel {}",
+        invoker_id, cmd.name, channel_id
+    );
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let command_store = SlashCommandStore::new(&state.pool);
+    let reminder_store = ReminderStore::new(&state.pool);
+    let command_service = CommandService::new(
+        &chan_repo,
+        &user_repo,
+        &msg_store,
+        &command_store,
+        &reminder_store,
+        &state.ek,
+        &state.dk,
+        &state.webhook_sender,
+    );
+
+    let outcome = command_service.execute(invoker_id, channel_id, cmd).await?;
+
+    if let Some(text) = &outcome.response {
+        let event = ServerEvent::Ephemeral {
+            channel_id,
+            text: text.clone(),
+        };
+        state.send_to_users(&[invoker_id], &event).await;
+    }
+    if let Some(channel) = outcome.updated_channel {
+        let event = ServerEvent::ChannelUpdated { channel };
+        state.send_to_channel(channel_id, &event).await?;
+    }
+    for system_msg in &outcome.system_msgs {
+        state.send_system_message(system_msg).await?;
+    }
+    for event in outcome.webhook_events {
+        dispatch_webhook(state, channel_id, event).await;
+    }
+
+    let mut message = None;
+    if let Some(text) = outcome.post {
+        let req = SendMessageReq {
+            sender_id: Some(invoker_id),
+            parent_msg_id: None,
+            content_type: MessageContentType::Text,
+            text_content: text,
+            media_url: None,
+            media_metadata: None,
+            sender_override: None,
+            attachments: vec![],
+        };
+        let msg_service = MsgService::new(&chan_repo, &user_repo, &msg_store);
+        let msg = msg_service.send_message(channel_id, &req).await?;
+        publish_message(state, &msg).await?;
+        message = Some(msg);
+    }
+
+    Ok(CommandResp {
+        command: format!("/{}", cmd.name),
+        response: outcome.response,
+        message,
+    })
+}
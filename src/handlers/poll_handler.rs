@@ -0,0 +1,147 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::IntoResponse,
+};
+use tracing::{debug, info};
+
+use crate::{
+    auth::extractor::CurrentUser,
+    dto::{event::ServerEvent, poll::CreatePollReq},
+    errors::AppError,
+    extract::ValidatedJson,
+    handlers::publish_message,
+    models::{
+        channel::ChanRepository, message::MessageStore, poll::PollStore, user::UserRepository,
+    },
+    service::poll::PollService,
+    state::AppState,
+};
+
+pub async fn create_poll(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(channel_id): Path<i64>,
+    ValidatedJson(req): ValidatedJson<CreatePollReq>,
+) -> Result<impl IntoResponse, AppError> {
+    debug!(
+        "user {} create poll in channel {}: {:?}",
+        user.id, channel_id, req
+    );
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let poll_store = PollStore::new(&state.pool);
+    let poll_service = PollService::new(&chan_repo, &user_repo, &msg_store, &poll_store);
+
+    let resp = poll_service.create_poll(user.id, channel_id, &req).await?;
+    publish_message(&state, &resp.message).await?;
+    Ok(Json(resp))
+}
+
+pub async fn get_poll(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(message_id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("user {} get poll {}", user.id, message_id);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let poll_store = PollStore::new(&state.pool);
+    let poll_service = PollService::new(&chan_repo, &user_repo, &msg_store, &poll_store);
+
+    let resp = poll_service.get_poll(user.id, message_id).await?;
+    Ok(Json(resp))
+}
+
+pub async fn vote(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path((message_id, option_index)): Path<(i64, i32)>,
+) -> Result<impl IntoResponse, AppError> {
+    info!(
+        "user {} vote {} on poll {}",
+        user.id, option_index, message_id
+    );
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let poll_store = PollStore::new(&state.pool);
+    let poll_service = PollService::new(&chan_repo, &user_repo, &msg_store, &poll_store);
+
+    let resp = poll_service.vote(user.id, message_id, option_index).await?;
+    let event = ServerEvent::PollUpdated {
+        poll: resp.poll.clone(),
+    };
+    state.send_to_channel(resp.poll.channel_id, &event).await?;
+    Ok(Json(resp))
+}
+
+pub async fn unvote(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path((message_id, option_index)): Path<(i64, i32)>,
+) -> Result<impl IntoResponse, AppError> {
+    info!(
+        "user {} unvote {} on poll {}",
+        user.id, option_index, message_id
+    );
+    retract_votes(state, user.id, message_id, Some(option_index)).await
+}
+
+pub async fn unvote_all(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(message_id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("user {} unvote all on poll {}", user.id, message_id);
+    retract_votes(state, user.id, message_id, None).await
+}
+
+async fn retract_votes(
+    state: AppState,
+    user_id: i64,
+    message_id: i64,
+    option_index: Option<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let poll_store = PollStore::new(&state.pool);
+    let poll_service = PollService::new(&chan_repo, &user_repo, &msg_store, &poll_store);
+
+    let resp = poll_service
+        .unvote(user_id, message_id, option_index)
+        .await?;
+    let event = ServerEvent::PollUpdated {
+        poll: resp.poll.clone(),
+    };
+    state.send_to_channel(resp.poll.channel_id, &event).await?;
+    Ok(Json(resp))
+}
+
+pub async fn close_poll(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(message_id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("user {} close poll {}", user.id, message_id);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let poll_store = PollStore::new(&state.pool);
+    let poll_service = PollService::new(&chan_repo, &user_repo, &msg_store, &poll_store);
+
+    let resp = poll_service.close_poll(user.id, message_id).await?;
+    let event = ServerEvent::PollUpdated {
+        poll: resp.poll.clone(),
+    };
+    state.send_to_channel(resp.poll.channel_id, &event).await?;
+    state.send_system_message(&resp.system_msg).await?;
+    Ok(Json(resp))
+}
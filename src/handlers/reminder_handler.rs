@@ -0,0 +1,120 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use tracing::{debug, info};
+
+use crate::{
+    auth::extractor::CurrentUser,
+    dto::reminder::{CreateReminderReq, ListRemindersReq, SnoozeReminderReq},
+    errors::AppError,
+    extract::{ValidatedJson, ValidatedQuery},
+    models::{
+        channel::ChanRepository, message::MessageStore, reminder::ReminderStore,
+        user::UserRepository,
+    },
+    service::reminder::ReminderService,
+    state::AppState,
+};
+
+pub async fn list_reminders(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    ValidatedQuery(req): ValidatedQuery<ListRemindersReq>,
+) -> Result<impl IntoResponse, AppError> {
+    debug!("user {} list reminders: {:?}", user.id, req);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let reminder_store = ReminderStore::new(&state.pool);
+    let reminder_service =
+        ReminderService::new(&chan_repo, &user_repo, &msg_store, &reminder_store);
+
+    let resp = reminder_service.list_reminders(user.id, &req).await?;
+    Ok(Json(resp))
+}
+
+pub async fn create_reminder(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    ValidatedJson(req): ValidatedJson<CreateReminderReq>,
+) -> Result<impl IntoResponse, AppError> {
+    debug!("user {} create reminder: {:?}", user.id, req);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let reminder_store = ReminderStore::new(&state.pool);
+    let reminder_service =
+        ReminderService::new(&chan_repo, &user_repo, &msg_store, &reminder_store);
+
+    let resp = reminder_service.create_reminder(&user, &req).await?;
+    Ok(Json(resp))
+}
+
+pub async fn snooze_reminder(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(reminder_id): Path<i64>,
+    ValidatedJson(req): ValidatedJson<SnoozeReminderReq>,
+) -> Result<impl IntoResponse, AppError> {
+    debug!(
+        "user {} snooze reminder {}: {:?}",
+        user.id, reminder_id, req
+    );
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let reminder_store = ReminderStore::new(&state.pool);
+    let reminder_service =
+        ReminderService::new(&chan_repo, &user_repo, &msg_store, &reminder_store);
+
+    let resp = reminder_service
+        .snooze_reminder(&user, reminder_id, &req)
+        .await?;
+    Ok(Json(resp))
+}
+
+pub async fn complete_reminder(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(reminder_id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("user {} complete reminder {}", user.id, reminder_id);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let reminder_store = ReminderStore::new(&state.pool);
+    let reminder_service =
+        ReminderService::new(&chan_repo, &user_repo, &msg_store, &reminder_store);
+
+    let resp = reminder_service
+        .complete_reminder(user.id, reminder_id)
+        .await?;
+    Ok(Json(resp))
+}
+
+pub async fn delete_reminder(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(reminder_id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("user {} delete reminder {}", user.id, reminder_id);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let reminder_store = ReminderStore::new(&state.pool);
+    let reminder_service =
+        ReminderService::new(&chan_repo, &user_repo, &msg_store, &reminder_store);
+
+    reminder_service
+        .delete_reminder(user.id, reminder_id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,85 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use tracing::{debug, info};
+
+use crate::{
+    auth::extractor::CurrentUser,
+    dto::saved::{ListSavedItemsReq, SaveItemReq, UpdateSavedItemReq},
+    errors::AppError,
+    extract::{ValidatedJson, ValidatedQuery},
+    models::{channel::ChanRepository, message::MessageStore, saved::SavedItemStore},
+    service::saved::SavedService,
+    state::AppState,
+};
+
+pub async fn list_saved_items(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    ValidatedQuery(req): ValidatedQuery<ListSavedItemsReq>,
+) -> Result<impl IntoResponse, AppError> {
+    debug!("user {} list saved items: {:?}", user.id, req);
+
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let saved_store = SavedItemStore::new(&state.pool);
+    let saved_service = SavedService::new(&chan_repo, &msg_store, &saved_store);
+
+    let resp = saved_service.list_items(user.id, &req).await?;
+    Ok(Json(resp))
+}
+
+pub async fn save_item(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    ValidatedJson(req): ValidatedJson<SaveItemReq>,
+) -> Result<impl IntoResponse, AppError> {
+    debug!("user {} save item: {:?}", user.id, req);
+
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let saved_store = SavedItemStore::new(&state.pool);
+    let saved_service = SavedService::new(&chan_repo, &msg_store, &saved_store);
+
+    let resp = saved_service.save_item(user.id, &req).await?;
+    Ok(Json(resp))
+}
+
+pub async fn update_saved_item(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(message_id): Path<i64>,
+    ValidatedJson(req): ValidatedJson<UpdateSavedItemReq>,
+) -> Result<impl IntoResponse, AppError> {
+    debug!(
+        "user {} update saved item {}: {:?}",
+        user.id, message_id, req
+    );
+
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let saved_store = SavedItemStore::new(&state.pool);
+    let saved_service = SavedService::new(&chan_repo, &msg_store, &saved_store);
+
+    let resp = saved_service.update_item(user.id, message_id, &req).await?;
+    Ok(Json(resp))
+}
+
+pub async fn remove_saved_item(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(message_id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("user {} remove saved item {}", user.id, message_id);
+
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let saved_store = SavedItemStore::new(&state.pool);
+    let saved_service = SavedService::new(&chan_repo, &msg_store, &saved_store);
+
+    saved_service.remove_item(user.id, message_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,99 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    response::IntoResponse,
+};
+use tracing::{debug, info};
+
+use crate::{
+    auth::extractor::CurrentUser,
+    dto::scheduled::{ListScheduledMessagesReq, ScheduleMessageReq, UpdateScheduledMessageReq},
+    errors::AppError,
+    extract::{ValidatedJson, ValidatedQuery},
+    models::{
+        channel::ChanRepository, message::MessageStore, scheduled::ScheduledMessageStore,
+        user::UserRepository,
+    },
+    service::scheduled::ScheduledService,
+    state::AppState,
+};
+
+pub async fn list_scheduled_messages(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    ValidatedQuery(req): ValidatedQuery<ListScheduledMessagesReq>,
+) -> Result<impl IntoResponse, AppError> {
+    debug!("user {} list scheduled messages: {:?}", user.id, req);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let scheduled_store = ScheduledMessageStore::new(&state.pool);
+    let scheduled_service =
+        ScheduledService::new(&chan_repo, &user_repo, &msg_store, &scheduled_store);
+
+    let resp = scheduled_service.list_messages(user.id, &req).await?;
+    Ok(Json(resp))
+}
+
+pub async fn schedule_message(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    ValidatedJson(req): ValidatedJson<ScheduleMessageReq>,
+) -> Result<impl IntoResponse, AppError> {
+    debug!("user {} schedule message: {:?}", user.id, req);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let scheduled_store = ScheduledMessageStore::new(&state.pool);
+    let scheduled_service =
+        ScheduledService::new(&chan_repo, &user_repo, &msg_store, &scheduled_store);
+
+    let resp = scheduled_service.schedule_message(user.id, &req).await?;
+    Ok(Json(resp))
+}
+
+pub async fn update_scheduled_message(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(scheduled_id): Path<i64>,
+    ValidatedJson(req): ValidatedJson<UpdateScheduledMessageReq>,
+) -> Result<impl IntoResponse, AppError> {
+    debug!(
+        "user {} update scheduled message {}: {:?}",
+        user.id, scheduled_id, req
+    );
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let scheduled_store = ScheduledMessageStore::new(&state.pool);
+    let scheduled_service =
+        ScheduledService::new(&chan_repo, &user_repo, &msg_store, &scheduled_store);
+
+    let resp = scheduled_service
+        .update_message(user.id, scheduled_id, &req)
+        .await?;
+    Ok(Json(resp))
+}
+
+pub async fn cancel_scheduled_message(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(scheduled_id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("user {} cancel scheduled message {}", user.id, scheduled_id);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let scheduled_store = ScheduledMessageStore::new(&state.pool);
+    let scheduled_service =
+        ScheduledService::new(&chan_repo, &user_repo, &msg_store, &scheduled_store);
+
+    let resp = scheduled_service
+        .cancel_message(user.id, scheduled_id)
+        .await?;
+    Ok(Json(resp))
+}
@@ -1,39 +1,54 @@
 use axum::{
     Json,
     extract::{Path, State},
+    http::StatusCode,
     response::IntoResponse,
 };
+use tracing::{debug, info};
 
 use crate::{
-    dto::user::{LoginReq, RegisterRequest},
+    auth::extractor::CurrentUser,
+    dto::user::{ChangePasswordReq, DeactivateResp, LoginReq, RegisterRequest, UpdateProfileReq},
     errors::AppError,
-    models::user::UserRepository,
-    service::user::UserService,
+    extract::ValidatedJson,
+    models::{audit::AuditStore, user::UserRepository},
+    service::{
+        audit::{Auditor, ClientInfo},
+        user::UserService,
+    },
     state::AppState,
 };
 
 pub async fn register(
     State(state): State<AppState>,
-    Json(payload): Json<RegisterRequest>,
+    ValidatedJson(payload): ValidatedJson<RegisterRequest>,
 ) -> Result<impl IntoResponse, AppError> {
-    println!("register user: {:?}", payload);
+    debug!("register user: {:?}", payload);
 
     let user_repo = UserRepository::new(&state.pool);
-    let user_service = UserService::new(&user_repo, &state.ek, &state.dk);
+    let auth = &state.config.auth;
+    let user_service = UserService::new(&user_repo, &state.ek, &state.dk)
+        .with_password_len(auth.min_password_len, auth.max_password_len);
 
     let resp = user_service.create_user(&payload).await?;
-    println!("created user: {:?}", resp.user);
+    debug!("created user: {:?}", resp.user);
     Ok(Json(resp))
 }
 
 pub async fn login(
     State(state): State<AppState>,
-    Json(req): Json<LoginReq>,
+    client: ClientInfo,
+    ValidatedJson(req): ValidatedJson<LoginReq>,
 ) -> Result<impl IntoResponse, AppError> {
-    println!("login user: {:?}", req);
+    debug!("login user: {:?}", req);
 
     let user_repo = UserRepository::new(&state.pool);
-    let user_service = UserService::new(&user_repo, &state.ek, &state.dk);
+    let audit_store = AuditStore::new(&state.pool);
+    let client_ip = client.ip.clone().unwrap_or_default();
+    let auditor = Auditor::new(&audit_store, client);
+    let user_service = UserService::new(&user_repo, &state.ek, &state.dk)
+        .with_auditor(&auditor)
+        .with_login_guard(&state.login_guard, client_ip);
 
     let resp = user_service.login(&req).await?;
     Ok(Json(resp))
@@ -49,3 +64,52 @@ pub async fn get_user(
     let resp = user_service.get_user(user_id).await?;
     Ok(Json(resp))
 }
+
+pub async fn update_me(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    ValidatedJson(req): ValidatedJson<UpdateProfileReq>,
+) -> Result<impl IntoResponse, AppError> {
+    debug!("update user {} profile: {:?}", user.id, req);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let user_service = UserService::new(&user_repo, &state.ek, &state.dk);
+
+    let resp = user_service.update_profile(user.id, &req).await?;
+    Ok(Json(resp))
+}
+
+pub async fn change_password(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    client: ClientInfo,
+    ValidatedJson(req): ValidatedJson<ChangePasswordReq>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("change user {} password", user.id);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let audit_store = AuditStore::new(&state.pool);
+    let auditor = Auditor::new(&audit_store, client);
+    let auth = &state.config.auth;
+    let user_service = UserService::new(&user_repo, &state.ek, &state.dk)
+        .with_auditor(&auditor)
+        .with_password_len(auth.min_password_len, auth.max_password_len);
+
+    user_service.change_password(user.id, &req).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn deactivate_me(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+) -> Result<impl IntoResponse, AppError> {
+    info!("deactivate user {}", user.id);
+
+    let user_repo = UserRepository::new(&state.pool);
+    let user_service = UserService::new(&user_repo, &state.ek, &state.dk);
+
+    let user = user_service.deactivate(user.id).await?;
+    state.disconnect_user(user.id).await;
+
+    Ok(Json(DeactivateResp { user }))
+}
@@ -0,0 +1,214 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use tracing::{debug, info};
+
+use crate::{
+    auth::extractor::CurrentUser,
+    dto::webhook::{
+        CreateIncomingWebhookReq, CreateWebhookReq, IncomingWebhookReq, ListDeliveriesReq,
+    },
+    errors::AppError,
+    extract::{ValidatedJson, ValidatedQuery},
+    handlers::publish_message,
+    models::{
+        channel::ChanRepository, message::MessageStore, user::UserRepository, webhook::WebhookStore,
+    },
+    service::webhook::{IncomingWebhookService, WebhookService},
+    state::AppState,
+};
+
+pub async fn create_webhook(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(channel_id): Path<i64>,
+    ValidatedJson(req): ValidatedJson<CreateWebhookReq>,
+) -> Result<impl IntoResponse, AppError> {
+    debug!(
+        "user {} create webhook in channel {}: {:?}",
+        user.id, channel_id, req
+    );
+
+    let chan_repo = ChanRepository::new(&state.pool);
+    let webhook_store = WebhookStore::new(&state.pool);
+    let webhook_service = WebhookService::new(&chan_repo, &webhook_store);
+
+    let resp = webhook_service
+        .create_webhook(user.id, user.is_admin, channel_id, &req)
+        .await?;
+    Ok(Json(resp))
+}
+
+pub async fn list_webhooks(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(channel_id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("user {} list webhooks of channel {}", user.id, channel_id);
+
+    let chan_repo = ChanRepository::new(&state.pool);
+    let webhook_store = WebhookStore::new(&state.pool);
+    let webhook_service = WebhookService::new(&chan_repo, &webhook_store);
+
+    let resp = webhook_service
+        .list_webhooks(user.id, user.is_admin, channel_id)
+        .await?;
+    Ok(Json(resp))
+}
+
+pub async fn delete_webhook(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path((channel_id, webhook_id)): Path<(i64, i64)>,
+) -> Result<impl IntoResponse, AppError> {
+    info!(
+        "user {} delete webhook {} of channel {}",
+        user.id, webhook_id, channel_id
+    );
+
+    let chan_repo = ChanRepository::new(&state.pool);
+    let webhook_store = WebhookStore::new(&state.pool);
+    let webhook_service = WebhookService::new(&chan_repo, &webhook_store);
+
+    webhook_service
+        .delete_webhook(user.id, user.is_admin, channel_id, webhook_id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn enable_webhook(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path((channel_id, webhook_id)): Path<(i64, i64)>,
+) -> Result<impl IntoResponse, AppError> {
+    info!(
+        "user {} enable webhook {} of channel {}",
+        user.id, webhook_id, channel_id
+    );
+
+    let chan_repo = ChanRepository::new(&state.pool);
+    let webhook_store = WebhookStore::new(&state.pool);
+    let webhook_service = WebhookService::new(&chan_repo, &webhook_store);
+
+    let resp = webhook_service
+        .enable_webhook(user.id, user.is_admin, channel_id, webhook_id)
+        .await?;
+    Ok(Json(resp))
+}
+
+pub async fn list_deliveries(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path((channel_id, webhook_id)): Path<(i64, i64)>,
+    ValidatedQuery(req): ValidatedQuery<ListDeliveriesReq>,
+) -> Result<impl IntoResponse, AppError> {
+    debug!(
+        "user {} list deliveries of webhook {}: {:?}",
+        user.id, webhook_id, req
+    );
+
+    let chan_repo = ChanRepository::new(&state.pool);
+    let webhook_store = WebhookStore::new(&state.pool);
+    let webhook_service = WebhookService::new(&chan_repo, &webhook_store);
+
+    let resp = webhook_service
+        .list_deliveries(user.id, user.is_admin, channel_id, webhook_id, &req)
+        .await?;
+    Ok(Json(resp))
+}
+
+pub async fn create_incoming_webhook(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(channel_id): Path<i64>,
+    ValidatedJson(req): ValidatedJson<CreateIncomingWebhookReq>,
+) -> Result<impl IntoResponse, AppError> {
+    debug!(
+        "user {} create incoming webhook in channel {}: {:?}",
+        user.id, channel_id, req
+    );
+
+    let chan_repo = ChanRepository::new(&state.pool);
+    let user_repo = UserRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let webhook_store = WebhookStore::new(&state.pool);
+    let incoming_service =
+        IncomingWebhookService::new(&chan_repo, &user_repo, &msg_store, &webhook_store);
+
+    let resp = incoming_service
+        .create_webhook(user.id, user.is_admin, channel_id, &req)
+        .await?;
+    Ok(Json(resp))
+}
+
+pub async fn list_incoming_webhooks(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(channel_id): Path<i64>,
+) -> Result<impl IntoResponse, AppError> {
+    info!(
+        "user {} list incoming webhooks of channel {}",
+        user.id, channel_id
+    );
+
+    let chan_repo = ChanRepository::new(&state.pool);
+    let user_repo = UserRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let webhook_store = WebhookStore::new(&state.pool);
+    let incoming_service =
+        IncomingWebhookService::new(&chan_repo, &user_repo, &msg_store, &webhook_store);
+
+    let resp = incoming_service
+        .list_webhooks(user.id, user.is_admin, channel_id)
+        .await?;
+    Ok(Json(resp))
+}
+
+pub async fn revoke_incoming_webhook(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path((channel_id, webhook_id)): Path<(i64, i64)>,
+) -> Result<impl IntoResponse, AppError> {
+    info!(
+        "user {} revoke incoming webhook {} of channel {}",
+        user.id, webhook_id, channel_id
+    );
+
+    let chan_repo = ChanRepository::new(&state.pool);
+    let user_repo = UserRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let webhook_store = WebhookStore::new(&state.pool);
+    let incoming_service =
+        IncomingWebhookService::new(&chan_repo, &user_repo, &msg_store, &webhook_store);
+
+    incoming_service
+        .revoke_webhook(user.id, user.is_admin, channel_id, webhook_id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Unauthenticated: the token in the path is the credential, so it is never logged.
+pub async fn post_to_hook(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    ValidatedJson(req): ValidatedJson<IncomingWebhookReq>,
+) -> Result<impl IntoResponse, AppError> {
+    let chan_repo = ChanRepository::new(&state.pool);
+    let user_repo = UserRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let webhook_store = WebhookStore::new(&state.pool);
+    let incoming_service =
+        IncomingWebhookService::new(&chan_repo, &user_repo, &msg_store, &webhook_store);
+
+    let resp = incoming_service.post_message(&token, &req).await?;
+    info!(
+        "incoming webhook posted message {} to channel {}",
+        resp.id, resp.channel_id
+    );
+
+    publish_message(&state, &resp).await?;
+    Ok(Json(resp))
+}
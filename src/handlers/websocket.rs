@@ -1,125 +1,254 @@
 use crate::{
-    dto::message::{SendMessageInSocket, WebSocketMessage},
+    auth::extractor::SocketUser,
+    dto::{
+        event::ServerEvent,
+        message::{MessageContentType, SendMessageInSocket, WebSocketMessage},
+        webhook::WebhookEvent,
+    },
     errors::AppError,
-    handlers::{list_channel_memebers, list_simple_users, send_message_to_channel},
-    state::AppState,
+    handlers::{
+        dispatch_webhook, list_simple_users, run_command, send_message_to_channel, spawn_unfurl,
+    },
+    ratelimit::TokenBucket,
+    service::command::parse_command,
+    state::{AppState, Outbound},
 };
+use tracing::{Instrument, debug, info, info_span, warn};
 
 use axum::{
     extract::{
         Path, State,
-        ws::{Message, WebSocket, WebSocketUpgrade},
+        ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade, close_code},
     },
     response::IntoResponse,
 };
 use futures_util::{sink::SinkExt, stream::StreamExt};
 use tokio::sync::broadcast;
+use validator::Validate;
 
+/// What a socket is sent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Protocol {
+    // `/{user_id}/websocket`: bare chat messages only, the shape from before events.
+    Legacy,
+    // `/api/v1/websocket`: every `ServerEvent`, tagged by `type`.
+    V1,
+}
+
+/// The original socket, kept for existing clients. The path must name the user the
+/// token belongs to.
 pub async fn message_loop(
     ws: WebSocketUpgrade,
     State(state): State<AppState>,
+    SocketUser(user): SocketUser,
     Path(user_id): Path<i64>,
 ) -> Result<impl IntoResponse, AppError> {
-    let resp = ws.on_upgrade(move |socket| handle_socket(user_id, socket, state));
+    if user.id != user_id {
+        return Err(AppError::PermissionDenied(
+            "socket belongs to another user".to_string(),
+        ));
+    }
+    upgrade(ws, state, user.id, Protocol::Legacy)
+}
+
+pub async fn event_loop(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+    SocketUser(user): SocketUser,
+) -> Result<impl IntoResponse, AppError> {
+    upgrade(ws, state, user.id, Protocol::V1)
+}
+
+fn upgrade(
+    ws: WebSocketUpgrade,
+    state: AppState,
+    user_id: i64,
+    protocol: Protocol,
+) -> Result<impl IntoResponse, AppError> {
+    if state.is_shutting_down() {
+        return Err(AppError::Unavailable("server is shutting down".to_string()));
+    }
+    let resp = ws.on_upgrade(move |socket| handle_socket(user_id, protocol, socket, state));
     Ok(resp)
 }
 
-async fn handle_socket(user_id: i64, stream: WebSocket, state: AppState) {
+async fn handle_socket(user_id: i64, protocol: Protocol, stream: WebSocket, state: AppState) {
+    // the upgrade outlives the request's span, so each connection gets its own.
+    let span = info_span!("socket", user_id, conn_id = %nanoid::nanoid!(8));
+    span.in_scope(|| info!("socket connected"));
+    state.metrics.ws_connections.inc();
+
     // Use state and socket here
     let (mut sender, mut receiver) = stream.split();
 
-    let (tx, mut rx) = broadcast::channel(100);
+    let (tx, mut rx) = broadcast::channel(state.config.limits.socket_buffer);
     {
         let mut hash_map = state.tx_set.write().await;
+        // shutdown may have swept the sockets while this one was upgrading.
+        if state.is_shutting_down() {
+            let _ = tx.send(Outbound::GoingAway);
+        }
         hash_map.insert(user_id, tx);
     }
 
     // Spawn the first task that will receive broadcast messages and send text
     // messages over the websocket to our client.
-    let mut send_task = tokio::spawn(async move {
-        while let Ok(msg) = rx.recv().await {
-            println!("received: {}", msg);
-            // In any websocket error, break loop.
-            if sender.send(Message::text(msg)).await.is_err() {
-                break;
+    let metrics = state.metrics.clone();
+    let mut send_task = tokio::spawn(
+        async move {
+            loop {
+                let msg = match rx.recv().await {
+                    Ok(Outbound::Event { tagged, legacy }) => match protocol {
+                        Protocol::V1 => tagged,
+                        Protocol::Legacy => match legacy {
+                            Some(msg) => msg,
+                            None => continue,
+                        },
+                    },
+                    Ok(Outbound::GoingAway) => {
+                        let frame = CloseFrame {
+                            code: close_code::AWAY,
+                            reason: "server going away".into(),
+                        };
+                        let _ = sender.send(Message::Close(Some(frame))).await;
+                        break;
+                    }
+                    // the client missed events; closing makes it reconnect and refetch.
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("socket lagged behind by {} events", skipped);
+                        metrics.broadcast_lagged.inc();
+                        metrics.broadcast_dropped.inc_by(skipped);
+                        break;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                debug!("received: {}", msg);
+                // In any websocket error, break loop.
+                if sender.send(Message::text(msg)).await.is_err() {
+                    break;
+                }
             }
         }
-    });
+        .instrument(span.clone()),
+    );
 
+    let metrics = state.metrics.clone();
     let state = state.clone();
 
-    // Spawn a task that takes messages from the websocket, prepends the user
-    // name, and sends them to all broadcast subscribers.
-    let mut recv_task = tokio::spawn(async move {
-        while let Some(Ok(Message::Text(text))) = receiver.next().await {
-            // Add username before message.
-            println!("received msg from client: {}", text);
-            let send_msg_opt: Option<SendMessageInSocket> = match serde_json::from_str(&text) {
-                Ok(v) => Some(v),
+    // Spawn a task that takes messages from the websocket, stores them through
+    // the message service and broadcasts them to the channel members.
+    let mut recv_task = tokio::spawn(
+        async move {
+            let sender = match list_simple_users(&state.pool, vec![user_id]).await {
+                Ok(mut users) if !users.is_empty() => users.remove(0),
+                Ok(_) => {
+                    warn!("socket user {} not found", user_id);
+                    return;
+                }
                 Err(e) => {
-                    println!("get send message from socket error: {}", e);
-                    None
+                    warn!("list simple users error: {}", e);
+                    return;
                 }
             };
 
-            if send_msg_opt.is_none() {
-                continue;
-            }
+            // a frame can carry several messages, and each of them takes a token.
+            let mut bucket = TokenBucket::new(state.config.rate_limits.socket);
+            while let Some(Ok(Message::Text(text))) = receiver.next().await {
+                debug!("received msg from client: {}", text);
+                let send_msg: SendMessageInSocket = match serde_json::from_str(&text) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        warn!("get send message from socket error: {}", e);
+                        continue;
+                    }
+                };
+                if let Err(e) = send_msg.validate() {
+                    let event = ServerEvent::Error {
+                        message: AppError::from(e).public_message(),
+                    };
+                    state.send_to_users(&[user_id], &event).await;
+                    continue;
+                }
 
-            let send_msg = send_msg_opt.unwrap();
-            let members_res = list_channel_memebers(&state.pool, send_msg.channel_id).await;
-
-            match members_res {
-                Ok(members) => {
-                    let tx_set = state.tx_set.read().await;
-                    let member_ids: Vec<i64> = members
-                        .chan_members_list
-                        .iter()
-                        .map(|m| m.user_id)
-                        .collect();
-
-                    let senders_res = list_simple_users(&state.pool, member_ids).await;
-
-                    match senders_res {
-                        Ok(senders) => {
-                            for sender in senders {
-                                let sender_opt = tx_set.get(&sender.id);
-                                if sender_opt.is_some() {
-                                    for msg in &send_msg.msgs {
-                                        let socket_msg = WebSocketMessage {
-                                            sender: sender.clone(),
-                                            parent_msg_id: msg.parent_msg_id,
-                                            content_type: msg.content_type.clone(),
-                                            text_content: msg.text_content.clone(),
-                                            media_url: msg.media_url.clone(),
-                                            media_metadata: msg.media_metadata.clone(),
-                                        };
-
-                                        let msg_data = serde_json::to_string(&socket_msg).unwrap();
-
-                                        let _ = sender_opt.unwrap().send(msg_data);
-                                        println!("created msg in channel: {}", send_msg.channel_id)
-                                    }
-                                }
-                            }
+                for msg in &send_msg.msgs {
+                    if let Err(e) = bucket.try_take() {
+                        let event = ServerEvent::Error {
+                            message: e.public_message(),
+                        };
+                        state.send_to_users(&[user_id], &event).await;
+                        break;
+                    }
+
+                    // the socket owner is the sender, whatever the payload claims.
+                    let mut req = msg.clone();
+                    req.sender_id = Some(user_id);
+
+                    if let (MessageContentType::Text, Some(cmd)) =
+                        (&req.content_type, parse_command(&req.text_content))
+                    {
+                        // the answer itself reaches the socket as an `ephemeral` event.
+                        if let Err(e) =
+                            run_command(&state, user_id, send_msg.channel_id, &cmd).await
+                        {
+                            let event = ServerEvent::Error {
+                                message: e.public_message(),
+                            };
+                            state.send_to_users(&[user_id], &event).await;
                         }
-                        Err(e) => println!("list simple users error: {}", e),
+                        continue;
                     }
+
+                    let created =
+                        match send_message_to_channel(&state.pool, send_msg.channel_id, &req).await
+                        {
+                            Ok(msg) => msg,
+                            Err(e) => {
+                                let event = ServerEvent::Error {
+                                    message: e.public_message(),
+                                };
+                                state.send_to_users(&[user_id], &event).await;
+                                continue;
+                            }
+                        };
+                    state.metrics.messages_sent.inc();
+
+                    let event = ServerEvent::Message(WebSocketMessage {
+                        sender: sender.clone(),
+                        parent_msg_id: req.parent_msg_id,
+                        content_type: req.content_type,
+                        text_content: req.text_content,
+                        media_url: req.media_url,
+                        media_metadata: req.media_metadata,
+                    });
+                    match state.send_to_channel(send_msg.channel_id, &event).await {
+                        Ok(_) => info!("created msg in channel: {}", send_msg.channel_id),
+                        Err(e) => warn!("list channel members error: {}", e),
+                    }
+                    if let Err(e) = state.notify_mentions(&created).await {
+                        warn!("notify mentions error: {}", e);
+                    }
+                    spawn_unfurl(&state, &created);
+                    let channel_id = created.channel_id;
+                    let event = WebhookEvent::MessageCreated { message: created };
+                    dispatch_webhook(&state, channel_id, event).await;
                 }
-                Err(e) => println!("list channel members error: {}", e),
             }
         }
-    });
+        .instrument(span.clone()),
+    );
 
     tokio::select! {
         _ = &mut send_task => recv_task.abort(),
         _ = &mut recv_task => send_task.abort(),
     };
+    metrics.ws_connections.dec();
+    span.in_scope(|| info!("socket closed"));
 }
 
 #[cfg(test)]
 mod tests {
     use crate::dto::SimpleUser;
+    use crate::dto::event::ServerEvent;
     use crate::dto::message::{MessageContentType, WebSocketMessage};
 
     #[test]
@@ -129,6 +258,7 @@ mod tests {
                 id: 1,
                 avatar_url: "http://localhost:8888/users/1/avatar".to_string(),
                 display_name: "Alice".to_string(),
+                is_bot: false,
             },
             parent_msg_id: None,
             content_type: MessageContentType::Text,
@@ -140,4 +270,15 @@ mod tests {
         let data = serde_json::to_string(&msg).unwrap();
         println!("{}", data);
     }
+
+    #[test]
+    fn test_server_event_is_tagged() {
+        let event = ServerEvent::Error {
+            message: "channel is archived and read-only".to_string(),
+        };
+
+        let data = serde_json::to_value(&event).unwrap();
+        assert_eq!(data["type"], "error");
+        assert_eq!(data["message"], "channel is archived and read-only");
+    }
 }
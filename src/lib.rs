@@ -1,8 +1,19 @@
 pub mod auth;
+pub mod config;
 pub mod dto;
 pub mod errors;
+pub mod extract;
 pub mod handlers;
+pub mod metrics;
 pub mod models;
+pub mod ratelimit;
+pub mod request_id;
 pub mod router;
+pub mod scheduler;
 pub mod service;
 pub mod state;
+pub mod telemetry;
+#[cfg(test)]
+mod test_util;
+pub mod unfurl;
+pub mod webhook;
@@ -1,28 +1,92 @@
 use dotenv::dotenv;
-use slac::{router::get_router, state::AppState};
-use sqlx::PgPool;
-use std::env;
-use tokio::net::TcpListener;
+use slac::{
+    config::Config,
+    router::{get_metrics_router, get_router},
+    scheduler::run_scheduler,
+    state::AppState,
+    telemetry,
+    webhook::run_delivery_worker,
+};
+use std::{env, net::SocketAddr, time::Duration};
+use tokio::{net::TcpListener, signal};
+use tracing::{info, warn};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // Load variables from .env file
     dotenv().ok();
+    telemetry::init(env::var("LOG_FORMAT").is_ok_and(|f| f == "json"));
 
-    // Using expect when the variable is required
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set in .env file");
-    println!("database_url: {}", database_url);
+    // a bad config stops the server here, before anything binds or connects.
+    let config = Config::load()?;
+    info!(?config, "loaded config");
 
-    let pool = PgPool::connect(&database_url).await?;
-    println!("connected database: {:?}", pool);
+    // the url carries the database password, so it is never logged.
+    let pool = config
+        .database
+        .pool_options()
+        .connect(&config.database.url)
+        .await?;
+    info!("connected to database");
 
-    let state = AppState::new(pool)?;
-    let router = get_router(state).await?;
+    let addr = config.server.bind;
+    let metrics_addr = config.server.metrics_bind;
+    let drain_timeout = Duration::from_secs(config.server.shutdown_timeout_secs);
+    let state = AppState::new(pool.clone(), config)?;
+    tokio::spawn(run_delivery_worker(pool, state.webhook_sender.clone()));
+    tokio::spawn(run_scheduler(state.clone()));
 
-    let addr = format!("0.0.0.0:{}", "6869");
-    let listener = TcpListener::bind(&addr).await?;
-    println!("Listening on: {}", addr);
+    let router = get_router(state.clone()).await?;
 
-    axum::serve(listener, router.into_make_service()).await?;
+    let listener = TcpListener::bind(addr).await?;
+    info!(%addr, "listening");
+
+    let metrics_listener = TcpListener::bind(metrics_addr).await?;
+    info!(%metrics_addr, "serving metrics");
+    let metrics_router = get_metrics_router(state.clone());
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(metrics_listener, metrics_router).await {
+            warn!("metrics listener error: {}", e);
+        }
+    });
+
+    let shutdown_state = state.clone();
+    axum::serve(
+        listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(async move {
+        shutdown_signal().await;
+        info!("shutting down, draining requests");
+        shutdown_state.begin_shutdown().await;
+    })
+    .await?;
+
+    state.wait_for_sockets(drain_timeout).await;
+    info!("shutdown complete");
     Ok(())
 }
+
+// Resolves on Ctrl-C, or on SIGTERM from an orchestrator.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl-C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
@@ -0,0 +1,251 @@
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use prometheus::{
+    HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use tracing::warn;
+
+use crate::{
+    errors::AppError,
+    models::{
+        poll::PollStore, reminder::ReminderStore, scheduled::ScheduledMessageStore,
+        webhook::WebhookStore,
+    },
+    state::AppState,
+};
+
+/// Every metric the server exports. Counters are bumped where things happen; pool, queue
+/// and broadcast gauges are sampled when `/metrics` is scraped.
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+    pub ws_connections: IntGauge,
+    pub messages_sent: IntCounter,
+    pub broadcast_lagged: IntCounter,
+    pub broadcast_dropped: IntCounter,
+    broadcast_backlog: IntGauge,
+    db_connections: IntGaugeVec,
+    db_max_connections: IntGauge,
+    queue_depth: IntGaugeVec,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry =
+            Registry::new_custom(Some("slac".to_string()), None).expect("metrics registry prefix");
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests by route and status."),
+            &["method", "route", "status"],
+        )
+        .expect("http_requests_total");
+        let http_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time to answer an HTTP request.",
+            ),
+            &["method", "route"],
+        )
+        .expect("http_request_duration_seconds");
+        let ws_connections = IntGauge::new("websocket_connections", "Open websocket connections.")
+            .expect("websocket_connections");
+        let messages_sent = IntCounter::new("messages_sent_total", "Messages posted to channels.")
+            .expect("messages_sent_total");
+        let broadcast_lagged = IntCounter::new(
+            "broadcast_lagged_total",
+            "Sockets closed because they fell behind their broadcast channel.",
+        )
+        .expect("broadcast_lagged_total");
+        let broadcast_dropped = IntCounter::new(
+            "broadcast_dropped_events_total",
+            "Events a lagging socket never received.",
+        )
+        .expect("broadcast_dropped_events_total");
+        let broadcast_backlog = IntGauge::new(
+            "broadcast_backlog_max",
+            "Most events waiting in any one user's broadcast channel.",
+        )
+        .expect("broadcast_backlog_max");
+        let db_connections = IntGaugeVec::new(
+            Opts::new("db_pool_connections", "Database connections by state."),
+            &["state"],
+        )
+        .expect("db_pool_connections");
+        let db_max_connections = IntGauge::new(
+            "db_pool_max_connections",
+            "Connections the database pool may open.",
+        )
+        .expect("db_pool_max_connections");
+        let queue_depth = IntGaugeVec::new(
+            Opts::new(
+                "job_queue_depth",
+                "Background jobs waiting, and how many of them are already due.",
+            ),
+            &["queue", "state"],
+        )
+        .expect("job_queue_depth");
+
+        let metrics = Self {
+            registry,
+            http_requests,
+            http_duration,
+            ws_connections,
+            messages_sent,
+            broadcast_lagged,
+            broadcast_dropped,
+            broadcast_backlog,
+            db_connections,
+            db_max_connections,
+            queue_depth,
+        };
+        metrics.register().expect("register metrics");
+        metrics
+    }
+
+    fn register(&self) -> Result<(), prometheus::Error> {
+        self.registry
+            .register(Box::new(self.http_requests.clone()))?;
+        self.registry
+            .register(Box::new(self.http_duration.clone()))?;
+        self.registry
+            .register(Box::new(self.ws_connections.clone()))?;
+        self.registry
+            .register(Box::new(self.messages_sent.clone()))?;
+        self.registry
+            .register(Box::new(self.broadcast_lagged.clone()))?;
+        self.registry
+            .register(Box::new(self.broadcast_dropped.clone()))?;
+        self.registry
+            .register(Box::new(self.broadcast_backlog.clone()))?;
+        self.registry
+            .register(Box::new(self.db_connections.clone()))?;
+        self.registry
+            .register(Box::new(self.db_max_connections.clone()))?;
+        self.registry.register(Box::new(self.queue_depth.clone()))?;
+        Ok(())
+    }
+
+    pub fn observe_request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        self.http_requests
+            .with_label_values(&[method, route, &status.to_string()])
+            .inc();
+        self.http_duration
+            .with_label_values(&[method, route])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    // A queue that can't be counted keeps its last value rather than failing the scrape;
+    // the pool gauges matter most exactly when the database is in trouble.
+    async fn sample(&self, state: &AppState) {
+        let pool = &state.pool;
+        let idle = pool.num_idle() as i64;
+        self.db_connections.with_label_values(&["idle"]).set(idle);
+        self.db_connections
+            .with_label_values(&["busy"])
+            .set(pool.size() as i64 - idle);
+        self.db_max_connections
+            .set(pool.options().get_max_connections() as i64);
+
+        let backlog = state
+            .tx_set
+            .read()
+            .await
+            .values()
+            .map(|tx| tx.len())
+            .max()
+            .unwrap_or(0);
+        self.broadcast_backlog.set(backlog as i64);
+
+        let queues = [
+            (
+                "scheduled_messages",
+                ScheduledMessageStore::new(pool).count_queued().await,
+            ),
+            ("reminders", ReminderStore::new(pool).count_queued().await),
+            (
+                "webhook_deliveries",
+                WebhookStore::new(pool).count_queued().await,
+            ),
+            ("poll_closes", PollStore::new(pool).count_queued().await),
+        ];
+        for (queue, counts) in queues {
+            match counts {
+                Ok((pending, due)) => {
+                    self.queue_depth
+                        .with_label_values(&[queue, "pending"])
+                        .set(pending);
+                    self.queue_depth.with_label_values(&[queue, "due"]).set(due);
+                }
+                Err(e) => warn!("count {} queue error: {}", queue, e),
+            }
+        }
+    }
+
+    /// The metrics in Prometheus' text exposition format.
+    pub fn encode(&self) -> Result<String, AppError> {
+        let text = TextEncoder::new().encode_to_string(&self.registry.gather())?;
+        Ok(text)
+    }
+
+    pub async fn render(&self, state: &AppState) -> Result<String, AppError> {
+        self.sample(state).await;
+        self.encode()
+    }
+}
+
+/// Counts and times every request by its matched route, so ids in paths don't each make
+/// a new series. Sits outside the rate limiter so rejected requests show up too.
+pub async fn track_http(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let method = req.method().to_string();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unmatched", |path| path.as_str())
+        .to_string();
+
+    let start = Instant::now();
+    let resp = next.run(req).await;
+    state
+        .metrics
+        .observe_request(&method, &route, resp.status().as_u16(), start.elapsed());
+    resp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let metrics = Metrics::new();
+        metrics.observe_request(
+            "GET",
+            "/api/v1/channels/{channel_id}",
+            200,
+            Duration::from_millis(12),
+        );
+        metrics.messages_sent.inc_by(3);
+        metrics.ws_connections.inc();
+
+        let text = metrics.encode().unwrap();
+        assert!(text.contains(
+            r#"slac_http_requests_total{method="GET",route="/api/v1/channels/{channel_id}",status="200"} 1"#
+        ));
+        assert!(text.contains("slac_http_request_duration_seconds_bucket"));
+        assert!(text.contains("slac_messages_sent_total 3"));
+        assert!(text.contains("slac_websocket_connections 1"));
+    }
+}
@@ -0,0 +1,172 @@
+use chrono::Utc;
+use sqlx::{FromRow, PgPool};
+
+use crate::errors::AppError;
+
+#[derive(Debug, FromRow)]
+pub struct ApiToken {
+    pub id: i64,
+    pub user_id: i64,
+    pub name: String,
+    pub token_hash: String,
+    pub token_prefix: String,
+    pub scopes: Vec<String>,
+    pub created_by: i64,
+    pub expires_at: Option<chrono::DateTime<Utc>>,
+    pub last_used_at: Option<chrono::DateTime<Utc>>,
+    pub revoked_at: Option<chrono::DateTime<Utc>>,
+    pub created_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct CreateApiToken {
+    pub user_id: i64,
+    pub name: String,
+    pub token_hash: String,
+    pub token_prefix: String,
+    pub scopes: Vec<String>,
+    pub created_by: i64,
+    pub expires_at: Option<chrono::DateTime<Utc>>,
+}
+
+#[derive(Debug)]
+pub struct ApiTokenStore<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> ApiTokenStore<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, token: &CreateApiToken) -> Result<ApiToken, AppError> {
+        let created = sqlx::query_as(
+            r#"
+            INSERT INTO api_tokens (user_id, name, token_hash, token_prefix, scopes, created_by, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING *
+            "#,
+        )
+        .bind(token.user_id)
+        .bind(&token.name)
+        .bind(&token.token_hash)
+        .bind(&token.token_prefix)
+        .bind(&token.scopes)
+        .bind(token.created_by)
+        .bind(token.expires_at)
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(created)
+    }
+
+    // Only tokens that can still authenticate: not revoked and not expired.
+    pub async fn get_valid_by_hash(&self, token_hash: &str) -> Result<Option<ApiToken>, AppError> {
+        let token = sqlx::query_as(
+            r#"
+            SELECT * FROM api_tokens
+            WHERE token_hash = $1
+                AND revoked_at IS NULL
+                AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP)
+            "#,
+        )
+        .bind(token_hash)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(token)
+    }
+
+    pub async fn get_by_id(&self, id: i64) -> Result<Option<ApiToken>, AppError> {
+        let token = sqlx::query_as(
+            r#"
+            SELECT * FROM api_tokens WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(token)
+    }
+
+    pub async fn list_by_user(&self, user_id: i64) -> Result<Vec<ApiToken>, AppError> {
+        let tokens = sqlx::query_as(
+            r#"
+            SELECT * FROM api_tokens
+            WHERE user_id = $1 AND revoked_at IS NULL
+            ORDER BY id ASC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(tokens)
+    }
+
+    pub async fn revoke(&self, id: i64) -> Result<u64, AppError> {
+        let res = sqlx::query(
+            r#"
+            UPDATE api_tokens SET revoked_at = CURRENT_TIMESTAMP
+            WHERE id = $1 AND revoked_at IS NULL
+            "#,
+        )
+        .bind(id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(res.rows_affected())
+    }
+
+    pub async fn revoke_all(&self, user_id: i64) -> Result<u64, AppError> {
+        let res = sqlx::query(
+            r#"
+            UPDATE api_tokens SET revoked_at = CURRENT_TIMESTAMP
+            WHERE user_id = $1 AND revoked_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(res.rows_affected())
+    }
+
+    // Replaces the secret of a token in place: the old one stops working at once.
+    pub async fn rotate(
+        &self,
+        id: i64,
+        token_hash: &str,
+        token_prefix: &str,
+    ) -> Result<Option<ApiToken>, AppError> {
+        let token = sqlx::query_as(
+            r#"
+            UPDATE api_tokens
+            SET token_hash = $2, token_prefix = $3, last_used_at = NULL
+            WHERE id = $1 AND revoked_at IS NULL
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(token_hash)
+        .bind(token_prefix)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(token)
+    }
+
+    pub async fn touch(&self, id: i64) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE api_tokens SET last_used_at = CURRENT_TIMESTAMP WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+
+use crate::errors::AppError;
+
+#[derive(Debug, FromRow, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub id: i64,
+    pub actor_id: Option<i64>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<i64>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub diff: serde_json::Value,
+    pub created_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct CreateAuditEvent {
+    pub actor_id: Option<i64>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<i64>,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub diff: serde_json::Value,
+}
+
+#[derive(Debug, Default)]
+pub struct AuditFilter {
+    pub actor_id: Option<i64>,
+    pub action: Option<String>,
+    pub since: Option<chrono::DateTime<Utc>>,
+    pub until: Option<chrono::DateTime<Utc>>,
+}
+
+#[derive(Debug)]
+pub struct AuditStore<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> AuditStore<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, event: &CreateAuditEvent) -> Result<AuditEvent, AppError> {
+        let created = sqlx::query_as(
+            r#"
+            INSERT INTO audit_events
+            (actor_id, action, target_type, target_id, ip, user_agent, diff)
+            VALUES ($1, $2, $3, $4, $5, $6, $7::jsonb)
+            RETURNING *
+            "#,
+        )
+        .bind(event.actor_id)
+        .bind(&event.action)
+        .bind(&event.target_type)
+        .bind(event.target_id)
+        .bind(&event.ip)
+        .bind(&event.user_agent)
+        .bind(&event.diff)
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(created)
+    }
+
+    pub async fn list(
+        &self,
+        filter: &AuditFilter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<AuditEvent>, AppError> {
+        let events = sqlx::query_as(
+            r#"
+            SELECT * FROM audit_events
+            WHERE ($1::BIGINT IS NULL OR actor_id = $1)
+                AND ($2::TEXT IS NULL OR action = $2)
+                AND ($3::TIMESTAMPTZ IS NULL OR created_at >= $3)
+                AND ($4::TIMESTAMPTZ IS NULL OR created_at < $4)
+            ORDER BY created_at DESC, id DESC
+            LIMIT $5 OFFSET $6
+            "#,
+        )
+        .bind(filter.actor_id)
+        .bind(&filter.action)
+        .bind(filter.since)
+        .bind(filter.until)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(events)
+    }
+}
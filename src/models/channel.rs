@@ -4,17 +4,21 @@ use sqlx::{FromRow, PgPool};
 
 use crate::errors::AppError;
 
-const ROLE_MEMBER: &'static str = "member";
-const ROLE_ADMIN: &'static str = "admin";
+pub const ROLE_MEMBER: &str = "member";
+pub const ROLE_ADMIN: &str = "admin";
 
 #[derive(Debug, FromRow, Serialize, Deserialize)]
 pub struct Channel {
     pub id: i64,
     pub ch_name: String,
     pub ch_description: String,
+    pub ch_topic: String,
     pub creator_id: i64,
     pub is_private: bool,  // public or private channel
     pub is_archived: bool, // ACTIVE, ARCHIVED
+    pub pins_admin_only: bool,
+    // a direct message channel between two users
+    pub is_direct: bool,
     pub created_at: chrono::DateTime<Utc>,
     pub updated_at: chrono::DateTime<Utc>,
 }
@@ -79,6 +83,66 @@ impl<'a> ChanRepository<'a> {
         Ok(channel)
     }
 
+    /// The direct message channel between two users, created with both as members on first
+    /// use. Concurrent callers for the same pair end up with the same channel.
+    pub async fn get_or_create_direct(
+        &self,
+        user_a: i64,
+        user_b: i64,
+    ) -> Result<Channel, AppError> {
+        let dm_key = format!("{}:{}", user_a.min(user_b), user_a.max(user_b));
+        sqlx::query(
+            r#"
+            INSERT INTO channels (ch_name, ch_description, creator_id, is_private, is_direct, dm_key)
+            VALUES ($1, '', $2, TRUE, TRUE, $3)
+            ON CONFLICT (dm_key) DO NOTHING
+            "#,
+        )
+        // a random name, so an existing channel cannot squat it.
+        .bind(format!("dm-{}", nanoid::nanoid!(16)))
+        .bind(user_a)
+        .bind(&dm_key)
+        .execute(self.pool)
+        .await?;
+
+        let channel: Channel = sqlx::query_as(
+            r#"
+            SELECT * FROM channels WHERE dm_key = $1
+            "#,
+        )
+        .bind(&dm_key)
+        .fetch_one(self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO channel_members (user_id, channel_id, member_role)
+            SELECT u, $1, $2 FROM UNNEST($3::BIGINT[]) AS u
+            ON CONFLICT (user_id, channel_id) DO NOTHING
+            "#,
+        )
+        .bind(channel.id)
+        .bind(ROLE_MEMBER)
+        .bind(vec![user_a, user_b])
+        .execute(self.pool)
+        .await?;
+
+        Ok(channel)
+    }
+
+    pub async fn get_by_name(&self, ch_name: &str) -> Result<Option<Channel>, AppError> {
+        let channel = sqlx::query_as(
+            r#"
+            SELECT * FROM channels WHERE ch_name = $1
+            "#,
+        )
+        .bind(ch_name)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(channel)
+    }
+
     // Update
     pub async fn update(&self, channel: &Channel) -> Result<Option<Channel>, AppError> {
         let updated_channel = sqlx::query_as(
@@ -87,16 +151,21 @@ impl<'a> ChanRepository<'a> {
             SET 
                 ch_name = $1,
                 ch_description = $2,
-                is_private = $3,
-                is_archived = $4,
-            WHERE id = $5
+                ch_topic = $3,
+                is_private = $4,
+                is_archived = $5,
+                pins_admin_only = $6,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE id = $7
             RETURNING *
             "#,
         )
         .bind(&channel.ch_name)
         .bind(&channel.ch_description)
+        .bind(&channel.ch_topic)
         .bind(&channel.is_private)
         .bind(&channel.is_archived)
+        .bind(channel.pins_admin_only)
         .bind(channel.id)
         .fetch_optional(self.pool)
         .await?;
@@ -104,29 +173,64 @@ impl<'a> ChanRepository<'a> {
         Ok(updated_channel)
     }
 
-    pub async fn list_all(&self, creator_id: i64) -> Result<Vec<Channel>, AppError> {
+    pub async fn list_all(
+        &self,
+        creator_id: i64,
+        include_archived: bool,
+    ) -> Result<Vec<Channel>, AppError> {
         let channels = sqlx::query_as(
             r#"
-            SELECT * FROM channels WHERE creator_id=$1
+            SELECT * FROM channels WHERE creator_id=$1 AND ($2 OR NOT is_archived)
             "#,
         )
         .bind(creator_id)
+        .bind(include_archived)
         .fetch_all(self.pool)
         .await?;
 
         Ok(channels)
     }
 
-    pub async fn list_user_channels(&self, user_id: i64) -> Result<Vec<Channel>, AppError> {
+    pub async fn list_user_channels(
+        &self,
+        user_id: i64,
+        include_archived: bool,
+    ) -> Result<Vec<Channel>, AppError> {
         let chan_members = self.list_chan_members_by_user(user_id).await?;
         let channel_ids: Vec<i64> = chan_members.into_iter().map(|cm| cm.channel_id).collect();
 
         let channels = sqlx::query_as(
             r#"
-            SELECT * FROM channels WHERE id = ANY($1)
+            SELECT * FROM channels WHERE id = ANY($1) AND ($2 OR NOT is_archived)
             "#,
         )
         .bind(channel_ids)
+        .bind(include_archived)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(channels)
+    }
+
+    // Search covers archived channels too; private channels only show up for their members.
+    pub async fn search(
+        &self,
+        user_id: i64,
+        query: &str,
+        limit: i64,
+    ) -> Result<Vec<Channel>, AppError> {
+        let channels = sqlx::query_as(
+            r#"
+            SELECT * FROM channels
+            WHERE (ch_name ILIKE '%' || $2 || '%' OR ch_description ILIKE '%' || $2 || '%')
+                AND (NOT is_private OR id IN (SELECT channel_id FROM channel_members WHERE user_id = $1))
+            ORDER BY is_archived ASC, ch_name ASC
+            LIMIT $3
+            "#,
+        )
+        .bind(user_id)
+        .bind(query)
+        .bind(limit)
         .fetch_all(self.pool)
         .await?;
 
@@ -205,4 +309,46 @@ impl<'a> ChanRepository<'a> {
 
         Ok(res.rows_affected())
     }
+
+    pub async fn get_channel_member(
+        &self,
+        channel_id: i64,
+        user_id: i64,
+    ) -> Result<Option<ChannelMembers>, AppError> {
+        let chan_member = sqlx::query_as(
+            r#"
+            SELECT * FROM channel_members
+            WHERE user_id=$1 AND channel_id=$2
+            "#,
+        )
+        .bind(user_id)
+        .bind(channel_id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(chan_member)
+    }
+
+    pub async fn update_member_role(
+        &self,
+        channel_id: i64,
+        user_id: i64,
+        member_role: &str,
+    ) -> Result<Option<ChannelMembers>, AppError> {
+        let chan_member = sqlx::query_as(
+            r#"
+            UPDATE channel_members
+            SET member_role = $3
+            WHERE user_id=$1 AND channel_id=$2
+            RETURNING *
+            "#,
+        )
+        .bind(user_id)
+        .bind(channel_id)
+        .bind(member_role)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(chan_member)
+    }
 }
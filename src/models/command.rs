@@ -0,0 +1,98 @@
+use chrono::Utc;
+use sqlx::{FromRow, PgPool};
+
+use crate::errors::AppError;
+
+#[derive(Debug, FromRow)]
+pub struct SlashCommand {
+    pub id: i64,
+    pub name: String,
+    pub description: String,
+    pub usage_hint: String,
+    pub url: String,
+    pub secret: String,
+    pub creator_id: i64,
+    pub created_at: chrono::DateTime<Utc>,
+    pub updated_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct CreateSlashCommand {
+    pub name: String,
+    pub description: String,
+    pub usage_hint: String,
+    pub url: String,
+    pub secret: String,
+    pub creator_id: i64,
+}
+
+#[derive(Debug)]
+pub struct SlashCommandStore<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> SlashCommandStore<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Returns None when the name is taken.
+    pub async fn create(&self, cmd: &CreateSlashCommand) -> Result<Option<SlashCommand>, AppError> {
+        let created = sqlx::query_as(
+            r#"
+            INSERT INTO slash_commands (name, description, usage_hint, url, secret, creator_id)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (name) DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(&cmd.name)
+        .bind(&cmd.description)
+        .bind(&cmd.usage_hint)
+        .bind(&cmd.url)
+        .bind(&cmd.secret)
+        .bind(cmd.creator_id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(created)
+    }
+
+    pub async fn get_by_name(&self, name: &str) -> Result<Option<SlashCommand>, AppError> {
+        let cmd = sqlx::query_as(
+            r#"
+            SELECT * FROM slash_commands WHERE name = $1
+            "#,
+        )
+        .bind(name)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(cmd)
+    }
+
+    pub async fn list(&self) -> Result<Vec<SlashCommand>, AppError> {
+        let cmds = sqlx::query_as(
+            r#"
+            SELECT * FROM slash_commands ORDER BY name ASC
+            "#,
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(cmds)
+    }
+
+    pub async fn delete(&self, id: i64) -> Result<u64, AppError> {
+        let res = sqlx::query(
+            r#"
+            DELETE FROM slash_commands WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(res.rows_affected())
+    }
+}
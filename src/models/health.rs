@@ -0,0 +1,32 @@
+use sqlx::PgPool;
+
+use crate::errors::AppError;
+
+#[derive(Debug)]
+pub struct HealthStore<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> HealthStore<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn ping(&self) -> Result<(), AppError> {
+        sqlx::query("SELECT 1").execute(self.pool).await?;
+        Ok(())
+    }
+
+    // The versions `sqlx migrate run` recorded as applied; fails if it never ran.
+    pub async fn applied_migrations(&self) -> Result<Vec<i64>, AppError> {
+        let versions = sqlx::query_scalar(
+            r#"
+            SELECT version FROM _sqlx_migrations WHERE success ORDER BY version
+            "#,
+        )
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(versions)
+    }
+}
@@ -1,9 +1,10 @@
 use chrono::Utc;
-use sqlx::{FromRow, PgPool};
+use sqlx::{FromRow, PgPool, Postgres, Transaction};
+use tracing::debug;
 
 use crate::errors::AppError;
 
-#[derive(sqlx::Type, Debug, PartialEq, Eq)]
+#[derive(sqlx::Type, Debug, Clone, PartialEq, Eq)]
 #[sqlx(type_name = "message_content_type", rename_all = "lowercase")]
 pub enum MessageContentType {
     Text,
@@ -11,6 +12,7 @@ pub enum MessageContentType {
     Video,
     File,
     System,
+    Poll,
 }
 
 #[derive(Debug, FromRow)]
@@ -23,10 +25,45 @@ pub struct Message {
     pub text_content: String,
     pub media_url: Option<String>,
     pub media_metadata: serde_json::Value,
+    pub rich_text: serde_json::Value,
+    pub plain_text: String,
+    pub unfurls: serde_json::Value,
+    pub sender_override: Option<serde_json::Value>,
+    pub attachments: serde_json::Value,
     pub created_at: chrono::DateTime<Utc>,
     pub updated_at: chrono::DateTime<Utc>,
 }
 
+#[derive(Debug, FromRow)]
+pub struct PinnedMessage {
+    pub id: i64,
+    pub channel_id: i64,
+    pub message_id: i64,
+    pub pinned_by: i64,
+    pub pinned_at: chrono::DateTime<Utc>,
+}
+
+/// What pinning did; the channel may have filled up since the caller looked at it.
+#[derive(Debug)]
+pub enum PinOutcome {
+    Pinned(PinnedMessage),
+    AlreadyPinned,
+    Full,
+}
+
+pub const MENTION_USER: &str = "user";
+pub const MENTION_CHANNEL: &str = "channel";
+pub const MENTION_HERE: &str = "here";
+
+#[derive(Debug, FromRow)]
+pub struct MessageMention {
+    pub id: i64,
+    pub message_id: i64,
+    pub mention_type: String,
+    pub user_id: Option<i64>,
+    pub created_at: chrono::DateTime<Utc>,
+}
+
 #[derive(Debug, FromRow)]
 pub struct CreateMessage {
     pub channel_id: i64,
@@ -36,6 +73,10 @@ pub struct CreateMessage {
     pub text_content: String,
     pub media_url: Option<String>,
     pub media_metadata: serde_json::Value,
+    pub rich_text: serde_json::Value,
+    pub plain_text: String,
+    pub sender_override: Option<serde_json::Value>,
+    pub attachments: serde_json::Value,
 }
 
 pub struct MessageStore<'a> {
@@ -47,73 +88,126 @@ impl<'a> MessageStore<'a> {
         Self { pool }
     }
 
-    pub async fn create(&self, new_message: &CreateMessage) -> Result<Message, AppError> {
-        println!("content type: {:?}", new_message.content_type);
+    /// Inserts a message together with its mentions, in one transaction.
+    pub async fn create(
+        &self,
+        new_message: &CreateMessage,
+        mention_types: &[&str],
+        user_ids: &[Option<i64>],
+    ) -> Result<Message, AppError> {
+        let mut tx = self.pool.begin().await?;
+        let message = insert_message(&mut tx, new_message, mention_types, user_ids).await?;
+        tx.commit().await?;
+        Ok(message)
+    }
 
-        // // as "state: ServiceState"
+    pub async fn get_by_id(&self, id: i64) -> Result<Option<Message>, AppError> {
         let message = sqlx::query_as(
             r#"
-            INSERT INTO messages (
-                channel_id, 
-                sender_id, 
-                parent_msg_id, 
-                content_type,  
-                text_content, 
-                media_url, 
-                media_metadata
-            )
-            VALUES ($1, $2, $3, $4, $5, $6, $7::jsonb)
-            RETURNING *
+            SELECT * FROM messages WHERE id = $1
             "#,
         )
-        .bind(new_message.channel_id)
-        .bind(new_message.sender_id)
-        .bind(new_message.parent_msg_id)
-        .bind(&new_message.content_type)
-        .bind(&new_message.text_content)
-        .bind(&new_message.media_url)
-        .bind(&new_message.media_metadata)
-        .fetch_one(self.pool)
+        .bind(id)
+        .fetch_optional(self.pool)
         .await?;
 
         Ok(message)
     }
 
-    pub async fn get_by_id(&self, id: i64) -> Result<Option<Message>, AppError> {
-        let message = sqlx::query_as(
+    pub async fn get_by_ids(&self, ids: Vec<i64>) -> Result<Vec<Message>, AppError> {
+        let messages = sqlx::query_as(
             r#"
-            SELECT * FROM messages WHERE id = $1
+            SELECT * FROM messages WHERE id = any($1)
             "#,
         )
-        .bind(id)
-        .fetch_optional(self.pool)
+        .bind(ids)
+        .fetch_all(self.pool)
         .await?;
 
-        Ok(message)
+        Ok(messages)
     }
 
-    pub async fn update(&self, message: &Message) -> Result<Option<Message>, AppError> {
-        let updated_message = sqlx::query_as(
+    /// Updates a message's content and replaces its mentions, in one transaction.
+    pub async fn update(
+        &self,
+        message: &Message,
+        mention_types: &[&str],
+        user_ids: &[Option<i64>],
+    ) -> Result<Option<Message>, AppError> {
+        let mut tx = self.pool.begin().await?;
+        let updated_message: Option<Message> = sqlx::query_as(
             r#"
             UPDATE messages
             SET 
                 text_content = $1,
                 media_url = $2,
-                media_metadata = $3,
-            WHERE id = $4
+                media_metadata = $3::jsonb,
+                rich_text = $4::jsonb,
+                plain_text = $5,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE id = $6
             RETURNING *
             "#,
         )
         .bind(&message.text_content)
         .bind(&message.media_url)
         .bind(&message.media_metadata)
+        .bind(&message.rich_text)
+        .bind(&message.plain_text)
         .bind(&message.id)
-        .fetch_optional(self.pool)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if updated_message.is_none() {
+            return Ok(None);
+        }
+
+        // an edit may have removed every mention, so clear them even when there are none.
+        sqlx::query(
+            r#"
+            DELETE FROM message_mentions WHERE message_id = $1
+            "#,
+        )
+        .bind(message.id)
+        .execute(&mut *tx)
         .await?;
+        insert_mentions(&mut tx, message.id, mention_types, user_ids).await?;
 
+        tx.commit().await?;
         Ok(updated_message)
     }
 
+    pub async fn set_unfurls(
+        &self,
+        id: i64,
+        unfurls: &serde_json::Value,
+    ) -> Result<Option<Message>, AppError> {
+        let message = sqlx::query_as(
+            r#"
+            UPDATE messages SET unfurls = $2::jsonb WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(unfurls)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(message)
+    }
+
+    pub async fn delete(&self, id: i64) -> Result<u64, AppError> {
+        let res = sqlx::query(
+            r#"
+            DELETE FROM messages WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(res.rows_affected())
+    }
+
     pub async fn list_by_channel(
         &self,
         channel_id: i64,
@@ -151,4 +245,214 @@ impl<'a> MessageStore<'a> {
 
         Ok(replies)
     }
+
+    /// Pins a message unless the channel already has `max_pins`. The channel row is locked
+    /// while counting, so concurrent pins can't both take the last slot.
+    pub async fn pin(
+        &self,
+        channel_id: i64,
+        message_id: i64,
+        pinned_by: i64,
+        max_pins: i64,
+    ) -> Result<PinOutcome, AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            SELECT id FROM channels WHERE id = $1 FOR UPDATE
+            "#,
+        )
+        .bind(channel_id)
+        .execute(&mut *tx)
+        .await?;
+
+        let total: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM pinned_messages WHERE channel_id = $1
+            "#,
+        )
+        .bind(channel_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if total >= max_pins {
+            return Ok(PinOutcome::Full);
+        }
+
+        let pin = sqlx::query_as(
+            r#"
+            INSERT INTO pinned_messages (channel_id, message_id, pinned_by)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (message_id) DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(channel_id)
+        .bind(message_id)
+        .bind(pinned_by)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(match pin {
+            Some(pin) => PinOutcome::Pinned(pin),
+            None => PinOutcome::AlreadyPinned,
+        })
+    }
+
+    pub async fn unpin(&self, channel_id: i64, message_id: i64) -> Result<u64, AppError> {
+        let res = sqlx::query(
+            r#"
+            DELETE FROM pinned_messages WHERE channel_id = $1 AND message_id = $2
+            "#,
+        )
+        .bind(channel_id)
+        .bind(message_id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(res.rows_affected())
+    }
+
+    pub async fn list_pins(&self, channel_id: i64) -> Result<Vec<PinnedMessage>, AppError> {
+        let pins = sqlx::query_as(
+            r#"
+            SELECT * FROM pinned_messages
+            WHERE channel_id = $1
+            ORDER BY pinned_at DESC
+            "#,
+        )
+        .bind(channel_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(pins)
+    }
+
+    pub async fn list_mentions(
+        &self,
+        message_ids: Vec<i64>,
+    ) -> Result<Vec<MessageMention>, AppError> {
+        let mentions = sqlx::query_as(
+            r#"
+            SELECT * FROM message_mentions
+            WHERE message_id = any($1)
+            ORDER BY id ASC
+            "#,
+        )
+        .bind(message_ids)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(mentions)
+    }
+
+    // Messages in the user's channels that name them directly or use @channel/@here,
+    // leaving out what they sent themselves.
+    pub async fn list_mentioning(
+        &self,
+        user_id: i64,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Message>, AppError> {
+        let messages = sqlx::query_as(
+            r#"
+            SELECT m.* FROM messages m
+            JOIN channels c ON c.id = m.channel_id
+            WHERE m.sender_id IS DISTINCT FROM $1
+                AND (
+                    c.creator_id = $1
+                    OR EXISTS (
+                        SELECT 1 FROM channel_members cm
+                        WHERE cm.channel_id = m.channel_id AND cm.user_id = $1
+                    )
+                )
+                AND EXISTS (
+                    SELECT 1 FROM message_mentions mm
+                    WHERE mm.message_id = m.id
+                        AND (mm.user_id = $1 OR mm.mention_type IN ('channel', 'here'))
+                )
+            ORDER BY m.created_at DESC, m.id DESC
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(user_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(messages)
+    }
+}
+
+async fn insert_mentions(
+    tx: &mut Transaction<'_, Postgres>,
+    message_id: i64,
+    mention_types: &[&str],
+    user_ids: &[Option<i64>],
+) -> Result<(), AppError> {
+    if mention_types.is_empty() {
+        return Ok(());
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO message_mentions (message_id, mention_type, user_id)
+        SELECT $1, * FROM UNNEST($2::VARCHAR[], $3::BIGINT[])
+        "#,
+    )
+    .bind(message_id)
+    .bind(mention_types)
+    .bind(user_ids)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Inserts a message and its mentions as part of the caller's transaction.
+pub(crate) async fn insert_message(
+    tx: &mut Transaction<'_, Postgres>,
+    new_message: &CreateMessage,
+    mention_types: &[&str],
+    user_ids: &[Option<i64>],
+) -> Result<Message, AppError> {
+    debug!("content type: {:?}", new_message.content_type);
+
+    // // as "state: ServiceState"
+    let message: Message = sqlx::query_as(
+        r#"
+        INSERT INTO messages (
+            channel_id, 
+            sender_id, 
+            parent_msg_id, 
+            content_type,  
+            text_content, 
+            media_url, 
+            media_metadata,
+            rich_text,
+            plain_text,
+            sender_override,
+            attachments
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7::jsonb, $8::jsonb, $9, $10::jsonb, $11::jsonb)
+        RETURNING *
+        "#,
+    )
+    .bind(new_message.channel_id)
+    .bind(new_message.sender_id)
+    .bind(new_message.parent_msg_id)
+    .bind(&new_message.content_type)
+    .bind(&new_message.text_content)
+    .bind(&new_message.media_url)
+    .bind(&new_message.media_metadata)
+    .bind(&new_message.rich_text)
+    .bind(&new_message.plain_text)
+    .bind(&new_message.sender_override)
+    .bind(&new_message.attachments)
+    .fetch_one(&mut **tx)
+    .await?;
+
+    insert_mentions(tx, message.id, mention_types, user_ids).await?;
+    Ok(message)
 }
@@ -1,3 +1,13 @@
+pub mod api_token;
+pub mod audit;
 pub mod channel;
+pub mod command;
+pub mod health;
 pub mod message;
+pub mod poll;
+pub mod reminder;
+pub mod saved;
+pub mod scheduled;
+pub mod unfurl;
 pub mod user;
+pub mod webhook;
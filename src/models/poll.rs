@@ -0,0 +1,259 @@
+use chrono::Utc;
+use sqlx::{FromRow, PgPool};
+
+use crate::{
+    errors::AppError,
+    models::message::{CreateMessage, Message, insert_message},
+};
+
+#[derive(Debug, FromRow)]
+pub struct Poll {
+    pub message_id: i64,
+    pub channel_id: i64,
+    pub creator_id: i64,
+    pub options: Vec<String>,
+    pub allow_multiple: bool,
+    pub is_anonymous: bool,
+    pub closes_at: Option<chrono::DateTime<Utc>>,
+    pub closed_at: Option<chrono::DateTime<Utc>>,
+    pub created_at: chrono::DateTime<Utc>,
+}
+
+impl Poll {
+    pub fn is_closed(&self) -> bool {
+        self.closed_at.is_some() || self.closes_at.is_some_and(|at| at <= Utc::now())
+    }
+}
+
+#[derive(Debug)]
+pub struct CreatePoll {
+    pub channel_id: i64,
+    pub creator_id: i64,
+    pub options: Vec<String>,
+    pub allow_multiple: bool,
+    pub is_anonymous: bool,
+    pub closes_at: Option<chrono::DateTime<Utc>>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct PollVote {
+    pub id: i64,
+    pub message_id: i64,
+    pub option_index: i32,
+    pub user_id: i64,
+    pub created_at: chrono::DateTime<Utc>,
+}
+
+/// What a vote did; the poll may have closed since the caller looked at it.
+#[derive(Debug, PartialEq)]
+pub enum VoteOutcome {
+    Voted,
+    Closed,
+}
+
+#[derive(Debug)]
+pub struct PollStore<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> PollStore<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Posts the poll's message and creates the poll in one transaction, so a poll message
+    /// never exists without its poll.
+    pub async fn create(
+        &self,
+        message: &CreateMessage,
+        mention_types: &[&str],
+        user_ids: &[Option<i64>],
+        poll: &CreatePoll,
+    ) -> Result<(Message, Poll), AppError> {
+        let mut tx = self.pool.begin().await?;
+        let message = insert_message(&mut tx, message, mention_types, user_ids).await?;
+
+        let created = sqlx::query_as(
+            r#"
+            INSERT INTO polls (message_id, channel_id, creator_id, options, allow_multiple, is_anonymous, closes_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING *
+            "#,
+        )
+        .bind(message.id)
+        .bind(poll.channel_id)
+        .bind(poll.creator_id)
+        .bind(&poll.options)
+        .bind(poll.allow_multiple)
+        .bind(poll.is_anonymous)
+        .bind(poll.closes_at)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok((message, created))
+    }
+
+    pub async fn get_by_message_id(&self, message_id: i64) -> Result<Option<Poll>, AppError> {
+        let poll = sqlx::query_as(
+            r#"
+            SELECT * FROM polls WHERE message_id = $1
+            "#,
+        )
+        .bind(message_id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(poll)
+    }
+
+    pub async fn list_votes(&self, message_id: i64) -> Result<Vec<PollVote>, AppError> {
+        let votes = sqlx::query_as(
+            r#"
+            SELECT * FROM poll_votes WHERE message_id = $1 ORDER BY created_at ASC, id ASC
+            "#,
+        )
+        .bind(message_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(votes)
+    }
+
+    /// Records a vote. With `replace`, for single-choice polls, the user's other votes are
+    /// dropped in the same transaction; the poll row is locked so that neither concurrent
+    /// votes nor closing the poll can interleave.
+    pub async fn vote(
+        &self,
+        message_id: i64,
+        option_index: i32,
+        user_id: i64,
+        replace: bool,
+    ) -> Result<VoteOutcome, AppError> {
+        let mut tx = self.pool.begin().await?;
+
+        let open: Option<(i64,)> = sqlx::query_as(
+            r#"
+            SELECT message_id FROM polls
+            WHERE message_id = $1
+                AND closed_at IS NULL
+                AND (closes_at IS NULL OR closes_at > CURRENT_TIMESTAMP)
+            FOR UPDATE
+            "#,
+        )
+        .bind(message_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if open.is_none() {
+            return Ok(VoteOutcome::Closed);
+        }
+
+        if replace {
+            sqlx::query(
+                r#"
+                DELETE FROM poll_votes WHERE message_id = $1 AND user_id = $2 AND option_index <> $3
+                "#,
+            )
+            .bind(message_id)
+            .bind(user_id)
+            .bind(option_index)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO poll_votes (message_id, option_index, user_id)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (message_id, option_index, user_id) DO NOTHING
+            "#,
+        )
+        .bind(message_id)
+        .bind(option_index)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(VoteOutcome::Voted)
+    }
+
+    // Removes one vote, or all of the user's votes when `option_index` is None.
+    pub async fn unvote(
+        &self,
+        message_id: i64,
+        option_index: Option<i32>,
+        user_id: i64,
+    ) -> Result<u64, AppError> {
+        let res = sqlx::query(
+            r#"
+            DELETE FROM poll_votes
+            WHERE message_id = $1 AND user_id = $2 AND ($3::INT IS NULL OR option_index = $3)
+                AND message_id IN (
+                    SELECT message_id FROM polls
+                    WHERE closed_at IS NULL AND (closes_at IS NULL OR closes_at > CURRENT_TIMESTAMP)
+                )
+            "#,
+        )
+        .bind(message_id)
+        .bind(user_id)
+        .bind(option_index)
+        .execute(self.pool)
+        .await?;
+
+        Ok(res.rows_affected())
+    }
+
+    // None when the poll was already closed, so only one caller posts the result.
+    pub async fn close(&self, message_id: i64) -> Result<Option<Poll>, AppError> {
+        let poll = sqlx::query_as(
+            r#"
+            UPDATE polls SET closed_at = CURRENT_TIMESTAMP
+            WHERE message_id = $1 AND closed_at IS NULL
+            RETURNING *
+            "#,
+        )
+        .bind(message_id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(poll)
+    }
+
+    /// Closes the polls whose close time has passed and returns them, skipping rows another
+    /// scheduler holds.
+    pub async fn close_due(&self, limit: i64) -> Result<Vec<Poll>, AppError> {
+        let polls = sqlx::query_as(
+            r#"
+            UPDATE polls SET closed_at = closes_at
+            WHERE message_id IN (
+                SELECT message_id FROM polls
+                WHERE closed_at IS NULL AND closes_at <= CURRENT_TIMESTAMP
+                ORDER BY closes_at ASC
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(polls)
+    }
+
+    /// How many polls with a close time are waiting, and how many of those are already due.
+    pub async fn count_queued(&self) -> Result<(i64, i64), AppError> {
+        let counts = sqlx::query_as(
+            r#"
+            SELECT COUNT(*), COUNT(*) FILTER (WHERE closes_at <= CURRENT_TIMESTAMP)
+            FROM polls WHERE closed_at IS NULL AND closes_at IS NOT NULL
+            "#,
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(counts)
+    }
+}
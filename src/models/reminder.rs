@@ -0,0 +1,202 @@
+use chrono::Utc;
+use sqlx::{FromRow, PgPool};
+
+use crate::errors::AppError;
+
+pub const REMINDER_PENDING: &str = "pending";
+pub const REMINDER_DELIVERED: &str = "delivered";
+pub const REMINDER_COMPLETED: &str = "completed";
+
+#[derive(Debug, FromRow)]
+pub struct Reminder {
+    pub id: i64,
+    pub user_id: i64,
+    pub message_id: Option<i64>,
+    pub text: String,
+    pub remind_at: chrono::DateTime<Utc>,
+    pub status: String,
+    pub dm_message_id: Option<i64>,
+    pub delivered_at: Option<chrono::DateTime<Utc>>,
+    pub completed_at: Option<chrono::DateTime<Utc>>,
+    pub created_at: chrono::DateTime<Utc>,
+    pub updated_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct CreateReminder {
+    pub user_id: i64,
+    pub message_id: Option<i64>,
+    pub text: String,
+    pub remind_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct ReminderStore<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> ReminderStore<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, reminder: &CreateReminder) -> Result<Reminder, AppError> {
+        let created = sqlx::query_as(
+            r#"
+            INSERT INTO reminders (user_id, message_id, text, remind_at)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *
+            "#,
+        )
+        .bind(reminder.user_id)
+        .bind(reminder.message_id)
+        .bind(&reminder.text)
+        .bind(reminder.remind_at)
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(created)
+    }
+
+    pub async fn get_by_id(&self, id: i64) -> Result<Option<Reminder>, AppError> {
+        let reminder = sqlx::query_as(
+            r#"
+            SELECT * FROM reminders WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(reminder)
+    }
+
+    // Without a status, lists the reminders that are not completed yet.
+    pub async fn list_by_user(
+        &self,
+        user_id: i64,
+        status: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Reminder>, AppError> {
+        let reminders = sqlx::query_as(
+            r#"
+            SELECT * FROM reminders
+            WHERE user_id = $1
+                AND (($2::TEXT IS NULL AND status <> 'completed') OR status = $2)
+            ORDER BY remind_at ASC, id ASC
+            LIMIT $3 OFFSET $4
+            "#,
+        )
+        .bind(user_id)
+        .bind(status)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(reminders)
+    }
+
+    /// How many reminders are waiting, and how many of those are already due.
+    pub async fn count_queued(&self) -> Result<(i64, i64), AppError> {
+        let counts = sqlx::query_as(
+            r#"
+            SELECT COUNT(*), COUNT(*) FILTER (WHERE remind_at <= CURRENT_TIMESTAMP)
+            FROM reminders WHERE status = 'pending'
+            "#,
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(counts)
+    }
+
+    // Snoozing works on pending and delivered reminders alike; None once completed.
+    pub async fn snooze(
+        &self,
+        id: i64,
+        remind_at: chrono::DateTime<Utc>,
+    ) -> Result<Option<Reminder>, AppError> {
+        let reminder = sqlx::query_as(
+            r#"
+            UPDATE reminders
+            SET status = 'pending', remind_at = $2, delivered_at = NULL, updated_at = CURRENT_TIMESTAMP
+            WHERE id = $1 AND status <> 'completed'
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(remind_at)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(reminder)
+    }
+
+    pub async fn complete(&self, id: i64) -> Result<Option<Reminder>, AppError> {
+        let reminder = sqlx::query_as(
+            r#"
+            UPDATE reminders
+            SET status = 'completed', completed_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP
+            WHERE id = $1 AND status <> 'completed'
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(reminder)
+    }
+
+    pub async fn delete(&self, id: i64) -> Result<u64, AppError> {
+        let res = sqlx::query(
+            r#"
+            DELETE FROM reminders WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(res.rows_affected())
+    }
+
+    /// Marks due reminders delivered and returns them, skipping rows another worker holds.
+    pub async fn claim_due(&self, limit: i64) -> Result<Vec<Reminder>, AppError> {
+        let reminders = sqlx::query_as(
+            r#"
+            UPDATE reminders
+            SET status = 'delivered', delivered_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP
+            WHERE id IN (
+                SELECT id FROM reminders
+                WHERE status = 'pending' AND remind_at <= CURRENT_TIMESTAMP
+                ORDER BY remind_at ASC
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(reminders)
+    }
+
+    pub async fn set_dm_message(&self, id: i64, dm_message_id: i64) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE reminders SET dm_message_id = $2, updated_at = CURRENT_TIMESTAMP WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(dm_message_id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
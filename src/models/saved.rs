@@ -0,0 +1,132 @@
+use chrono::Utc;
+use sqlx::{FromRow, PgPool};
+
+use crate::errors::AppError;
+
+#[derive(Debug, FromRow)]
+pub struct SavedItem {
+    pub id: i64,
+    pub user_id: i64,
+    pub message_id: i64,
+    pub remind_at: Option<chrono::DateTime<Utc>>,
+    pub is_completed: bool,
+    pub completed_at: Option<chrono::DateTime<Utc>>,
+    pub created_at: chrono::DateTime<Utc>,
+    pub updated_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct SavedItemStore<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> SavedItemStore<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    // Saving an already saved message only refreshes its reminder.
+    pub async fn upsert(
+        &self,
+        user_id: i64,
+        message_id: i64,
+        remind_at: Option<chrono::DateTime<Utc>>,
+    ) -> Result<SavedItem, AppError> {
+        let item = sqlx::query_as(
+            r#"
+            INSERT INTO saved_items (user_id, message_id, remind_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id, message_id)
+            DO UPDATE SET remind_at = EXCLUDED.remind_at, updated_at = CURRENT_TIMESTAMP
+            RETURNING *
+            "#,
+        )
+        .bind(user_id)
+        .bind(message_id)
+        .bind(remind_at)
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(item)
+    }
+
+    pub async fn update(
+        &self,
+        user_id: i64,
+        message_id: i64,
+        remind_at: Option<chrono::DateTime<Utc>>,
+        is_completed: bool,
+    ) -> Result<Option<SavedItem>, AppError> {
+        let item = sqlx::query_as(
+            r#"
+            UPDATE saved_items
+            SET
+                remind_at = $3,
+                is_completed = $4,
+                completed_at = CASE
+                    WHEN NOT $4 THEN NULL
+                    WHEN is_completed THEN completed_at
+                    ELSE CURRENT_TIMESTAMP
+                END,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE user_id = $1 AND message_id = $2
+            RETURNING *
+            "#,
+        )
+        .bind(user_id)
+        .bind(message_id)
+        .bind(remind_at)
+        .bind(is_completed)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(item)
+    }
+
+    pub async fn delete(&self, user_id: i64, message_id: i64) -> Result<u64, AppError> {
+        let res = sqlx::query(
+            r#"
+            DELETE FROM saved_items WHERE user_id = $1 AND message_id = $2
+            "#,
+        )
+        .bind(user_id)
+        .bind(message_id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(res.rows_affected())
+    }
+
+    // Deleted messages cascade away; items in channels the user is no longer part of are
+    // skipped rather than removed so they come back after rejoining.
+    pub async fn list_visible(
+        &self,
+        user_id: i64,
+        is_completed: Option<bool>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<SavedItem>, AppError> {
+        let items = sqlx::query_as(
+            r#"
+            SELECT s.* FROM saved_items s
+            JOIN messages m ON m.id = s.message_id
+            WHERE s.user_id = $1
+                AND ($2::BOOLEAN IS NULL OR s.is_completed = $2)
+                AND EXISTS (
+                    SELECT 1 FROM channel_members cm
+                    WHERE cm.channel_id = m.channel_id AND cm.user_id = $1
+                )
+            ORDER BY s.created_at DESC, s.id DESC
+            LIMIT $3 OFFSET $4
+            "#,
+        )
+        .bind(user_id)
+        .bind(is_completed)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(items)
+    }
+}
@@ -0,0 +1,303 @@
+use chrono::Utc;
+use sqlx::{FromRow, PgPool};
+
+use crate::{errors::AppError, models::message::MessageContentType};
+
+pub const SCHEDULED_PENDING: &str = "pending";
+// Claimed by a scheduler until `claimed_at` plus the lease; a row left here by a crashed
+// instance is claimed again, so it may be posted twice but is never lost.
+pub const SCHEDULED_SENDING: &str = "sending";
+pub const SCHEDULED_SENT: &str = "sent";
+pub const SCHEDULED_FAILED: &str = "failed";
+pub const SCHEDULED_CANCELLED: &str = "cancelled";
+
+#[derive(Debug, FromRow)]
+pub struct ScheduledMessage {
+    pub id: i64,
+    pub channel_id: i64,
+    pub sender_id: i64,
+    pub parent_msg_id: Option<i64>,
+    pub content_type: MessageContentType,
+    pub text_content: String,
+    pub media_url: Option<String>,
+    pub media_metadata: serde_json::Value,
+    pub send_at: chrono::DateTime<Utc>,
+    pub status: String,
+    pub message_id: Option<i64>,
+    pub last_error: Option<String>,
+    pub claimed_at: Option<chrono::DateTime<Utc>>,
+    // how many times a scheduler has claimed it.
+    pub attempts: i32,
+    pub created_at: chrono::DateTime<Utc>,
+    pub updated_at: chrono::DateTime<Utc>,
+}
+
+impl ScheduledMessage {
+    /// Claimed before by a scheduler that never finished it, so it may already be posted.
+    pub fn is_reclaimed(&self) -> bool {
+        self.attempts > 1
+    }
+}
+
+#[derive(Debug)]
+pub struct CreateScheduledMessage {
+    pub channel_id: i64,
+    pub sender_id: i64,
+    pub parent_msg_id: Option<i64>,
+    pub content_type: MessageContentType,
+    pub text_content: String,
+    pub media_url: Option<String>,
+    pub media_metadata: serde_json::Value,
+    pub send_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct ScheduledMessageStore<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> ScheduledMessageStore<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, msg: &CreateScheduledMessage) -> Result<ScheduledMessage, AppError> {
+        let created = sqlx::query_as(
+            r#"
+            INSERT INTO scheduled_messages
+                (channel_id, sender_id, parent_msg_id, content_type, text_content, media_url, media_metadata, send_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING *
+            "#,
+        )
+        .bind(msg.channel_id)
+        .bind(msg.sender_id)
+        .bind(msg.parent_msg_id)
+        .bind(&msg.content_type)
+        .bind(&msg.text_content)
+        .bind(&msg.media_url)
+        .bind(&msg.media_metadata)
+        .bind(msg.send_at)
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(created)
+    }
+
+    pub async fn get_by_id(&self, id: i64) -> Result<Option<ScheduledMessage>, AppError> {
+        let msg = sqlx::query_as(
+            r#"
+            SELECT * FROM scheduled_messages WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(msg)
+    }
+
+    pub async fn list_by_sender(
+        &self,
+        sender_id: i64,
+        channel_id: Option<i64>,
+        status: &str,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<ScheduledMessage>, AppError> {
+        let msgs = sqlx::query_as(
+            r#"
+            SELECT * FROM scheduled_messages
+            WHERE sender_id = $1
+                AND ($2::BIGINT IS NULL OR channel_id = $2)
+                AND status = $3
+            ORDER BY send_at ASC, id ASC
+            LIMIT $4 OFFSET $5
+            "#,
+        )
+        .bind(sender_id)
+        .bind(channel_id)
+        .bind(status)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(msgs)
+    }
+
+    /// How many messages are waiting, and how many of those are already due.
+    pub async fn count_queued(&self) -> Result<(i64, i64), AppError> {
+        let counts = sqlx::query_as(
+            r#"
+            SELECT COUNT(*), COUNT(*) FILTER (WHERE send_at <= CURRENT_TIMESTAMP)
+            FROM scheduled_messages WHERE status = 'pending'
+            "#,
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(counts)
+    }
+
+    // Only pending messages can change; None when it was sent or cancelled meanwhile.
+    pub async fn update_pending(
+        &self,
+        id: i64,
+        text_content: &str,
+        send_at: chrono::DateTime<Utc>,
+    ) -> Result<Option<ScheduledMessage>, AppError> {
+        let msg = sqlx::query_as(
+            r#"
+            UPDATE scheduled_messages
+            SET text_content = $2, send_at = $3, updated_at = CURRENT_TIMESTAMP
+            WHERE id = $1 AND status = 'pending'
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(text_content)
+        .bind(send_at)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(msg)
+    }
+
+    pub async fn cancel_pending(&self, id: i64) -> Result<Option<ScheduledMessage>, AppError> {
+        let msg = sqlx::query_as(
+            r#"
+            UPDATE scheduled_messages
+            SET status = 'cancelled', updated_at = CURRENT_TIMESTAMP
+            WHERE id = $1 AND status = 'pending'
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(msg)
+    }
+
+    /// Moves due messages to `sending` for `lease_secs` and returns them, along with any
+    /// whose lease ran out before they were finished, up to `max_attempts` claims. Rows
+    /// another scheduler holds are skipped rather than waited for.
+    pub async fn claim_due(
+        &self,
+        limit: i64,
+        lease_secs: f64,
+        max_attempts: i32,
+    ) -> Result<Vec<ScheduledMessage>, AppError> {
+        let msgs = sqlx::query_as(
+            r#"
+            UPDATE scheduled_messages
+            SET
+                status = 'sending',
+                attempts = attempts + 1,
+                claimed_at = CURRENT_TIMESTAMP,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE id IN (
+                SELECT id FROM scheduled_messages
+                WHERE (status = 'pending' AND send_at <= CURRENT_TIMESTAMP)
+                    OR (
+                        status = 'sending'
+                        AND claimed_at <= CURRENT_TIMESTAMP - make_interval(secs => $2)
+                        AND attempts < $3
+                    )
+                ORDER BY send_at ASC
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(limit)
+        .bind(lease_secs)
+        .bind(max_attempts)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(msgs)
+    }
+
+    /// Fails messages whose last allowed claim expired too, and returns how many.
+    pub async fn fail_abandoned(
+        &self,
+        lease_secs: f64,
+        max_attempts: i32,
+    ) -> Result<u64, AppError> {
+        let res = sqlx::query(
+            r#"
+            UPDATE scheduled_messages
+            SET status = 'failed', last_error = 'abandoned by the scheduler',
+                updated_at = CURRENT_TIMESTAMP
+            WHERE status = 'sending'
+                AND claimed_at <= CURRENT_TIMESTAMP - make_interval(secs => $1)
+                AND attempts >= $2
+            "#,
+        )
+        .bind(lease_secs)
+        .bind(max_attempts)
+        .execute(self.pool)
+        .await?;
+
+        Ok(res.rows_affected())
+    }
+
+    pub async fn finish(
+        &self,
+        id: i64,
+        status: &str,
+        message_id: Option<i64>,
+        error: Option<&str>,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE scheduled_messages
+            SET status = $2, message_id = $3, last_error = $4, updated_at = CURRENT_TIMESTAMP
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(status)
+        .bind(message_id)
+        .bind(error)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_reclaimed() {
+        let now = Utc::now();
+        let mut msg = ScheduledMessage {
+            id: 1,
+            channel_id: 2,
+            sender_id: 3,
+            parent_msg_id: None,
+            content_type: MessageContentType::Text,
+            text_content: "standup".to_string(),
+            media_url: None,
+            media_metadata: serde_json::Value::Null,
+            send_at: now,
+            status: SCHEDULED_SENDING.to_string(),
+            message_id: None,
+            last_error: None,
+            claimed_at: Some(now),
+            attempts: 1,
+            created_at: now,
+            updated_at: now,
+        };
+        assert!(!msg.is_reclaimed());
+
+        msg.attempts = 2;
+        assert!(msg.is_reclaimed());
+    }
+}
@@ -0,0 +1,52 @@
+use chrono::Utc;
+use sqlx::{FromRow, PgPool};
+
+use crate::errors::AppError;
+
+#[derive(Debug, FromRow)]
+pub struct CachedUnfurl {
+    pub url: String,
+    pub card: Option<serde_json::Value>,
+    pub fetched_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct UnfurlStore<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> UnfurlStore<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn get(&self, url: &str) -> Result<Option<CachedUnfurl>, AppError> {
+        let cached = sqlx::query_as(
+            r#"
+            SELECT * FROM link_unfurls WHERE url = $1
+            "#,
+        )
+        .bind(url)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(cached)
+    }
+
+    pub async fn put(&self, url: &str, card: Option<serde_json::Value>) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO link_unfurls (url, card)
+            VALUES ($1, $2::jsonb)
+            ON CONFLICT (url)
+            DO UPDATE SET card = EXCLUDED.card, fetched_at = CURRENT_TIMESTAMP
+            "#,
+        )
+        .bind(url)
+        .bind(card)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
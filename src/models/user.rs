@@ -11,7 +11,15 @@ pub struct User {
     pub avatar_url: String,
     pub password_hash: String,
     pub display_name: String,
+    pub title: String,
+    pub status_text: String,
+    pub timezone: String,
     pub is_active: bool,
+    pub is_admin: bool,
+    pub must_reset_password: bool,
+    pub is_bot: bool,
+    // the user who manages a bot
+    pub owner_id: Option<i64>,
     pub created_at: chrono::DateTime<Utc>,
     pub updated_at: chrono::DateTime<Utc>,
 }
@@ -23,15 +31,22 @@ pub struct CreateUser {
     pub password_hash: String,
     pub display_name: String,
     pub is_active: bool,
+    pub is_bot: bool,
+    pub owner_id: Option<i64>,
 }
 
-#[derive(Debug)]
+// None fields are left unchanged by `UserRepository::update`.
+#[derive(Debug, Default)]
 pub struct UpdateUser {
     pub id: i64,
-    pub avatar_url: String,
-    pub password_hash: String,
-    pub display_name: String,
-    pub is_active: bool,
+    pub avatar_url: Option<String>,
+    pub password_hash: Option<String>,
+    pub display_name: Option<String>,
+    pub title: Option<String>,
+    pub status_text: Option<String>,
+    pub timezone: Option<String>,
+    pub is_active: Option<bool>,
+    pub must_reset_password: Option<bool>,
 }
 
 #[derive(Debug)]
@@ -47,9 +62,9 @@ impl<'a> UserRepository<'a> {
     pub async fn create(&self, user: &CreateUser) -> Result<User, AppError> {
         let user = sqlx::query_as(
             r#"
-            INSERT INTO users (username, password_hash, display_name, is_active, avatar_url)
-            VALUES ($1, $2, $3, $4, $5)
-            RETURNING id, username, password_hash, display_name, is_active, avatar_url, created_at, updated_at
+            INSERT INTO users (username, password_hash, display_name, is_active, avatar_url, is_bot, owner_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id, username, password_hash, display_name, title, status_text, timezone, is_active, is_admin, must_reset_password, is_bot, owner_id, avatar_url, created_at, updated_at
             "#,
         )
         .bind(&user.username)
@@ -57,6 +72,8 @@ impl<'a> UserRepository<'a> {
         .bind(&user.display_name)
         .bind(&user.is_active)
         .bind(&user.avatar_url)
+        .bind(user.is_bot)
+        .bind(user.owner_id)
         .fetch_one(self.pool)
         .await?;
 
@@ -75,16 +92,25 @@ impl<'a> UserRepository<'a> {
                 avatar_url = COALESCE($2, avatar_url),
                 display_name = COALESCE($3, display_name),
                 password_hash = COALESCE($4, password_hash),
-                is_active = $5
+                title = COALESCE($5, title),
+                status_text = COALESCE($6, status_text),
+                timezone = COALESCE($7, timezone),
+                is_active = COALESCE($8, is_active),
+                must_reset_password = COALESCE($9, must_reset_password),
+                updated_at = CURRENT_TIMESTAMP
             WHERE id = $1
-            RETURNING id, username, display_name, is_active, avatar_url
+            RETURNING id, username, password_hash, display_name, title, status_text, timezone, is_active, is_admin, must_reset_password, is_bot, owner_id, avatar_url, created_at, updated_at
             "#,
         )
         .bind(user.id)
         .bind(&user.avatar_url)
         .bind(&user.display_name)
         .bind(&user.password_hash)
+        .bind(&user.title)
+        .bind(&user.status_text)
+        .bind(&user.timezone)
         .bind(user.is_active)
+        .bind(user.must_reset_password)
         .fetch_optional(self.pool)
         .await?;
 
@@ -94,7 +120,7 @@ impl<'a> UserRepository<'a> {
     pub async fn get_by_id(&self, id: i64) -> Result<Option<User>, AppError> {
         let user = sqlx::query_as(
             r#"
-            SELECT id, username, password_hash,display_name, is_active, avatar_url, created_at, updated_at
+            SELECT id, username, password_hash, display_name, title, status_text, timezone, is_active, is_admin, must_reset_password, is_bot, owner_id, avatar_url, created_at, updated_at
             FROM users
             WHERE id = $1
             "#,
@@ -109,7 +135,7 @@ impl<'a> UserRepository<'a> {
     pub async fn get_user_by_ids(&self, ids: Vec<i64>) -> Result<Vec<User>, AppError> {
         let users = sqlx::query_as(
             r#"
-            SELECT id, username, password_hash,display_name, is_active, avatar_url, created_at, updated_at
+            SELECT id, username, password_hash, display_name, title, status_text, timezone, is_active, is_admin, must_reset_password, is_bot, owner_id, avatar_url, created_at, updated_at
             FROM users
             WHERE id = any($1)
             "#,
@@ -124,7 +150,7 @@ impl<'a> UserRepository<'a> {
     pub async fn get_by_username(&self, user_name: &String) -> Result<Option<User>, AppError> {
         let user = sqlx::query_as(
             r#"
-            SELECT id, username, password_hash,display_name, is_active, avatar_url, created_at, updated_at
+            SELECT id, username, password_hash, display_name, title, status_text, timezone, is_active, is_admin, must_reset_password, is_bot, owner_id, avatar_url, created_at, updated_at
             FROM users
             WHERE username = $1
             "#,
@@ -135,4 +161,73 @@ impl<'a> UserRepository<'a> {
 
         Ok(user)
     }
+
+    pub async fn get_by_usernames(&self, user_names: &[String]) -> Result<Vec<User>, AppError> {
+        let users = sqlx::query_as(
+            r#"
+            SELECT id, username, password_hash, display_name, title, status_text, timezone, is_active, is_admin, must_reset_password, is_bot, owner_id, avatar_url, created_at, updated_at
+            FROM users
+            WHERE username = any($1)
+            "#,
+        )
+        .bind(user_names)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(users)
+    }
+
+    pub async fn search(
+        &self,
+        query: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<User>, AppError> {
+        let users = sqlx::query_as(
+            r#"
+            SELECT id, username, password_hash, display_name, title, status_text, timezone, is_active, is_admin, must_reset_password, is_bot, owner_id, avatar_url, created_at, updated_at
+            FROM users
+            WHERE $1::TEXT IS NULL OR username ILIKE '%' || $1 || '%' OR display_name ILIKE '%' || $1 || '%'
+            ORDER BY id ASC
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(query)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(users)
+    }
+
+    pub async fn count(&self, query: Option<&str>) -> Result<i64, AppError> {
+        let total: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM users
+            WHERE $1::TEXT IS NULL OR username ILIKE '%' || $1 || '%' OR display_name ILIKE '%' || $1 || '%'
+            "#,
+        )
+        .bind(query)
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(total)
+    }
+
+    pub async fn list_bots(&self, owner_id: i64) -> Result<Vec<User>, AppError> {
+        let users = sqlx::query_as(
+            r#"
+            SELECT id, username, password_hash, display_name, title, status_text, timezone, is_active, is_admin, must_reset_password, is_bot, owner_id, avatar_url, created_at, updated_at
+            FROM users
+            WHERE owner_id = $1 AND is_bot AND is_active
+            ORDER BY id ASC
+            "#,
+        )
+        .bind(owner_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(users)
+    }
 }
@@ -0,0 +1,492 @@
+use chrono::Utc;
+use serde::Serialize;
+use sqlx::{FromRow, PgPool};
+
+use crate::errors::AppError;
+
+pub const DELIVERY_PENDING: &str = "pending";
+pub const DELIVERY_DELIVERED: &str = "delivered";
+pub const DELIVERY_FAILED: &str = "failed";
+
+#[derive(Debug, FromRow)]
+pub struct OutgoingWebhook {
+    pub id: i64,
+    pub channel_id: i64,
+    pub creator_id: i64,
+    pub url: String,
+    pub secret: String,
+    pub event_types: Vec<String>,
+    pub is_active: bool,
+    pub consecutive_failures: i32,
+    pub created_at: chrono::DateTime<Utc>,
+    pub updated_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct CreateWebhook {
+    pub channel_id: i64,
+    pub creator_id: i64,
+    pub url: String,
+    pub secret: String,
+    pub event_types: Vec<String>,
+}
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub event_type: String,
+    pub payload: serde_json::Value,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: chrono::DateTime<Utc>,
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: chrono::DateTime<Utc>,
+    pub updated_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct DeliveryAttempt {
+    pub id: i64,
+    pub delivery_id: i64,
+    pub attempt: i32,
+    pub status_code: Option<i32>,
+    pub error: Option<String>,
+    pub duration_ms: i32,
+    pub created_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct IncomingWebhook {
+    pub id: i64,
+    pub channel_id: i64,
+    pub creator_id: i64,
+    pub bot_user_id: i64,
+    pub name: String,
+    pub token_hash: String,
+    pub revoked_at: Option<chrono::DateTime<Utc>>,
+    pub last_used_at: Option<chrono::DateTime<Utc>>,
+    pub created_at: chrono::DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct CreateIncomingWebhook {
+    pub channel_id: i64,
+    pub creator_id: i64,
+    pub bot_user_id: i64,
+    pub name: String,
+    pub token_hash: String,
+}
+
+#[derive(Debug)]
+pub struct WebhookStore<'a> {
+    pool: &'a PgPool,
+}
+
+impl<'a> WebhookStore<'a> {
+    pub fn new(pool: &'a PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, hook: &CreateWebhook) -> Result<OutgoingWebhook, AppError> {
+        let created = sqlx::query_as(
+            r#"
+            INSERT INTO outgoing_webhooks (channel_id, creator_id, url, secret, event_types)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+            "#,
+        )
+        .bind(hook.channel_id)
+        .bind(hook.creator_id)
+        .bind(&hook.url)
+        .bind(&hook.secret)
+        .bind(&hook.event_types)
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(created)
+    }
+
+    pub async fn get_by_id(&self, id: i64) -> Result<Option<OutgoingWebhook>, AppError> {
+        let hook = sqlx::query_as(
+            r#"
+            SELECT * FROM outgoing_webhooks WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(hook)
+    }
+
+    pub async fn list_by_channel(&self, channel_id: i64) -> Result<Vec<OutgoingWebhook>, AppError> {
+        let hooks = sqlx::query_as(
+            r#"
+            SELECT * FROM outgoing_webhooks WHERE channel_id = $1 ORDER BY id ASC
+            "#,
+        )
+        .bind(channel_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(hooks)
+    }
+
+    pub async fn delete(&self, id: i64) -> Result<u64, AppError> {
+        let res = sqlx::query(
+            r#"
+            DELETE FROM outgoing_webhooks WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(res.rows_affected())
+    }
+
+    // Re-enabling also forgets the failures that disabled the webhook.
+    pub async fn set_active(
+        &self,
+        id: i64,
+        is_active: bool,
+    ) -> Result<Option<OutgoingWebhook>, AppError> {
+        let hook = sqlx::query_as(
+            r#"
+            UPDATE outgoing_webhooks
+            SET
+                is_active = $2,
+                consecutive_failures = CASE WHEN $2 THEN 0 ELSE consecutive_failures END,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(is_active)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(hook)
+    }
+
+    // Queues the event for every active webhook of the channel subscribed to it.
+    pub async fn enqueue(
+        &self,
+        channel_id: i64,
+        event_type: &str,
+        payload: &serde_json::Value,
+    ) -> Result<u64, AppError> {
+        let res = sqlx::query(
+            r#"
+            INSERT INTO webhook_deliveries (webhook_id, event_type, payload)
+            SELECT id, $2, $3::jsonb FROM outgoing_webhooks
+            WHERE channel_id = $1 AND is_active AND $2 = ANY(event_types)
+            "#,
+        )
+        .bind(channel_id)
+        .bind(event_type)
+        .bind(payload)
+        .execute(self.pool)
+        .await?;
+
+        Ok(res.rows_affected())
+    }
+
+    // Claims due deliveries for `lease_secs`; should this worker die, they become due again
+    // once the lease runs out. SKIP LOCKED keeps concurrent workers off each other's rows.
+    pub async fn claim_due(
+        &self,
+        limit: i64,
+        lease_secs: f64,
+    ) -> Result<Vec<WebhookDelivery>, AppError> {
+        let deliveries = sqlx::query_as(
+            r#"
+            UPDATE webhook_deliveries
+            SET
+                attempts = attempts + 1,
+                next_attempt_at = CURRENT_TIMESTAMP + make_interval(secs => $2),
+                updated_at = CURRENT_TIMESTAMP
+            WHERE id IN (
+                SELECT id FROM webhook_deliveries
+                WHERE status = 'pending' AND next_attempt_at <= CURRENT_TIMESTAMP
+                ORDER BY next_attempt_at ASC
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(limit)
+        .bind(lease_secs)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(deliveries)
+    }
+
+    /// How many deliveries are waiting, and how many of those are already due.
+    pub async fn count_queued(&self) -> Result<(i64, i64), AppError> {
+        let counts = sqlx::query_as(
+            r#"
+            SELECT COUNT(*), COUNT(*) FILTER (WHERE next_attempt_at <= CURRENT_TIMESTAMP)
+            FROM webhook_deliveries WHERE status = 'pending'
+            "#,
+        )
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(counts)
+    }
+
+    pub async fn log_attempt(
+        &self,
+        delivery: &WebhookDelivery,
+        status_code: Option<i32>,
+        error: Option<&str>,
+        duration_ms: i32,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO webhook_delivery_attempts (delivery_id, attempt, status_code, error, duration_ms)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(delivery.id)
+        .bind(delivery.attempts)
+        .bind(status_code)
+        .bind(error)
+        .bind(duration_ms)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // `retry_in_secs` of None ends the delivery with `status`, otherwise it stays pending.
+    pub async fn finish_attempt(
+        &self,
+        delivery_id: i64,
+        status: &str,
+        status_code: Option<i32>,
+        error: Option<&str>,
+        retry_in_secs: Option<f64>,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE webhook_deliveries
+            SET
+                status = $2,
+                last_status_code = $3,
+                last_error = $4,
+                next_attempt_at = CURRENT_TIMESTAMP + make_interval(secs => COALESCE($5, 0)),
+                updated_at = CURRENT_TIMESTAMP
+            WHERE id = $1
+            "#,
+        )
+        .bind(delivery_id)
+        .bind(status)
+        .bind(status_code)
+        .bind(error)
+        .bind(retry_in_secs)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn reset_failures(&self, webhook_id: i64) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE outgoing_webhooks SET consecutive_failures = 0
+            WHERE id = $1 AND consecutive_failures > 0
+            "#,
+        )
+        .bind(webhook_id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // Counts a failed attempt and disables the webhook once `disable_after` failures in a row
+    // were seen. Returns true when this call disabled it.
+    pub async fn record_failure(
+        &self,
+        webhook_id: i64,
+        disable_after: i32,
+    ) -> Result<bool, AppError> {
+        let disabled: Option<bool> = sqlx::query_scalar(
+            r#"
+            UPDATE outgoing_webhooks
+            SET
+                consecutive_failures = consecutive_failures + 1,
+                is_active = is_active AND consecutive_failures + 1 < $2,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE id = $1
+            RETURNING consecutive_failures = $2
+            "#,
+        )
+        .bind(webhook_id)
+        .bind(disable_after)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(disabled.unwrap_or(false))
+    }
+
+    pub async fn fail_pending(&self, webhook_id: i64, error: &str) -> Result<u64, AppError> {
+        let res = sqlx::query(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = 'failed', last_error = $2, updated_at = CURRENT_TIMESTAMP
+            WHERE webhook_id = $1 AND status = 'pending'
+            "#,
+        )
+        .bind(webhook_id)
+        .bind(error)
+        .execute(self.pool)
+        .await?;
+
+        Ok(res.rows_affected())
+    }
+
+    pub async fn list_deliveries(
+        &self,
+        webhook_id: i64,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<WebhookDelivery>, AppError> {
+        let deliveries = sqlx::query_as(
+            r#"
+            SELECT * FROM webhook_deliveries
+            WHERE webhook_id = $1
+            ORDER BY created_at DESC, id DESC
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(webhook_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(deliveries)
+    }
+
+    pub async fn list_attempts(
+        &self,
+        delivery_ids: Vec<i64>,
+    ) -> Result<Vec<DeliveryAttempt>, AppError> {
+        let attempts = sqlx::query_as(
+            r#"
+            SELECT * FROM webhook_delivery_attempts
+            WHERE delivery_id = any($1)
+            ORDER BY id ASC
+            "#,
+        )
+        .bind(delivery_ids)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(attempts)
+    }
+
+    pub async fn create_incoming(
+        &self,
+        hook: &CreateIncomingWebhook,
+    ) -> Result<IncomingWebhook, AppError> {
+        let created = sqlx::query_as(
+            r#"
+            INSERT INTO incoming_webhooks (channel_id, creator_id, bot_user_id, name, token_hash)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+            "#,
+        )
+        .bind(hook.channel_id)
+        .bind(hook.creator_id)
+        .bind(hook.bot_user_id)
+        .bind(&hook.name)
+        .bind(&hook.token_hash)
+        .fetch_one(self.pool)
+        .await?;
+
+        Ok(created)
+    }
+
+    pub async fn get_incoming_by_id(&self, id: i64) -> Result<Option<IncomingWebhook>, AppError> {
+        let hook = sqlx::query_as(
+            r#"
+            SELECT * FROM incoming_webhooks WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(hook)
+    }
+
+    pub async fn get_incoming_by_token_hash(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<IncomingWebhook>, AppError> {
+        let hook = sqlx::query_as(
+            r#"
+            SELECT * FROM incoming_webhooks WHERE token_hash = $1 AND revoked_at IS NULL
+            "#,
+        )
+        .bind(token_hash)
+        .fetch_optional(self.pool)
+        .await?;
+
+        Ok(hook)
+    }
+
+    pub async fn list_incoming_by_channel(
+        &self,
+        channel_id: i64,
+    ) -> Result<Vec<IncomingWebhook>, AppError> {
+        let hooks = sqlx::query_as(
+            r#"
+            SELECT * FROM incoming_webhooks
+            WHERE channel_id = $1 AND revoked_at IS NULL
+            ORDER BY id ASC
+            "#,
+        )
+        .bind(channel_id)
+        .fetch_all(self.pool)
+        .await?;
+
+        Ok(hooks)
+    }
+
+    pub async fn revoke_incoming(&self, id: i64) -> Result<u64, AppError> {
+        let res = sqlx::query(
+            r#"
+            UPDATE incoming_webhooks SET revoked_at = CURRENT_TIMESTAMP
+            WHERE id = $1 AND revoked_at IS NULL
+            "#,
+        )
+        .bind(id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(res.rows_affected())
+    }
+
+    pub async fn touch_incoming(&self, id: i64) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE incoming_webhooks SET last_used_at = CURRENT_TIMESTAMP WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .execute(self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
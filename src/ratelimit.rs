@@ -0,0 +1,339 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{Request, State},
+    http::header,
+    middleware::Next,
+    response::Response,
+};
+use serde::Deserialize;
+
+use crate::{
+    auth::api_token::{API_TOKEN_PREFIX, hash_token},
+    errors::AppError,
+    models::api_token::ApiTokenStore,
+    service::audit::ClientInfo,
+    state::AppState,
+};
+
+// Past this many keys, buckets that have refilled are dropped; a full bucket is the same
+// as a missing one.
+const MAX_TRACKED_KEYS: usize = 10_000;
+
+/// How many requests a key may burst, and how fast its allowance comes back.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Quota {
+    pub burst: u32,
+    pub per_second: f64,
+}
+
+impl Quota {
+    pub const API: Quota = Quota {
+        burst: 100,
+        per_second: 10.0,
+    };
+    pub const LOGIN: Quota = Quota {
+        burst: 10,
+        per_second: 1.0 / 6.0,
+    };
+    pub const SOCKET: Quota = Quota {
+        burst: 20,
+        per_second: 5.0,
+    };
+}
+
+#[derive(Debug)]
+pub struct TokenBucket {
+    quota: Quota,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(quota: Quota) -> Self {
+        Self {
+            quota,
+            tokens: quota.burst as f64,
+            updated: Instant::now(),
+        }
+    }
+
+    pub fn try_take(&mut self) -> Result<(), AppError> {
+        self.try_take_at(Instant::now())
+            .map_err(|wait| AppError::RateLimited(retry_after_secs(wait)))
+    }
+
+    // Takes one token, or says how long until one is available.
+    fn try_take_at(&mut self, now: Instant) -> Result<(), Duration> {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(Duration::from_secs_f64(
+            (1.0 - self.tokens) / self.quota.per_second,
+        ))
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.quota.per_second).min(self.quota.burst as f64);
+        self.updated = now;
+    }
+
+    fn is_full(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens >= self.quota.burst as f64
+    }
+}
+
+/// One token bucket per key, kept in memory, so every instance enforces its own limits.
+#[derive(Debug)]
+pub struct RateLimiter {
+    quota: Quota,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(quota: Quota) -> Self {
+        Self {
+            quota,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn check(&self, key: &str) -> Result<(), AppError> {
+        self.check_at(key, Instant::now())
+            .map_err(|wait| AppError::RateLimited(retry_after_secs(wait)))
+    }
+
+    fn check_at(&self, key: &str, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED_KEYS {
+            buckets.retain(|_, bucket| !bucket.is_full(now));
+        }
+
+        let quota = self.quota;
+        buckets
+            .entry(key.to_string())
+            .or_insert_with(|| TokenBucket {
+                quota,
+                tokens: quota.burst as f64,
+                updated: now,
+            })
+            .try_take_at(now)
+    }
+}
+
+const MAX_FAILED_LOGINS: u32 = 5;
+const FAILURE_WINDOW: Duration = Duration::from_secs(15 * 60);
+const LOCKOUT: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Debug)]
+struct Failures {
+    count: u32,
+    first_at: Instant,
+    locked_until: Option<Instant>,
+}
+
+/// Guards logins: each address gets a small allowance of attempts, and a username that
+/// keeps failing from the same address is locked out there for a while.
+#[derive(Debug)]
+pub struct LoginGuard {
+    attempts: RateLimiter,
+    failures: Mutex<HashMap<String, Failures>>,
+}
+
+impl Default for LoginGuard {
+    fn default() -> Self {
+        Self::new(Quota::LOGIN)
+    }
+}
+
+impl LoginGuard {
+    // `quota` is per address; the lockout after repeated failures is fixed.
+    pub fn new(quota: Quota) -> Self {
+        Self {
+            attempts: RateLimiter::new(quota),
+            failures: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn check(&self, ip: &str, username: &str) -> Result<(), AppError> {
+        self.check_at(ip, username, Instant::now())
+            .map_err(|wait| AppError::RateLimited(retry_after_secs(wait)))
+    }
+
+    pub fn record_failure(&self, ip: &str, username: &str) {
+        self.record_failure_at(ip, username, Instant::now());
+    }
+
+    pub fn record_success(&self, ip: &str, username: &str) {
+        self.failures
+            .lock()
+            .unwrap()
+            .remove(&failure_key(ip, username));
+    }
+
+    fn check_at(&self, ip: &str, username: &str, now: Instant) -> Result<(), Duration> {
+        let locked_until = self
+            .failures
+            .lock()
+            .unwrap()
+            .get(&failure_key(ip, username))
+            .and_then(|f| f.locked_until)
+            .filter(|until| *until > now);
+        if let Some(until) = locked_until {
+            return Err(until - now);
+        }
+
+        self.attempts.check_at(ip, now)
+    }
+
+    fn record_failure_at(&self, ip: &str, username: &str, now: Instant) {
+        let mut failures = self.failures.lock().unwrap();
+        if failures.len() >= MAX_TRACKED_KEYS {
+            failures.retain(|_, f| {
+                f.locked_until.is_some_and(|until| until > now)
+                    || now.saturating_duration_since(f.first_at) < FAILURE_WINDOW
+            });
+        }
+
+        let entry = failures
+            .entry(failure_key(ip, username))
+            .or_insert(Failures {
+                count: 0,
+                first_at: now,
+                locked_until: None,
+            });
+        if now.saturating_duration_since(entry.first_at) >= FAILURE_WINDOW {
+            *entry = Failures {
+                count: 0,
+                first_at: now,
+                locked_until: None,
+            };
+        }
+
+        entry.count += 1;
+        if entry.count >= MAX_FAILED_LOGINS {
+            entry.locked_until = Some(now + LOCKOUT);
+            // the next round of failures starts counting once the lockout ends.
+            entry.count = 0;
+            entry.first_at = now + LOCKOUT;
+        }
+    }
+}
+
+fn failure_key(ip: &str, username: &str) -> String {
+    format!("{}|{}", ip, username.to_lowercase())
+}
+
+// `Retry-After` takes whole seconds, and must not invite an immediate retry.
+fn retry_after_secs(wait: Duration) -> u64 {
+    wait.as_secs_f64().ceil().max(1.0) as u64
+}
+
+/// The layer limiting every API request, keyed by the authenticated user when the bearer
+/// token checks out and by the client's address otherwise.
+pub async fn rate_limit(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let bearer = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|v| v.to_string());
+    let key = request_key(&state, &client, bearer.as_deref()).await?;
+    state.rate_limiter.check(&key)?;
+    Ok(next.run(req).await)
+}
+
+// Only a token that checks out gets its own bucket; anything else, made-up tokens
+// included, shares the bucket of the address it came from.
+async fn request_key(
+    state: &AppState,
+    client: &ClientInfo,
+    bearer: Option<&str>,
+) -> Result<String, AppError> {
+    let ip_key = format!("ip:{}", client.ip.as_deref().unwrap_or(""));
+
+    let key = match bearer {
+        Some(token) if token.starts_with(API_TOKEN_PREFIX) => {
+            let token_hash = hash_token(token);
+            let token_store = ApiTokenStore::new(&state.pool);
+            match token_store.get_valid_by_hash(&token_hash).await? {
+                Some(_) => format!("token:{}", token_hash),
+                None => ip_key,
+            }
+        }
+        Some(token) => match state.dk.verify(token) {
+            Ok(claims) => format!("user:{}", claims.id),
+            Err(_) => ip_key,
+        },
+        None => ip_key,
+    };
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let limiter = RateLimiter::new(Quota {
+            burst: 2,
+            per_second: 0.5,
+        });
+        let start = Instant::now();
+
+        assert!(limiter.check_at("a", start).is_ok());
+        assert!(limiter.check_at("a", start).is_ok());
+        assert_eq!(limiter.check_at("a", start), Err(Duration::from_secs(2)));
+        // other keys have their own bucket.
+        assert!(limiter.check_at("b", start).is_ok());
+
+        assert!(
+            limiter
+                .check_at("a", start + Duration::from_secs(2))
+                .is_ok()
+        );
+        assert!(
+            limiter
+                .check_at("a", start + Duration::from_secs(2))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_login_lockout() {
+        let guard = LoginGuard::default();
+        let start = Instant::now();
+
+        for _ in 0..MAX_FAILED_LOGINS {
+            assert!(guard.check_at("10.0.0.1", "alice", start).is_ok());
+            guard.record_failure_at("10.0.0.1", "Alice", start);
+        }
+        assert_eq!(guard.check_at("10.0.0.1", "alice", start), Err(LOCKOUT));
+        // the lockout is per address, and other users are not affected.
+        assert!(guard.check_at("10.0.0.2", "alice", start).is_ok());
+        assert!(guard.check_at("10.0.0.1", "bob", start).is_ok());
+
+        assert!(guard.check_at("10.0.0.1", "alice", start + LOCKOUT).is_ok());
+    }
+
+    #[test]
+    fn test_retry_after_secs() {
+        assert_eq!(retry_after_secs(Duration::from_millis(10)), 1);
+        assert_eq!(retry_after_secs(Duration::from_millis(2500)), 3);
+    }
+}
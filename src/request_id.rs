@@ -0,0 +1,49 @@
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+const REQUEST_ID_LEN: usize = 16;
+const MAX_REQUEST_ID_LEN: usize = 64;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// The id of the request being handled, if called from within one.
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Gives each request an id, reusing the one a proxy sent if it looks sane, and returns it
+/// in the `x-request-id` response header.
+pub async fn assign_request_id(mut req: Request, next: Next) -> Response {
+    let request_id = req
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .filter(|id| is_valid(id))
+        .map(|id| id.to_string())
+        .unwrap_or_else(|| nanoid::nanoid!(REQUEST_ID_LEN));
+
+    // the id only holds header-safe characters, whether sent or generated.
+    let value = HeaderValue::from_str(&request_id).expect("request id is a valid header");
+    // inner layers, like the trace span, read it back from the request.
+    req.headers_mut().insert(REQUEST_ID_HEADER, value.clone());
+
+    let mut resp = REQUEST_ID.scope(request_id, next.run(req)).await;
+    resp.headers_mut().insert(REQUEST_ID_HEADER, value);
+    resp
+}
+
+fn is_valid(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
@@ -1,33 +1,65 @@
 use axum::{
-    Router, http,
+    Router,
+    extract::{DefaultBodyLimit, State},
+    http::{self, header},
+    middleware,
     response::IntoResponse,
-    routing::{any, delete, get, post, put},
+    routing::{any, delete, get, patch, post, put},
+};
+use tower_http::{
+    cors::{AllowOrigin, CorsLayer},
+    trace::TraceLayer,
 };
-use tower_http::cors::{Any, CorsLayer};
 
 use crate::{
     errors::AppError,
     handlers::{
+        admin_handler, bot_handler,
         channel_handler::{
-            create_channel, get_channel, join_channel, leave_channel, list_channel_memebers,
-            list_channels, list_user_channels,
+            add_member, archive_channel, change_member_role, create_channel, get_channel,
+            join_channel, leave_channel, list_channel_memebers, list_channels, list_user_channels,
+            remove_member, search_channels, unarchive_channel, update_channel,
+        },
+        command_handler,
+        health_handler::{healthz, readyz},
+        message_handler::{
+            delete_message, get_message, list_messages, list_my_mentions, list_pins, pin_message,
+            send_message_to_channel, unpin_message, update_message,
+        },
+        poll_handler, reminder_handler,
+        saved_handler::{list_saved_items, remove_saved_item, save_item, update_saved_item},
+        scheduled_handler,
+        user_handler::{change_password, deactivate_me, get_user, login, register, update_me},
+        webhook_handler::{
+            create_incoming_webhook, create_webhook, delete_webhook, enable_webhook,
+            list_deliveries, list_incoming_webhooks, list_webhooks, post_to_hook,
+            revoke_incoming_webhook,
         },
-        message_handler::{get_message, list_messages, send_message_to_channel, update_message},
-        user_handler::{get_user, login, register},
-        websocket::message_loop,
+        websocket::{event_loop, message_loop},
     },
+    metrics::track_http,
+    ratelimit::rate_limit,
+    request_id::assign_request_id,
     state::AppState,
+    telemetry::http_span,
 };
 
 pub async fn get_router(state: AppState) -> Result<Router, AppError> {
+    let cors_config = &state.config.cors;
+    let origins = if cors_config.allows_any() {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(cors_config.origins())
+    };
     let cors = CorsLayer::new()
-        // Allow requests from any origin
-        .allow_origin(Any)
+        // Allow requests from the configured origins
+        .allow_origin(origins)
         // Allow specific HTTP methods
         .allow_methods([
             http::Method::GET,
             http::Method::POST,
             http::Method::PUT,
+            http::Method::PATCH,
             http::Method::DELETE,
             http::Method::OPTIONS,
         ])
@@ -38,35 +70,215 @@ pub async fn get_router(state: AppState) -> Result<Router, AppError> {
             http::header::ACCEPT,
         ])
         // Set max age for browsers to cache CORS preflight requests
-        .max_age(std::time::Duration::from_secs(3600));
+        .max_age(std::time::Duration::from_secs(cors_config.max_age_secs));
+
+    let admin_router = Router::new()
+        .route("/users", get(admin_handler::list_users))
+        .route("/users/{user_id}", get(admin_handler::get_user))
+        .route(
+            "/users/{user_id}/deactivate",
+            post(admin_handler::deactivate_user),
+        )
+        .route(
+            "/users/{user_id}/reactivate",
+            post(admin_handler::reactivate_user),
+        )
+        .route(
+            "/users/{user_id}/password-reset",
+            post(admin_handler::reset_password),
+        )
+        .route(
+            "/users/{user_id}/avatar",
+            delete(admin_handler::reset_avatar),
+        )
+        .route(
+            "/users/{user_id}/channels",
+            get(admin_handler::list_user_memberships),
+        )
+        .route("/audit-events", get(admin_handler::list_audit_events));
 
     let api_router = Router::new()
-        .route("/index", get(index))
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
         .route("/{user_id}/websocket", any(message_loop))
+        .route("/api/v1/websocket", any(event_loop))
+        .route("/hooks/{token}", post(post_to_hook))
         .route("/api/v1/users/register", post(register))
         .route("/api/v1/users/login", post(login))
+        .route("/api/v1/users/me", patch(update_me).delete(deactivate_me))
+        .route("/api/v1/users/me/password", post(change_password))
+        .route("/api/v1/users/me/mentions", get(list_my_mentions))
+        .route(
+            "/api/v1/users/me/saved",
+            get(list_saved_items).post(save_item),
+        )
+        .route(
+            "/api/v1/users/me/saved/{message_id}",
+            put(update_saved_item).delete(remove_saved_item),
+        )
+        .route(
+            "/api/v1/bots",
+            get(bot_handler::list_bots).post(bot_handler::create_bot),
+        )
+        .route("/api/v1/bots/{bot_id}", delete(bot_handler::delete_bot))
+        .route(
+            "/api/v1/bots/{bot_id}/tokens",
+            get(bot_handler::list_tokens).post(bot_handler::create_token),
+        )
+        .route(
+            "/api/v1/bots/{bot_id}/tokens/{token_id}",
+            delete(bot_handler::revoke_token),
+        )
+        .route(
+            "/api/v1/bots/{bot_id}/tokens/{token_id}/rotate",
+            post(bot_handler::rotate_token),
+        )
+        .route(
+            "/api/v1/channels/{channel_id}/polls",
+            post(poll_handler::create_poll),
+        )
+        .route(
+            "/api/v1/messages/{message_id}/poll",
+            get(poll_handler::get_poll),
+        )
+        .route(
+            "/api/v1/messages/{message_id}/poll/votes",
+            delete(poll_handler::unvote_all),
+        )
+        .route(
+            "/api/v1/messages/{message_id}/poll/votes/{option_index}",
+            post(poll_handler::vote).delete(poll_handler::unvote),
+        )
+        .route(
+            "/api/v1/messages/{message_id}/poll/close",
+            post(poll_handler::close_poll),
+        )
+        .route(
+            "/api/v1/reminders",
+            get(reminder_handler::list_reminders).post(reminder_handler::create_reminder),
+        )
+        .route(
+            "/api/v1/reminders/{reminder_id}",
+            delete(reminder_handler::delete_reminder),
+        )
+        .route(
+            "/api/v1/reminders/{reminder_id}/snooze",
+            post(reminder_handler::snooze_reminder),
+        )
+        .route(
+            "/api/v1/reminders/{reminder_id}/complete",
+            post(reminder_handler::complete_reminder),
+        )
+        .route(
+            "/api/v1/scheduled-messages",
+            get(scheduled_handler::list_scheduled_messages)
+                .post(scheduled_handler::schedule_message),
+        )
+        .route(
+            "/api/v1/scheduled-messages/{scheduled_id}",
+            patch(scheduled_handler::update_scheduled_message)
+                .delete(scheduled_handler::cancel_scheduled_message),
+        )
+        .route(
+            "/api/v1/commands",
+            get(command_handler::list_commands).post(command_handler::register_command),
+        )
+        .route(
+            "/api/v1/commands/{command_id}",
+            delete(command_handler::delete_command),
+        )
         .route("/api/v1/users/{user_id}", get(get_user))
         .route("/api/v1/users/{user_id}/channels", get(list_user_channels))
         .route("/api/v1/channels/{channel_id}/join", post(join_channel))
         .route("/api/v1/channels/{channel_id}/leave", delete(leave_channel))
-        .route("/api/v1/channels/{channel_id}", get(get_channel))
+        .route("/api/v1/channels/search", get(search_channels))
+        .route(
+            "/api/v1/channels/{channel_id}",
+            get(get_channel).patch(update_channel),
+        )
+        .route(
+            "/api/v1/channels/{channel_id}/archive",
+            post(archive_channel),
+        )
+        .route(
+            "/api/v1/channels/{channel_id}/unarchive",
+            post(unarchive_channel),
+        )
         .route(
             "/api/v1/channels/{channel_id}/messages",
             get(list_messages).post(send_message_to_channel),
         )
+        .route(
+            "/api/v1/channels/{channel_id}/pins",
+            get(list_pins).post(pin_message),
+        )
+        .route(
+            "/api/v1/channels/{channel_id}/pins/{message_id}",
+            delete(unpin_message),
+        )
         .route(
             "/api/v1/channels/{channel_id}/members",
-            get(list_channel_memebers),
+            get(list_channel_memebers).post(add_member),
+        )
+        .route(
+            "/api/v1/channels/{channel_id}/members/{user_id}",
+            delete(remove_member),
+        )
+        .route(
+            "/api/v1/channels/{channel_id}/members/{user_id}/role",
+            put(change_member_role),
+        )
+        .route(
+            "/api/v1/channels/{channel_id}/webhooks",
+            get(list_webhooks).post(create_webhook),
+        )
+        .route(
+            "/api/v1/channels/{channel_id}/webhooks/{webhook_id}",
+            delete(delete_webhook),
+        )
+        .route(
+            "/api/v1/channels/{channel_id}/webhooks/{webhook_id}/enable",
+            post(enable_webhook),
+        )
+        .route(
+            "/api/v1/channels/{channel_id}/webhooks/{webhook_id}/deliveries",
+            get(list_deliveries),
+        )
+        .route(
+            "/api/v1/channels/{channel_id}/incoming-webhooks",
+            get(list_incoming_webhooks).post(create_incoming_webhook),
+        )
+        .route(
+            "/api/v1/channels/{channel_id}/incoming-webhooks/{webhook_id}",
+            delete(revoke_incoming_webhook),
         )
         .route("/api/v1/channels", post(create_channel).get(list_channels))
         .route("/api/v1/messages", put(update_message))
-        .route("/api/v1/messages/{message_id}", get(get_message))
+        .route(
+            "/api/v1/messages/{message_id}",
+            get(get_message).delete(delete_message),
+        )
+        .nest("/api/v1/admin", admin_router)
+        .layer(DefaultBodyLimit::max(state.config.limits.max_body_bytes))
+        .layer(middleware::from_fn_with_state(state.clone(), rate_limit))
+        .layer(middleware::from_fn_with_state(state.clone(), track_http))
+        .layer(TraceLayer::new_for_http().make_span_with(http_span))
+        .layer(middleware::from_fn(assign_request_id))
         .layer(cors)
         .with_state(state);
 
     Ok(api_router)
 }
 
-async fn index() -> Result<impl IntoResponse, AppError> {
-    Ok("Hello, World!")
+/// Serves `/metrics` on its own listener, `server.metrics_bind`, so scrapes never go through
+/// the public port: each one samples the pool and counts the job queues.
+pub fn get_metrics_router(state: AppState) -> Router {
+    Router::new()
+        .route("/metrics", get(metrics))
+        .with_state(state)
+}
+
+async fn metrics(State(state): State<AppState>) -> Result<impl IntoResponse, AppError> {
+    let text = state.metrics.render(&state).await?;
+    Ok(([(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], text))
 }
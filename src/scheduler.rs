@@ -0,0 +1,138 @@
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::{
+    dto::event::ServerEvent,
+    errors::AppError,
+    handlers::publish_message,
+    models::{
+        channel::ChanRepository, message::MessageStore, poll::PollStore, reminder::ReminderStore,
+        scheduled::ScheduledMessageStore, user::UserRepository,
+    },
+    service::{poll::PollService, reminder::ReminderService, scheduled::ScheduledService},
+    state::AppState,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const BATCH_SIZE: i64 = 50;
+// Long enough for a whole batch to post; a message still claimed after that is taken to
+// belong to a scheduler that died.
+const SEND_LEASE: Duration = Duration::from_secs(120);
+const MAX_SEND_ATTEMPTS: i32 = 3;
+
+/// Posts scheduled messages, delivers reminders and closes polls once they are due. Any number of
+/// instances can run this; each due row is claimed by exactly one of them.
+pub async fn run_scheduler(state: AppState) {
+    loop {
+        let sent = send_due(&state).await.unwrap_or_else(|e| {
+            warn!("send scheduled messages error: {}", e);
+            0
+        });
+        let reminded = deliver_reminders(&state).await.unwrap_or_else(|e| {
+            warn!("deliver reminders error: {}", e);
+            0
+        });
+        let closed = close_due_polls(&state).await.unwrap_or_else(|e| {
+            warn!("close due polls error: {}", e);
+            0
+        });
+
+        // a full batch likely means more are waiting.
+        if [sent, reminded, closed]
+            .iter()
+            .any(|n| *n as i64 == BATCH_SIZE)
+        {
+            continue;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Sends one batch of due messages and returns how many were claimed.
+pub async fn send_due(state: &AppState) -> Result<usize, AppError> {
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let scheduled_store = ScheduledMessageStore::new(&state.pool);
+    let scheduled_service =
+        ScheduledService::new(&chan_repo, &user_repo, &msg_store, &scheduled_store);
+
+    let lease_secs = SEND_LEASE.as_secs_f64();
+    let abandoned = scheduled_store
+        .fail_abandoned(lease_secs, MAX_SEND_ATTEMPTS)
+        .await?;
+    if abandoned > 0 {
+        warn!("gave up on {} abandoned scheduled messages", abandoned);
+    }
+
+    let due = scheduled_store
+        .claim_due(BATCH_SIZE, lease_secs, MAX_SEND_ATTEMPTS)
+        .await?;
+    for scheduled in &due {
+        if scheduled.is_reclaimed() {
+            warn!(
+                "retrying scheduled message {} after its lease expired",
+                scheduled.id
+            );
+        }
+        match scheduled_service.send_scheduled(scheduled).await {
+            Ok(msg) => {
+                info!("sent scheduled message {} as {}", scheduled.id, msg.id);
+                if let Err(e) = publish_message(state, &msg).await {
+                    warn!("publish scheduled message {} error: {}", msg.id, e);
+                }
+            }
+            Err(e) => warn!("scheduled message {} failed: {}", scheduled.id, e),
+        }
+    }
+    Ok(due.len())
+}
+
+/// Delivers one batch of due reminders and returns how many were claimed. A reminder whose
+/// delivery fails stays delivered rather than being retried, like scheduled messages.
+pub async fn deliver_reminders(state: &AppState) -> Result<usize, AppError> {
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let reminder_store = ReminderStore::new(&state.pool);
+    let reminder_service =
+        ReminderService::new(&chan_repo, &user_repo, &msg_store, &reminder_store);
+
+    let due = reminder_store.claim_due(BATCH_SIZE).await?;
+    for reminder in &due {
+        match reminder_service.deliver(reminder).await {
+            Ok(msg) => {
+                info!("delivered reminder {} as {}", reminder.id, msg.id);
+                if let Err(e) = publish_message(state, &msg).await {
+                    warn!("publish reminder {} error: {}", reminder.id, e);
+                }
+            }
+            Err(e) => warn!("reminder {} failed: {}", reminder.id, e),
+        }
+    }
+    Ok(due.len())
+}
+
+/// Closes one batch of polls whose close time has passed, announcing each result.
+pub async fn close_due_polls(state: &AppState) -> Result<usize, AppError> {
+    let user_repo = UserRepository::new(&state.pool);
+    let chan_repo = ChanRepository::new(&state.pool);
+    let msg_store = MessageStore::new(&state.pool);
+    let poll_store = PollStore::new(&state.pool);
+    let poll_service = PollService::new(&chan_repo, &user_repo, &msg_store, &poll_store);
+
+    let closed = poll_service.close_due(BATCH_SIZE).await?;
+    for resp in &closed {
+        info!("closed poll {}", resp.poll.message_id);
+        let event = ServerEvent::PollUpdated {
+            poll: resp.poll.clone(),
+        };
+        if let Err(e) = state.send_to_channel(resp.poll.channel_id, &event).await {
+            warn!("publish poll {} error: {}", resp.poll.message_id, e);
+        }
+        if let Err(e) = state.send_system_message(&resp.system_msg).await {
+            warn!("publish poll {} result error: {}", resp.poll.message_id, e);
+        }
+    }
+    Ok(closed.len())
+}
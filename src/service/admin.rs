@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+
+use serde_json::json;
+
+use crate::{
+    dto::{
+        admin::{
+            ChannelMembership, ListMembershipsResp, ListUsersReq, ListUsersResp, ResetPasswordResp,
+        },
+        channel::Channel as ChanDto,
+        user::User as UserDto,
+    },
+    errors::AppError,
+    models::{
+        channel::ChanRepository,
+        user::{UpdateUser, UserRepository},
+    },
+    service::{
+        audit::{AuditAction, AuditTarget, Auditor, change},
+        user::hash_password,
+    },
+};
+
+const MAX_PAGE_SIZE: i64 = 100;
+const TEMP_PWD_LEN: usize = 16;
+
+pub struct AdminService<'a> {
+    chan_store: &'a ChanRepository<'a>,
+    user_store: &'a UserRepository<'a>,
+    auditor: Option<&'a Auditor<'a>>,
+}
+
+impl<'a> AdminService<'a> {
+    pub fn new(chan_store: &'a ChanRepository, user_store: &'a UserRepository<'a>) -> Self {
+        Self {
+            chan_store,
+            user_store,
+            auditor: None,
+        }
+    }
+
+    pub fn with_auditor(mut self, auditor: &'a Auditor<'a>) -> Self {
+        self.auditor = Some(auditor);
+        self
+    }
+
+    async fn audit(
+        &self,
+        admin_id: i64,
+        action: AuditAction,
+        user_id: i64,
+        diff: serde_json::Value,
+    ) {
+        if let Some(auditor) = self.auditor {
+            auditor
+                .record(Some(admin_id), action, AuditTarget::User(user_id), diff)
+                .await;
+        }
+    }
+
+    pub async fn list_users(&self, req: &ListUsersReq) -> Result<ListUsersResp, AppError> {
+        if req.offset < 0 || req.limit <= 0 || req.limit > MAX_PAGE_SIZE {
+            return Err(AppError::InvalidArgument(format!(
+                "offset must be >= 0 and limit in 1..={}",
+                MAX_PAGE_SIZE
+            )));
+        }
+
+        let query = req.q.as_deref().filter(|q| !q.is_empty());
+        let users = self.user_store.search(query, req.limit, req.offset).await?;
+        let total = self.user_store.count(query).await?;
+
+        Ok(ListUsersResp {
+            has_more: req.offset + (users.len() as i64) < total,
+            users: users.into_iter().map(UserDto::from).collect(),
+            total,
+        })
+    }
+
+    pub async fn get_user(&self, user_id: i64) -> Result<UserDto, AppError> {
+        match self.user_store.get_by_id(user_id).await? {
+            Some(user) => Ok(UserDto::from(user)),
+            None => Err(AppError::NotFound(format!("user: {}", user_id))),
+        }
+    }
+
+    pub async fn set_active(
+        &self,
+        admin_id: i64,
+        user_id: i64,
+        is_active: bool,
+    ) -> Result<UserDto, AppError> {
+        if admin_id == user_id && !is_active {
+            return Err(AppError::InvalidArgument(
+                "admins cannot deactivate themselves".to_string(),
+            ));
+        }
+
+        let before = self.get_user(user_id).await?;
+        let user = self
+            .apply(UpdateUser {
+                id: user_id,
+                is_active: Some(is_active),
+                ..Default::default()
+            })
+            .await?;
+
+        let action = if is_active {
+            AuditAction::UserReactivated
+        } else {
+            AuditAction::UserDeactivated
+        };
+        self.audit(
+            admin_id,
+            action,
+            user_id,
+            change("is_active", before.is_active, user.is_active),
+        )
+        .await;
+        Ok(user)
+    }
+
+    // Replaces the password with a random one the user must change after logging in.
+    pub async fn reset_password(
+        &self,
+        admin_id: i64,
+        user_id: i64,
+    ) -> Result<ResetPasswordResp, AppError> {
+        let temporary_password = nanoid::nanoid!(TEMP_PWD_LEN);
+        let user = self
+            .apply(UpdateUser {
+                id: user_id,
+                password_hash: Some(hash_password(&temporary_password)?),
+                must_reset_password: Some(true),
+                ..Default::default()
+            })
+            .await?;
+
+        // the temporary password itself never goes into the log.
+        self.audit(
+            admin_id,
+            AuditAction::PasswordReset,
+            user_id,
+            json!({ "must_reset_password": true }),
+        )
+        .await;
+
+        Ok(ResetPasswordResp {
+            user,
+            temporary_password,
+        })
+    }
+
+    pub async fn reset_avatar(&self, admin_id: i64, user_id: i64) -> Result<UserDto, AppError> {
+        let before = self.get_user(user_id).await?;
+        let user = self
+            .apply(UpdateUser {
+                id: user_id,
+                avatar_url: Some("".to_string()),
+                ..Default::default()
+            })
+            .await?;
+
+        self.audit(
+            admin_id,
+            AuditAction::AvatarReset,
+            user_id,
+            change("avatar_url", before.avatar_url, user.avatar_url.clone()),
+        )
+        .await;
+        Ok(user)
+    }
+
+    pub async fn list_memberships(&self, user_id: i64) -> Result<ListMembershipsResp, AppError> {
+        // make sure the user exists so a typo isn't reported as "no memberships".
+        self.get_user(user_id).await?;
+
+        let members = self.chan_store.list_chan_members_by_user(user_id).await?;
+        let mut channels: HashMap<i64, ChanDto> = self
+            .chan_store
+            .list_user_channels(user_id, true)
+            .await?
+            .into_iter()
+            .map(|ch| (ch.id, ChanDto::from(ch)))
+            .collect();
+
+        let memberships = members
+            .into_iter()
+            .filter_map(|m| {
+                channels
+                    .remove(&m.channel_id)
+                    .map(|channel| ChannelMembership {
+                        channel,
+                        member_role: m.member_role,
+                        joined_at: m.joined_at,
+                    })
+            })
+            .collect();
+
+        Ok(ListMembershipsResp { memberships })
+    }
+
+    async fn apply(&self, update: UpdateUser) -> Result<UserDto, AppError> {
+        let user_id = update.id;
+        match self.user_store.update(&update).await? {
+            Some(user) => Ok(UserDto::from(user)),
+            None => Err(AppError::NotFound(format!("user: {}", user_id))),
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use serde_json::{Value, json};
+use tracing::warn;
+
+use crate::{
+    dto::audit::{ListAuditEventsReq, ListAuditEventsResp},
+    errors::AppError,
+    models::audit::{AuditFilter, AuditStore, CreateAuditEvent},
+};
+
+const MAX_PAGE_SIZE: i64 = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    LoginSucceeded,
+    LoginFailed,
+    PasswordChanged,
+    ChannelCreated,
+    ChannelUpdated,
+    ChannelJoined,
+    ChannelLeft,
+    MemberAdded,
+    MemberRemoved,
+    MemberRoleChanged,
+    ChannelArchived,
+    ChannelUnarchived,
+    MessageEdited,
+    MessageDeleted,
+    UserDeactivated,
+    UserReactivated,
+    PasswordReset,
+    AvatarReset,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::LoginSucceeded => "user.login_succeeded",
+            AuditAction::LoginFailed => "user.login_failed",
+            AuditAction::PasswordChanged => "user.password_changed",
+            AuditAction::ChannelCreated => "channel.created",
+            AuditAction::ChannelUpdated => "channel.updated",
+            AuditAction::ChannelJoined => "channel.joined",
+            AuditAction::ChannelLeft => "channel.left",
+            AuditAction::MemberAdded => "channel.member_added",
+            AuditAction::MemberRemoved => "channel.member_removed",
+            AuditAction::MemberRoleChanged => "channel.member_role_changed",
+            AuditAction::ChannelArchived => "channel.archived",
+            AuditAction::ChannelUnarchived => "channel.unarchived",
+            AuditAction::MessageEdited => "message.edited",
+            AuditAction::MessageDeleted => "message.deleted",
+            AuditAction::UserDeactivated => "user.deactivated",
+            AuditAction::UserReactivated => "user.reactivated",
+            AuditAction::PasswordReset => "user.password_reset",
+            AuditAction::AvatarReset => "user.avatar_reset",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditTarget {
+    None,
+    User(i64),
+    Channel(i64),
+    Message(i64),
+}
+
+impl AuditTarget {
+    fn parts(&self) -> (&'static str, Option<i64>) {
+        match self {
+            AuditTarget::None => ("", None),
+            AuditTarget::User(id) => ("user", Some(*id)),
+            AuditTarget::Channel(id) => ("channel", Some(*id)),
+            AuditTarget::Message(id) => ("message", Some(*id)),
+        }
+    }
+}
+
+/// Where a request came from, captured alongside every audit event.
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    // the connection's own address; `ip` differs only behind a trusted proxy.
+    pub peer_ip: Option<String>,
+}
+
+/// The hook services call to append to the audit log for the current request.
+pub struct Auditor<'a> {
+    store: &'a AuditStore<'a>,
+    client: ClientInfo,
+}
+
+impl<'a> Auditor<'a> {
+    pub fn new(store: &'a AuditStore<'a>, client: ClientInfo) -> Self {
+        Self { store, client }
+    }
+
+    // Failing to audit must not fail the action being audited, so errors are only logged.
+    pub async fn record(
+        &self,
+        actor_id: Option<i64>,
+        action: AuditAction,
+        target: AuditTarget,
+        diff: Value,
+    ) {
+        let (target_type, target_id) = target.parts();
+        let event = CreateAuditEvent {
+            actor_id,
+            action: action.as_str().to_string(),
+            target_type: target_type.to_string(),
+            target_id,
+            ip: self.client.ip.clone(),
+            user_agent: self.client.user_agent.clone(),
+            diff,
+        };
+
+        if let Err(e) = self.store.create(&event).await {
+            warn!("record audit event {} error: {}", action.as_str(), e);
+        }
+    }
+}
+
+/// Builds the `{"field": {"from": .., "to": ..}}` diff stored with an event.
+pub fn change(field: &str, from: impl Into<Value>, to: impl Into<Value>) -> Value {
+    json!({ field: field_change(from, to) })
+}
+
+pub fn field_change(from: impl Into<Value>, to: impl Into<Value>) -> Value {
+    json!({ "from": from.into(), "to": to.into() })
+}
+
+pub struct AuditService<'a> {
+    audit_store: &'a AuditStore<'a>,
+}
+
+impl<'a> AuditService<'a> {
+    pub fn new(audit_store: &'a AuditStore<'a>) -> Self {
+        Self { audit_store }
+    }
+
+    pub async fn list_events(
+        &self,
+        req: &ListAuditEventsReq,
+    ) -> Result<ListAuditEventsResp, AppError> {
+        if req.offset < 0 || req.limit <= 0 || req.limit > MAX_PAGE_SIZE {
+            return Err(AppError::InvalidArgument(format!(
+                "offset must be >= 0 and limit in 1..={}",
+                MAX_PAGE_SIZE
+            )));
+        }
+
+        let filter = AuditFilter {
+            actor_id: req.actor_id,
+            action: req.action.clone(),
+            since: req.since,
+            until: req.until,
+        };
+
+        // fetch one extra row to learn whether another page exists.
+        let mut events = self
+            .audit_store
+            .list(&filter, req.limit + 1, req.offset)
+            .await?;
+        let has_more = events.len() as i64 > req.limit;
+        events.truncate(req.limit as usize);
+
+        Ok(ListAuditEventsResp { events, has_more })
+    }
+}
@@ -0,0 +1,235 @@
+use validator::Validate;
+
+use crate::{
+    auth::api_token::{API_SCOPES, generate_token, hash_token},
+    dto::{
+        bot::{
+            ApiToken, CreateApiTokenReq, CreateApiTokenResp, CreateBotReq, ListApiTokensResp,
+            ListBotsResp,
+        },
+        user::User as UserDto,
+    },
+    errors::AppError,
+    models::{
+        api_token::{ApiTokenStore, CreateApiToken},
+        user::{CreateUser, UpdateUser, User, UserRepository},
+    },
+    service::{mention::is_name_char, user::hash_password},
+};
+
+const MAX_BOTS_PER_OWNER: usize = 20;
+const MAX_TOKENS_PER_BOT: usize = 10;
+const PWD_LEN: usize = 32;
+
+/// Bots are users owned by a human. They cannot log in and act only through API tokens.
+pub struct BotService<'a> {
+    user_store: &'a UserRepository<'a>,
+    token_store: &'a ApiTokenStore<'a>,
+}
+
+impl<'a> BotService<'a> {
+    pub fn new(user_store: &'a UserRepository, token_store: &'a ApiTokenStore<'a>) -> Self {
+        Self {
+            user_store,
+            token_store,
+        }
+    }
+
+    pub async fn create_bot(
+        &self,
+        owner: &UserDto,
+        req: &CreateBotReq,
+    ) -> Result<UserDto, AppError> {
+        req.validate()?;
+        if owner.is_bot {
+            return Err(AppError::PermissionDenied(
+                "bots cannot own bots".to_string(),
+            ));
+        }
+        // bots are mentioned by username like everybody else.
+        if !req.username.chars().all(is_name_char) {
+            return Err(AppError::InvalidArgument(
+                "user name is invalid".to_string(),
+            ));
+        }
+        if self
+            .user_store
+            .get_by_username(&req.username)
+            .await?
+            .is_some()
+        {
+            return Err(AppError::AlreadyExists(
+                "username has registered".to_string(),
+            ));
+        }
+        if self.user_store.list_bots(owner.id).await?.len() >= MAX_BOTS_PER_OWNER {
+            return Err(AppError::InvalidArgument(format!(
+                "a user can own at most {} bots",
+                MAX_BOTS_PER_OWNER
+            )));
+        }
+
+        let bot = self
+            .user_store
+            .create(&CreateUser {
+                username: req.username.clone(),
+                avatar_url: req.avatar_url.clone().unwrap_or_default(),
+                // bots cannot log in, the password only fills the column.
+                password_hash: hash_password(&nanoid::nanoid!(PWD_LEN))?,
+                display_name: req.display_name.clone(),
+                is_active: true,
+                is_bot: true,
+                owner_id: Some(owner.id),
+            })
+            .await?;
+
+        Ok(bot.into())
+    }
+
+    pub async fn list_bots(&self, owner: &UserDto) -> Result<ListBotsResp, AppError> {
+        let bots = self.user_store.list_bots(owner.id).await?;
+        Ok(ListBotsResp {
+            bots: bots.into_iter().map(UserDto::from).collect(),
+        })
+    }
+
+    // The bot is deactivated rather than deleted so its messages keep their sender.
+    pub async fn delete_bot(&self, actor: &UserDto, bot_id: i64) -> Result<(), AppError> {
+        let bot = self.get_managed_bot(actor, bot_id).await?;
+
+        self.token_store.revoke_all(bot.id).await?;
+        self.user_store
+            .update(&UpdateUser {
+                id: bot.id,
+                is_active: Some(false),
+                ..Default::default()
+            })
+            .await?;
+        Ok(())
+    }
+
+    pub async fn create_token(
+        &self,
+        actor: &UserDto,
+        bot_id: i64,
+        req: &CreateApiTokenReq,
+    ) -> Result<CreateApiTokenResp, AppError> {
+        req.validate()?;
+        let bot = self.get_managed_bot(actor, bot_id).await?;
+
+        let mut scopes: Vec<String> = vec![];
+        for scope in &req.scopes {
+            if !API_SCOPES.contains(&scope.as_str()) {
+                return Err(AppError::InvalidArgument(format!(
+                    "unknown scope: {}",
+                    scope
+                )));
+            }
+            if !scopes.contains(scope) {
+                scopes.push(scope.clone());
+            }
+        }
+
+        if self.token_store.list_by_user(bot.id).await?.len() >= MAX_TOKENS_PER_BOT {
+            return Err(AppError::InvalidArgument(format!(
+                "a bot can have at most {} tokens",
+                MAX_TOKENS_PER_BOT
+            )));
+        }
+
+        let (token, token_prefix) = generate_token();
+        let api_token = self
+            .token_store
+            .create(&CreateApiToken {
+                user_id: bot.id,
+                name: req.name.clone(),
+                token_hash: hash_token(&token),
+                token_prefix,
+                scopes,
+                created_by: actor.id,
+                expires_at: req
+                    .expires_in_days
+                    .map(|days| chrono::Utc::now() + chrono::Duration::days(days)),
+            })
+            .await?;
+
+        Ok(CreateApiTokenResp {
+            api_token: api_token.into(),
+            token,
+        })
+    }
+
+    pub async fn list_tokens(
+        &self,
+        actor: &UserDto,
+        bot_id: i64,
+    ) -> Result<ListApiTokensResp, AppError> {
+        let bot = self.get_managed_bot(actor, bot_id).await?;
+
+        let tokens = self.token_store.list_by_user(bot.id).await?;
+        Ok(ListApiTokensResp {
+            tokens: tokens.into_iter().map(ApiToken::from).collect(),
+        })
+    }
+
+    // Issues a new secret for the token, keeping its name, scopes and expiry.
+    pub async fn rotate_token(
+        &self,
+        actor: &UserDto,
+        bot_id: i64,
+        token_id: i64,
+    ) -> Result<CreateApiTokenResp, AppError> {
+        let bot = self.get_managed_bot(actor, bot_id).await?;
+        self.get_token(bot.id, token_id).await?;
+
+        let (token, token_prefix) = generate_token();
+        match self
+            .token_store
+            .rotate(token_id, &hash_token(&token), &token_prefix)
+            .await?
+        {
+            Some(api_token) => Ok(CreateApiTokenResp {
+                api_token: api_token.into(),
+                token,
+            }),
+            None => Err(AppError::NotFound("api token".to_string())),
+        }
+    }
+
+    pub async fn revoke_token(
+        &self,
+        actor: &UserDto,
+        bot_id: i64,
+        token_id: i64,
+    ) -> Result<(), AppError> {
+        let bot = self.get_managed_bot(actor, bot_id).await?;
+        self.get_token(bot.id, token_id).await?;
+
+        if self.token_store.revoke(token_id).await? == 0 {
+            return Err(AppError::NotFound("api token".to_string()));
+        }
+        Ok(())
+    }
+
+    async fn get_token(&self, bot_id: i64, token_id: i64) -> Result<(), AppError> {
+        match self.token_store.get_by_id(token_id).await? {
+            Some(token) if token.user_id == bot_id && token.revoked_at.is_none() => Ok(()),
+            _ => Err(AppError::NotFound("api token".to_string())),
+        }
+    }
+
+    // Owners manage their bots; system admins manage everyone's.
+    async fn get_managed_bot(&self, actor: &UserDto, bot_id: i64) -> Result<User, AppError> {
+        let bot = match self.user_store.get_by_id(bot_id).await? {
+            Some(user) if user.is_bot && user.is_active => user,
+            _ => return Err(AppError::NotFound(format!("bot: {}", bot_id))),
+        };
+
+        if bot.owner_id != Some(actor.id) && !actor.is_admin {
+            return Err(AppError::PermissionDenied(
+                "only the owner can manage this bot".to_string(),
+            ));
+        }
+        Ok(bot)
+    }
+}
@@ -1,57 +1,150 @@
+use serde_json::json;
+use validator::Validate;
+
 use crate::{
-    dto::channel::{
-        Channel as ChanDto, CreateChannelRequest, CreateChannelResp, GetChanResp, JoinChanResp,
-        LeaveChanResp, ListChanMembersResp, ListChanReq, ListChanResp, ListUserChannels,
+    dto::{
+        channel::{
+            ArchiveChanResp, ChangeMemberRoleResp, Channel as ChanDto, CreateChannelRequest,
+            CreateChannelResp, GetChanResp, JoinChanResp, LeaveChanResp, ListChanMembersResp,
+            ListChanReq, ListChanResp, ListUserChannels, SearchChanReq, UpdateChanResp,
+            UpdateChannelReq,
+        },
+        message::{Message as MessageDto, SystemEvent},
     },
     errors::AppError,
     models::{
-        channel::{ChanRepository, CreateChannel},
+        channel::{ChanRepository, Channel, CreateChannel, ROLE_ADMIN, ROLE_MEMBER},
+        message::{Message, MessageStore},
         user::UserRepository,
     },
+    service::{
+        audit::{AuditAction, AuditTarget, Auditor, change, field_change},
+        message::post_system_message,
+    },
 };
 
+const DEFAULT_SEARCH_LIMIT: i64 = 20;
+const MAX_SEARCH_LIMIT: i64 = 100;
+
 pub struct ChannelService<'a> {
     chan_store: &'a ChanRepository<'a>,
     user_store: &'a UserRepository<'a>,
+    msg_store: &'a MessageStore<'a>,
+    auditor: Option<&'a Auditor<'a>>,
 }
 
 impl<'a> ChannelService<'a> {
-    pub fn new(chan_store: &'a ChanRepository, user_store: &'a UserRepository<'a>) -> Self {
+    pub fn new(
+        chan_store: &'a ChanRepository,
+        user_store: &'a UserRepository<'a>,
+        msg_store: &'a MessageStore<'a>,
+    ) -> Self {
         Self {
             chan_store,
             user_store,
+            msg_store,
+            auditor: None,
         }
     }
 
+    pub fn with_auditor(mut self, auditor: &'a Auditor<'a>) -> Self {
+        self.auditor = Some(auditor);
+        self
+    }
+
+    async fn audit(
+        &self,
+        actor_id: i64,
+        action: AuditAction,
+        channel_id: i64,
+        diff: serde_json::Value,
+    ) {
+        if let Some(auditor) = self.auditor {
+            auditor
+                .record(
+                    Some(actor_id),
+                    action,
+                    AuditTarget::Channel(channel_id),
+                    diff,
+                )
+                .await;
+        }
+    }
+
+    // A direct message channel belongs to its two users: nobody joins, leaves or reconfigures it.
+    fn reject_direct(channel: &Channel) -> Result<(), AppError> {
+        if channel.is_direct {
+            return Err(AppError::PermissionDenied(
+                "direct message channels cannot be changed".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    // The channel creator and members with the admin role may manage the channel.
+    async fn is_channel_admin(&self, channel: &Channel, user_id: i64) -> Result<bool, AppError> {
+        if channel.creator_id == user_id {
+            return Ok(true);
+        }
+
+        let member = self
+            .chan_store
+            .get_channel_member(channel.id, user_id)
+            .await?;
+        Ok(member.is_some_and(|m| m.member_role == ROLE_ADMIN))
+    }
+
     pub async fn create_channel(
         &self,
+        creator_id: i64,
         req: &CreateChannelRequest,
     ) -> Result<CreateChannelResp, AppError> {
-        let user = self.user_store.get_by_id(req.creator_id).await?;
+        let user = self.user_store.get_by_id(creator_id).await?;
         if user.is_none() {
             return Err(AppError::NotFound(format!(
                 "user: {} not found",
-                req.creator_id
+                creator_id
             )));
         }
 
         let ch = CreateChannel {
             ch_name: req.ch_name.clone(),
             ch_description: req.ch_desc.clone(),
-            creator_id: req.creator_id,
+            creator_id,
             is_private: req.is_private,
             is_archived: false,
         };
 
         let ch_result = self.chan_store.create(&ch).await?;
+        self.audit(
+            creator_id,
+            AuditAction::ChannelCreated,
+            ch_result.id,
+            json!({ "ch_name": ch_result.ch_name, "is_private": ch_result.is_private }),
+        )
+        .await;
+
+        let system_msg = self
+            .post_system_message(
+                ch_result.id,
+                &SystemEvent::ChannelCreated {
+                    actor_id: creator_id,
+                    ch_name: ch_result.ch_name.clone(),
+                },
+            )
+            .await?;
 
         Ok(CreateChannelResp {
             channel: ChanDto::from(ch_result),
+            system_msg,
         })
     }
 
     pub async fn list_channels(&self, req: &ListChanReq) -> Result<ListChanResp, AppError> {
-        let chan_list = self.chan_store.list_all(req.creator_id).await?;
+        let chan_list = self
+            .chan_store
+            .list_all(req.creator_id, req.include_archived)
+            .await?;
         Ok(ListChanResp {
             channels: chan_list.into_iter().map(|ch| ChanDto::from(ch)).collect(),
         })
@@ -61,12 +154,37 @@ impl<'a> ChannelService<'a> {
         &self,
         req: &ListUserChannels,
     ) -> Result<ListChanResp, AppError> {
-        let chan_list = self.chan_store.list_user_channels(req.user_id).await?;
+        let chan_list = self
+            .chan_store
+            .list_user_channels(req.user_id, req.include_archived)
+            .await?;
         Ok(ListChanResp {
             channels: chan_list.into_iter().map(|ch| ChanDto::from(ch)).collect(),
         })
     }
 
+    pub async fn search_channels(
+        &self,
+        user_id: i64,
+        req: &SearchChanReq,
+    ) -> Result<ListChanResp, AppError> {
+        let query = req.q.trim();
+        if query.is_empty() {
+            return Err(AppError::InvalidArgument(
+                "search query is empty".to_string(),
+            ));
+        }
+
+        let limit = req
+            .limit
+            .unwrap_or(DEFAULT_SEARCH_LIMIT)
+            .clamp(1, MAX_SEARCH_LIMIT);
+        let chan_list = self.chan_store.search(user_id, query, limit).await?;
+        Ok(ListChanResp {
+            channels: chan_list.into_iter().map(ChanDto::from).collect(),
+        })
+    }
+
     pub async fn list_channel_members(
         &self,
         chan_id: i64,
@@ -92,8 +210,21 @@ impl<'a> ChannelService<'a> {
         channel_id: i64,
     ) -> Result<JoinChanResp, AppError> {
         let channel = self.chan_store.get_by_id(channel_id).await?;
-        if channel.is_none() {
-            return Err(AppError::NotFound("channel not found".to_string()));
+        match channel {
+            None => return Err(AppError::NotFound("channel not found".to_string())),
+            Some(ch) if ch.is_archived => {
+                return Err(AppError::PermissionDenied(
+                    "channel is archived and read-only".to_string(),
+                ));
+            }
+            // private channels are invite-only, an owner or admin has to add people.
+            Some(ch) if ch.is_private => {
+                return Err(AppError::PermissionDenied(
+                    "private channels can only be joined when an owner or admin adds you"
+                        .to_string(),
+                ));
+            }
+            Some(ch) => Self::reject_direct(&ch)?,
         }
 
         let user = self.user_store.get_by_id(user_id).await?;
@@ -105,8 +236,17 @@ impl<'a> ChannelService<'a> {
             .chan_store
             .add_channel_member(channel_id, user_id)
             .await?;
+        self.audit(user_id, AuditAction::ChannelJoined, channel_id, json!({}))
+            .await;
+
+        let system_msg = self
+            .post_system_message(channel_id, &SystemEvent::MemberJoined { user_id })
+            .await?;
 
-        Ok(JoinChanResp { chan_members })
+        Ok(JoinChanResp {
+            chan_members,
+            system_msg,
+        })
     }
 
     pub async fn leave_channel(
@@ -114,9 +254,9 @@ impl<'a> ChannelService<'a> {
         user_id: i64,
         channel_id: i64,
     ) -> Result<LeaveChanResp, AppError> {
-        let channel = self.chan_store.get_by_id(channel_id).await?;
-        if channel.is_none() {
-            return Err(AppError::NotFound("channel not found".to_string()));
+        match self.chan_store.get_by_id(channel_id).await? {
+            Some(channel) => Self::reject_direct(&channel)?,
+            None => return Err(AppError::NotFound("channel not found".to_string())),
         }
 
         let user = self.user_store.get_by_id(user_id).await?;
@@ -124,13 +264,410 @@ impl<'a> ChannelService<'a> {
             return Err(AppError::NotFound(format!("user: {} not found", user_id)));
         }
 
-        let _ = self
+        let removed = self
             .chan_store
             .remove_channel_member(channel_id, user_id)
             .await?;
+        let mut system_msg = None;
+        if removed > 0 {
+            self.audit(user_id, AuditAction::ChannelLeft, channel_id, json!({}))
+                .await;
+            system_msg = Some(
+                self.post_system_message(channel_id, &SystemEvent::MemberLeft { user_id })
+                    .await?,
+            );
+        }
 
         let chan_members = self.chan_store.list_channel_members(channel_id).await?;
 
-        Ok(LeaveChanResp { chan_members })
+        Ok(LeaveChanResp {
+            chan_members,
+            system_msg,
+        })
+    }
+
+    pub async fn change_member_role(
+        &self,
+        actor_id: i64,
+        channel_id: i64,
+        user_id: i64,
+        member_role: &str,
+    ) -> Result<ChangeMemberRoleResp, AppError> {
+        if member_role != ROLE_MEMBER && member_role != ROLE_ADMIN {
+            return Err(AppError::InvalidArgument(format!(
+                "member role must be {} or {}",
+                ROLE_MEMBER, ROLE_ADMIN
+            )));
+        }
+
+        let channel = match self.chan_store.get_by_id(channel_id).await? {
+            Some(channel) => channel,
+            None => return Err(AppError::NotFound("channel not found".to_string())),
+        };
+        Self::reject_direct(&channel)?;
+
+        if !self.is_channel_admin(&channel, actor_id).await? {
+            return Err(AppError::PermissionDenied(
+                "only channel owners and admins can change member roles".to_string(),
+            ));
+        }
+
+        let member = match self
+            .chan_store
+            .get_channel_member(channel_id, user_id)
+            .await?
+        {
+            Some(member) => member,
+            None => {
+                return Err(AppError::NotFound(format!(
+                    "member: {} of channel: {}",
+                    user_id, channel_id
+                )));
+            }
+        };
+
+        let chan_member = self
+            .chan_store
+            .update_member_role(channel_id, user_id, member_role)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("member: {}", user_id)))?;
+
+        self.audit(
+            actor_id,
+            AuditAction::MemberRoleChanged,
+            channel_id,
+            json!({
+                "user_id": user_id,
+                "changes": change("member_role", member.member_role, member_role),
+            }),
+        )
+        .await;
+
+        Ok(ChangeMemberRoleResp { chan_member })
+    }
+
+    pub async fn update_channel(
+        &self,
+        actor_id: i64,
+        channel_id: i64,
+        req: &UpdateChannelReq,
+    ) -> Result<UpdateChanResp, AppError> {
+        req.validate()?;
+
+        let mut channel = match self.chan_store.get_by_id(channel_id).await? {
+            Some(channel) => channel,
+            None => return Err(AppError::NotFound("channel not found".to_string())),
+        };
+        Self::reject_direct(&channel)?;
+
+        if !self.is_channel_admin(&channel, actor_id).await? {
+            return Err(AppError::PermissionDenied(
+                "only channel owners and admins can update a channel".to_string(),
+            ));
+        }
+
+        if channel.is_archived {
+            return Err(AppError::PermissionDenied(
+                "channel is archived and read-only".to_string(),
+            ));
+        }
+
+        let mut changes = serde_json::Map::new();
+        let mut events = Vec::new();
+
+        // a taken name fails the update with the unique violation, reported as AlreadyExists.
+        if let Some(name) = req.ch_name.as_ref().filter(|n| **n != channel.ch_name) {
+            events.push(SystemEvent::ChannelRenamed {
+                actor_id,
+                from: channel.ch_name.clone(),
+                to: name.clone(),
+            });
+            changes.insert(
+                "ch_name".to_string(),
+                field_change(channel.ch_name.as_str(), name.as_str()),
+            );
+            channel.ch_name = name.clone();
+        }
+
+        // every other visible change is reported together in one `channel_updated` event.
+        let mut updated = serde_json::Map::new();
+        if let Some(desc) = req
+            .ch_desc
+            .as_ref()
+            .filter(|d| **d != channel.ch_description)
+        {
+            updated.insert(
+                "ch_description".to_string(),
+                field_change(channel.ch_description.as_str(), desc.as_str()),
+            );
+            channel.ch_description = desc.clone();
+        }
+
+        if let Some(topic) = req.ch_topic.as_ref().filter(|t| **t != channel.ch_topic) {
+            updated.insert(
+                "ch_topic".to_string(),
+                field_change(channel.ch_topic.as_str(), topic.as_str()),
+            );
+            channel.ch_topic = topic.clone();
+        }
+
+        if let Some(is_private) = req.is_private.filter(|p| *p != channel.is_private) {
+            updated.insert(
+                "is_private".to_string(),
+                field_change(channel.is_private, is_private),
+            );
+            channel.is_private = is_private;
+        }
+
+        if let Some(admin_only) = req
+            .pins_admin_only
+            .filter(|a| *a != channel.pins_admin_only)
+        {
+            updated.insert(
+                "pins_admin_only".to_string(),
+                field_change(channel.pins_admin_only, admin_only),
+            );
+            channel.pins_admin_only = admin_only;
+        }
+
+        if !updated.is_empty() {
+            changes.extend(updated.clone());
+            events.push(SystemEvent::ChannelUpdated {
+                actor_id,
+                changes: serde_json::Value::Object(updated),
+            });
+        }
+
+        if changes.is_empty() {
+            return Ok(UpdateChanResp {
+                channel: ChanDto::from(channel),
+                system_msgs: vec![],
+            });
+        }
+
+        let channel = self
+            .chan_store
+            .update(&channel)
+            .await?
+            .ok_or_else(|| AppError::NotFound("channel not found".to_string()))?;
+
+        self.audit(
+            actor_id,
+            AuditAction::ChannelUpdated,
+            channel_id,
+            serde_json::Value::Object(changes),
+        )
+        .await;
+
+        let mut system_msgs = Vec::with_capacity(events.len());
+        for event in &events {
+            system_msgs.push(self.post_system_message(channel_id, event).await?);
+        }
+
+        Ok(UpdateChanResp {
+            channel: ChanDto::from(channel),
+            system_msgs,
+        })
+    }
+
+    pub async fn set_archived(
+        &self,
+        actor_id: i64,
+        channel_id: i64,
+        archived: bool,
+    ) -> Result<ArchiveChanResp, AppError> {
+        let mut channel = match self.chan_store.get_by_id(channel_id).await? {
+            Some(channel) => channel,
+            None => return Err(AppError::NotFound("channel not found".to_string())),
+        };
+        Self::reject_direct(&channel)?;
+
+        if !self.is_channel_admin(&channel, actor_id).await? {
+            return Err(AppError::PermissionDenied(
+                "only channel owners and admins can archive a channel".to_string(),
+            ));
+        }
+
+        if channel.is_archived == archived {
+            return Err(AppError::InvalidArgument(format!(
+                "channel is already {}",
+                if archived { "archived" } else { "unarchived" }
+            )));
+        }
+
+        channel.is_archived = archived;
+        let channel = self
+            .chan_store
+            .update(&channel)
+            .await?
+            .ok_or_else(|| AppError::NotFound("channel not found".to_string()))?;
+
+        let (action, event) = if archived {
+            (
+                AuditAction::ChannelArchived,
+                SystemEvent::ChannelArchived { actor_id },
+            )
+        } else {
+            (
+                AuditAction::ChannelUnarchived,
+                SystemEvent::ChannelUnarchived { actor_id },
+            )
+        };
+        self.audit(
+            actor_id,
+            action,
+            channel_id,
+            change("is_archived", !archived, archived),
+        )
+        .await;
+
+        let system_msg = self.post_system_message(channel_id, &event).await?;
+
+        Ok(ArchiveChanResp {
+            channel: ChanDto::from(channel),
+            system_msg,
+        })
+    }
+
+    pub async fn add_member(
+        &self,
+        actor_id: i64,
+        channel_id: i64,
+        user_id: i64,
+    ) -> Result<JoinChanResp, AppError> {
+        let channel = match self.chan_store.get_by_id(channel_id).await? {
+            Some(channel) => channel,
+            None => return Err(AppError::NotFound("channel not found".to_string())),
+        };
+        Self::reject_direct(&channel)?;
+
+        if channel.is_archived {
+            return Err(AppError::PermissionDenied(
+                "channel is archived and read-only".to_string(),
+            ));
+        }
+
+        // private channels are invite-only, so only the owner and admins may add people;
+        // in public channels any member may, outsiders can only join themselves.
+        if channel.is_private {
+            if !self.is_channel_admin(&channel, actor_id).await? {
+                return Err(AppError::PermissionDenied(
+                    "only channel owner or admins can add members to a private channel".to_string(),
+                ));
+            }
+        } else {
+            let actor_member = self
+                .chan_store
+                .get_channel_member(channel_id, actor_id)
+                .await?;
+            if actor_member.is_none() && channel.creator_id != actor_id {
+                return Err(AppError::PermissionDenied(
+                    "only channel members can add members".to_string(),
+                ));
+            }
+        }
+
+        if self.user_store.get_by_id(user_id).await?.is_none() {
+            return Err(AppError::NotFound(format!("user: {}", user_id)));
+        }
+
+        if self
+            .chan_store
+            .get_channel_member(channel_id, user_id)
+            .await?
+            .is_some()
+        {
+            return Err(AppError::AlreadyExists(format!(
+                "user {} is already a member",
+                user_id
+            )));
+        }
+
+        let chan_members = self
+            .chan_store
+            .add_channel_member(channel_id, user_id)
+            .await?;
+        self.audit(
+            actor_id,
+            AuditAction::MemberAdded,
+            channel_id,
+            json!({ "user_id": user_id }),
+        )
+        .await;
+
+        let system_msg = self
+            .post_system_message(channel_id, &SystemEvent::MemberAdded { actor_id, user_id })
+            .await?;
+
+        Ok(JoinChanResp {
+            chan_members,
+            system_msg,
+        })
+    }
+
+    pub async fn remove_member(
+        &self,
+        actor_id: i64,
+        channel_id: i64,
+        user_id: i64,
+    ) -> Result<LeaveChanResp, AppError> {
+        let channel = match self.chan_store.get_by_id(channel_id).await? {
+            Some(channel) => channel,
+            None => return Err(AppError::NotFound("channel not found".to_string())),
+        };
+        Self::reject_direct(&channel)?;
+
+        if !self.is_channel_admin(&channel, actor_id).await? {
+            return Err(AppError::PermissionDenied(
+                "only channel owners and admins can remove members".to_string(),
+            ));
+        }
+
+        if channel.creator_id == user_id {
+            return Err(AppError::InvalidArgument(
+                "the channel owner cannot be removed".to_string(),
+            ));
+        }
+
+        let removed = self
+            .chan_store
+            .remove_channel_member(channel_id, user_id)
+            .await?;
+        if removed == 0 {
+            return Err(AppError::NotFound(format!(
+                "member: {} of channel: {}",
+                user_id, channel_id
+            )));
+        }
+
+        self.audit(
+            actor_id,
+            AuditAction::MemberRemoved,
+            channel_id,
+            json!({ "user_id": user_id }),
+        )
+        .await;
+
+        let system_msg = self
+            .post_system_message(
+                channel_id,
+                &SystemEvent::MemberRemoved { actor_id, user_id },
+            )
+            .await?;
+        let chan_members = self.chan_store.list_channel_members(channel_id).await?;
+
+        Ok(LeaveChanResp {
+            chan_members,
+            system_msg: Some(system_msg),
+        })
+    }
+
+    async fn post_system_message(
+        &self,
+        channel_id: i64,
+        event: &SystemEvent,
+    ) -> Result<MessageDto, AppError> {
+        let msg: Message = post_system_message(self.msg_store, channel_id, event).await?;
+        Ok(msg.into())
     }
 }
@@ -0,0 +1,565 @@
+use std::time::Duration;
+use tracing::warn;
+
+use validator::Validate;
+
+use crate::{
+    auth::{DecodingKey, EncodingKey},
+    dto::{
+        channel::{Channel as ChanDto, UpdateChannelReq},
+        command::{
+            CommandInfo, ExternalCommandReq, ExternalCommandResp, ListCommandsResp,
+            RegisterCommandReq, RegisterCommandResp, ResponseType,
+        },
+        message::Message as MessageDto,
+        reminder::CreateReminderReq,
+        user::{UpdateProfileReq, User as UserDto},
+        webhook::WebhookEvent,
+    },
+    errors::AppError,
+    models::{
+        channel::{ChanRepository, Channel},
+        command::{CreateSlashCommand, SlashCommand, SlashCommandStore},
+        message::MessageStore,
+        reminder::ReminderStore,
+        user::UserRepository,
+    },
+    service::{
+        channel::ChannelService,
+        mention::{is_name_char, parse_mentions},
+        remind_time::split_reminder,
+        reminder::{ReminderService, user_tz},
+        user::UserService,
+    },
+    webhook::{Delivery, WebhookSender},
+};
+
+// Slow commands would hold up the message box, so they get less time than webhooks.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(3);
+const MAX_ANSWER_BYTES: usize = 16 * 1024;
+const SECRET_LEN: usize = 32;
+
+pub struct BuiltinCommand {
+    pub name: &'static str,
+    pub usage_hint: &'static str,
+    pub description: &'static str,
+}
+
+pub const BUILTIN_COMMANDS: [BuiltinCommand; 6] = [
+    BuiltinCommand {
+        name: "help",
+        usage_hint: "",
+        description: "List the available commands",
+    },
+    BuiltinCommand {
+        name: "topic",
+        usage_hint: "[new topic]",
+        description: "Show or set the channel topic",
+    },
+    BuiltinCommand {
+        name: "invite",
+        usage_hint: "@user [@user ...]",
+        description: "Add people to the channel",
+    },
+    BuiltinCommand {
+        name: "leave",
+        usage_hint: "",
+        description: "Leave the channel",
+    },
+    BuiltinCommand {
+        name: "remind",
+        usage_hint: "me to <what> <when>",
+        description: "Set a reminder, e.g. /remind me to stretch in 30 minutes",
+    },
+    BuiltinCommand {
+        name: "status",
+        usage_hint: "[status text]",
+        description: "Set your status, or clear it when empty",
+    },
+];
+
+/// A slash command typed into the message box: `/name args`.
+#[derive(Debug, PartialEq)]
+pub struct ParsedCommand {
+    pub name: String,
+    pub args: String,
+}
+
+/// Parses `text` as a command. Text such as `/usr/bin` or `//` is an ordinary message.
+pub fn parse_command(text: &str) -> Option<ParsedCommand> {
+    let rest = text.trim_start().strip_prefix('/')?;
+    let name_len = rest.find(|c: char| !is_name_char(c)).unwrap_or(rest.len());
+    let (name, args) = rest.split_at(name_len);
+    if name.is_empty() || !(args.is_empty() || args.starts_with(char::is_whitespace)) {
+        return None;
+    }
+
+    Some(ParsedCommand {
+        name: name.to_lowercase(),
+        args: args.trim().to_string(),
+    })
+}
+
+/// What running a command changed, for the caller to broadcast.
+#[derive(Debug, Default)]
+pub struct CommandOutcome {
+    /// Shown only to the invoker.
+    pub response: Option<String>,
+    pub system_msgs: Vec<MessageDto>,
+    pub updated_channel: Option<ChanDto>,
+    pub webhook_events: Vec<WebhookEvent>,
+    /// Text an external command asked to post to the channel as the invoker.
+    pub post: Option<String>,
+}
+
+impl CommandOutcome {
+    fn respond(text: impl Into<String>) -> Self {
+        Self {
+            response: Some(text.into()),
+            ..Default::default()
+        }
+    }
+}
+
+pub struct CommandService<'a> {
+    chan_store: &'a ChanRepository<'a>,
+    user_store: &'a UserRepository<'a>,
+    msg_store: &'a MessageStore<'a>,
+    command_store: &'a SlashCommandStore<'a>,
+    reminder_store: &'a ReminderStore<'a>,
+    ek: &'a EncodingKey,
+    dk: &'a DecodingKey,
+    sender: &'a WebhookSender,
+}
+
+impl<'a> CommandService<'a> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        chan_store: &'a ChanRepository,
+        user_store: &'a UserRepository,
+        msg_store: &'a MessageStore,
+        command_store: &'a SlashCommandStore,
+        reminder_store: &'a ReminderStore,
+        ek: &'a EncodingKey,
+        dk: &'a DecodingKey,
+        sender: &'a WebhookSender,
+    ) -> Self {
+        Self {
+            chan_store,
+            user_store,
+            msg_store,
+            command_store,
+            reminder_store,
+            ek,
+            dk,
+            sender,
+        }
+    }
+
+    /// Runs a command for `invoker_id` in a channel. Refusals such as a missing permission
+    /// are answered to the invoker instead of failing the request.
+    pub async fn execute(
+        &self,
+        invoker_id: i64,
+        chan_id: i64,
+        cmd: &ParsedCommand,
+    ) -> Result<CommandOutcome, AppError> {
+        let invoker = match self.user_store.get_by_id(invoker_id).await? {
+            Some(user) if user.is_active => UserDto::from(user),
+            _ => return Err(AppError::NotFound(format!("user: {}", invoker_id))),
+        };
+        let chan = match self.chan_store.get_by_id(chan_id).await? {
+            Some(chan) => chan,
+            None => return Err(AppError::NotFound("channel".to_string())),
+        };
+        // commands see the channel's topic and name, and forward them to external urls.
+        if self
+            .chan_store
+            .get_channel_member(chan.id, invoker.id)
+            .await?
+            .is_none()
+        {
+            if chan.is_private {
+                return Err(AppError::NotFound("channel".to_string()));
+            }
+            return Err(AppError::PermissionDenied(format!(
+                "join #{} before running commands in it",
+                chan.ch_name
+            )));
+        }
+
+        let result = match cmd.name.as_str() {
+            "help" => self.help().await,
+            "topic" => self.topic(invoker.id, &chan, &cmd.args).await,
+            "invite" => self.invite(invoker.id, &chan, &cmd.args).await,
+            "leave" => self.leave(invoker.id, &chan).await,
+            "remind" => self.remind(&invoker, &cmd.args).await,
+            "status" => self.status(invoker.id, &cmd.args).await,
+            name => match self.command_store.get_by_name(name).await? {
+                Some(external) => Ok(self
+                    .forward(&external, &invoker.username, invoker.id, &chan, cmd)
+                    .await),
+                None => Ok(CommandOutcome::respond(format!(
+                    "Unknown command /{}. Try /help.",
+                    name
+                ))),
+            },
+        };
+
+        match result {
+            Err(e) if e.status().is_server_error() => Err(e),
+            Err(e) => Ok(CommandOutcome::respond(e.to_string())),
+            Ok(outcome) => Ok(outcome),
+        }
+    }
+
+    pub async fn list_commands(&self) -> Result<ListCommandsResp, AppError> {
+        let mut commands: Vec<CommandInfo> = BUILTIN_COMMANDS
+            .iter()
+            .map(|b| CommandInfo {
+                id: None,
+                name: b.name.to_string(),
+                usage_hint: b.usage_hint.to_string(),
+                description: b.description.to_string(),
+                is_builtin: true,
+            })
+            .collect();
+        commands.extend(
+            self.command_store
+                .list()
+                .await?
+                .into_iter()
+                .map(CommandInfo::from),
+        );
+
+        Ok(ListCommandsResp { commands })
+    }
+
+    pub async fn register_command(
+        &self,
+        admin_id: i64,
+        req: &RegisterCommandReq,
+    ) -> Result<RegisterCommandResp, AppError> {
+        req.validate()?;
+
+        let name = req.name.to_lowercase();
+        if !name.chars().all(is_name_char) {
+            return Err(AppError::InvalidArgument(
+                "command name is invalid".to_string(),
+            ));
+        }
+        if BUILTIN_COMMANDS.iter().any(|b| b.name == name) {
+            return Err(AppError::AlreadyExists(format!(
+                "/{} is a built-in command",
+                name
+            )));
+        }
+        let lower = req.url.to_ascii_lowercase();
+        if !lower.starts_with("http://") && !lower.starts_with("https://") {
+            return Err(AppError::InvalidArgument(
+                "command url must be http or https".to_string(),
+            ));
+        }
+
+        let secret = nanoid::nanoid!(SECRET_LEN);
+        let cmd = self
+            .command_store
+            .create(&CreateSlashCommand {
+                name: name.clone(),
+                description: req.description.clone(),
+                usage_hint: req.usage_hint.clone(),
+                url: req.url.clone(),
+                secret: secret.clone(),
+                creator_id: admin_id,
+            })
+            .await?
+            .ok_or_else(|| AppError::AlreadyExists(format!("/{} is registered", name)))?;
+
+        Ok(RegisterCommandResp {
+            command: cmd.into(),
+            secret,
+        })
+    }
+
+    pub async fn delete_command(&self, cmd_id: i64) -> Result<(), AppError> {
+        if self.command_store.delete(cmd_id).await? == 0 {
+            return Err(AppError::NotFound("command".to_string()));
+        }
+        Ok(())
+    }
+
+    async fn help(&self) -> Result<CommandOutcome, AppError> {
+        let lines: Vec<String> = self
+            .list_commands()
+            .await?
+            .commands
+            .iter()
+            .map(|c| {
+                let usage = if c.usage_hint.is_empty() {
+                    format!("/{}", c.name)
+                } else {
+                    format!("/{} {}", c.name, c.usage_hint)
+                };
+                format!("`{}` {}", usage, c.description)
+            })
+            .collect();
+
+        Ok(CommandOutcome::respond(lines.join("\n")))
+    }
+
+    async fn topic(
+        &self,
+        invoker_id: i64,
+        chan: &Channel,
+        args: &str,
+    ) -> Result<CommandOutcome, AppError> {
+        if args.is_empty() {
+            let topic = if chan.ch_topic.is_empty() {
+                "No topic is set.".to_string()
+            } else {
+                format!("Topic: {}", chan.ch_topic)
+            };
+            return Ok(CommandOutcome::respond(topic));
+        }
+
+        let chan_service = ChannelService::new(self.chan_store, self.user_store, self.msg_store);
+        let req = UpdateChannelReq {
+            ch_name: None,
+            ch_desc: None,
+            ch_topic: Some(args.to_string()),
+            is_private: None,
+            pins_admin_only: None,
+        };
+        let resp = chan_service
+            .update_channel(invoker_id, chan.id, &req)
+            .await?;
+
+        Ok(CommandOutcome {
+            updated_channel: (!resp.system_msgs.is_empty()).then_some(resp.channel),
+            system_msgs: resp.system_msgs,
+            ..Default::default()
+        })
+    }
+
+    async fn invite(
+        &self,
+        invoker_id: i64,
+        chan: &Channel,
+        args: &str,
+    ) -> Result<CommandOutcome, AppError> {
+        let usernames = parse_mentions(args).usernames;
+        if usernames.is_empty() {
+            return Ok(CommandOutcome::respond("Usage: /invite @user [@user ...]"));
+        }
+
+        let users = self.user_store.get_by_usernames(&usernames).await?;
+        let mut outcome = CommandOutcome::default();
+        let mut notes = vec![];
+        for name in &usernames {
+            let Some(user) = users.iter().find(|u| &u.username == name) else {
+                notes.push(format!("@{}: no such user", name));
+                continue;
+            };
+
+            let chan_service =
+                ChannelService::new(self.chan_store, self.user_store, self.msg_store);
+            match chan_service.add_member(invoker_id, chan.id, user.id).await {
+                Ok(resp) => {
+                    outcome.system_msgs.push(resp.system_msg);
+                    outcome
+                        .webhook_events
+                        .push(WebhookEvent::MemberJoined { user_id: user.id });
+                }
+                Err(e) if e.status().is_server_error() => return Err(e),
+                Err(e) => notes.push(format!("@{}: {}", name, e)),
+            }
+        }
+
+        if !notes.is_empty() {
+            outcome.response = Some(notes.join("\n"));
+        }
+        Ok(outcome)
+    }
+
+    async fn leave(&self, invoker_id: i64, chan: &Channel) -> Result<CommandOutcome, AppError> {
+        let chan_service = ChannelService::new(self.chan_store, self.user_store, self.msg_store);
+        let resp = chan_service.leave_channel(invoker_id, chan.id).await?;
+
+        let mut outcome = CommandOutcome::respond(format!("You left #{}.", chan.ch_name));
+        if let Some(system_msg) = resp.system_msg {
+            outcome.system_msgs.push(system_msg);
+            outcome.webhook_events.push(WebhookEvent::MemberLeft {
+                user_id: invoker_id,
+            });
+        }
+        Ok(outcome)
+    }
+
+    async fn remind(&self, invoker: &UserDto, args: &str) -> Result<CommandOutcome, AppError> {
+        let tz = user_tz(invoker);
+        let Some((text, remind_at)) = split_reminder(args, chrono::Utc::now(), tz) else {
+            return Ok(CommandOutcome::respond(
+                "Usage: /remind me to <what> <when>, e.g. /remind me to stretch in 30 minutes",
+            ));
+        };
+
+        let reminder_service = ReminderService::new(
+            self.chan_store,
+            self.user_store,
+            self.msg_store,
+            self.reminder_store,
+        );
+        let req = CreateReminderReq {
+            message_id: None,
+            text,
+            when: None,
+            remind_at: Some(remind_at),
+        };
+        let reminder = reminder_service.create_reminder(invoker, &req).await?;
+
+        Ok(CommandOutcome::respond(format!(
+            "I will remind you \"{}\" on {}.",
+            reminder.text,
+            remind_at.with_timezone(&tz).format("%a %b %-d at %H:%M %Z")
+        )))
+    }
+
+    async fn status(&self, invoker_id: i64, args: &str) -> Result<CommandOutcome, AppError> {
+        let user_service = UserService::new(self.user_store, self.ek, self.dk);
+        let req = UpdateProfileReq {
+            display_name: None,
+            avatar: None,
+            title: None,
+            status_text: Some(args.to_string()),
+            timezone: None,
+        };
+        let user: UserDto = user_service.update_profile(invoker_id, &req).await?;
+
+        let text = if user.status_text.is_empty() {
+            "Your status is cleared.".to_string()
+        } else {
+            format!("Your status is now: {}", user.status_text)
+        };
+        Ok(CommandOutcome::respond(text))
+    }
+
+    // External commands answer within the request; failures are reported to the invoker.
+    async fn forward(
+        &self,
+        cmd: &SlashCommand,
+        username: &str,
+        invoker_id: i64,
+        chan: &Channel,
+        parsed: &ParsedCommand,
+    ) -> CommandOutcome {
+        let command = format!("/{}", cmd.name);
+        let payload = ExternalCommandReq {
+            command: &command,
+            text: &parsed.args,
+            user_id: invoker_id,
+            username,
+            channel_id: chan.id,
+            channel_name: &chan.ch_name,
+        };
+        let body = serde_json::to_vec(&payload).unwrap_or_default();
+
+        match call_external(self.sender, cmd, &body).await {
+            Ok(None) => CommandOutcome::default(),
+            Ok(Some(answer)) if answer.response_type == ResponseType::InChannel => CommandOutcome {
+                post: Some(answer.text),
+                ..Default::default()
+            },
+            Ok(Some(answer)) => CommandOutcome::respond(answer.text),
+            Err(e) => {
+                warn!("command {} error: {}", command, e);
+                CommandOutcome::respond(format!("{} failed: {}", command, e))
+            }
+        }
+    }
+}
+
+async fn call_external(
+    sender: &WebhookSender,
+    cmd: &SlashCommand,
+    body: &[u8],
+) -> Result<Option<ExternalCommandResp>, String> {
+    let sender = sender.clone().with_timeout(COMMAND_TIMEOUT);
+    let delivery = Delivery {
+        id: None,
+        event_type: "slash_command",
+        body,
+    };
+
+    let answer = sender
+        .call(&cmd.url, &cmd.secret, &delivery, MAX_ANSWER_BYTES)
+        .await
+        .map_err(|e| e.to_string())?;
+    if answer.iter().all(u8::is_ascii_whitespace) {
+        return Ok(None);
+    }
+    let resp: ExternalCommandResp =
+        serde_json::from_slice(&answer).map_err(|e| format!("invalid answer: {}", e))?;
+    Ok((!resp.text.trim().is_empty()).then_some(resp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::serve_local;
+    use axum::{Json, Router, routing::post};
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(
+            parse_command("/Topic  Release  week "),
+            Some(ParsedCommand {
+                name: "topic".to_string(),
+                args: "Release  week".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_command("/leave"),
+            Some(ParsedCommand {
+                name: "leave".to_string(),
+                args: "".to_string(),
+            })
+        );
+        assert_eq!(parse_command("/usr/bin is a directory"), None);
+        assert_eq!(parse_command("// not a command"), None);
+        assert_eq!(parse_command("see /topic"), None);
+    }
+
+    #[tokio::test]
+    async fn test_call_external_command() {
+        let app = Router::new().route(
+            "/deploy",
+            post(|Json(req): Json<serde_json::Value>| async move {
+                Json(serde_json::json!({
+                    "text": format!("deploying {}", req["text"].as_str().unwrap_or("")),
+                    "response_type": "in_channel",
+                }))
+            }),
+        );
+        let addr = serve_local(app).await;
+
+        let cmd = SlashCommand {
+            id: 1,
+            name: "deploy".to_string(),
+            description: "".to_string(),
+            usage_hint: "".to_string(),
+            url: format!("http://{}/deploy", addr),
+            secret: "s3cret".to_string(),
+            creator_id: 1,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        };
+        let sender = WebhookSender::new(true);
+
+        let answer = call_external(&sender, &cmd, br#"{"text":"v1.2"}"#)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(answer.text, "deploying v1.2");
+        assert_eq!(answer.response_type, ResponseType::InChannel);
+    }
+}
@@ -0,0 +1,494 @@
+//! A Slack-like Markdown subset: `*bold*`, `_italic_`, `~strike~`, `` `code` ``, fenced code
+//! blocks, `>` quotes, `-`/`1.` lists, `<url|text>` and `[text](url)` links, bare URLs,
+//! `@user` and `#channel` mentions.
+//!
+//! Messages are parsed into a block AST stored next to `text_content`. Raw HTML never becomes
+//! markup: it stays in text nodes, and only http(s)/mailto URLs turn into links.
+
+use serde::{Deserialize, Serialize};
+
+use crate::service::mention::is_name_char;
+
+// Bounds the recursion of nested quotes and styles.
+const MAX_DEPTH: usize = 8;
+const SAFE_SCHEMES: [&str; 3] = ["https://", "http://", "mailto:"];
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Block {
+    Paragraph {
+        children: Vec<Inline>,
+    },
+    CodeBlock {
+        language: Option<String>,
+        code: String,
+    },
+    Quote {
+        children: Vec<Block>,
+    },
+    List {
+        ordered: bool,
+        items: Vec<Vec<Inline>>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Inline {
+    Text { text: String },
+    Bold { children: Vec<Inline> },
+    Italic { children: Vec<Inline> },
+    Strike { children: Vec<Inline> },
+    Code { text: String },
+    Link { url: String, text: Option<String> },
+    UserMention { username: String },
+    ChannelMention { ch_name: String },
+    // `@channel` or `@here`
+    Broadcast { target: String },
+    LineBreak,
+}
+
+pub fn parse(text: &str) -> Vec<Block> {
+    let lines: Vec<&str> = text.lines().collect();
+    parse_blocks(&lines, 0)
+}
+
+fn parse_blocks(lines: &[&str], depth: usize) -> Vec<Block> {
+    let mut blocks = vec![];
+    let mut paragraph: Vec<&str> = vec![];
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i].trim_start();
+
+        if let Some(rest) = line.strip_prefix("```") {
+            flush_paragraph(&mut paragraph, &mut blocks, depth);
+            i += 1;
+
+            // ```one line of code```
+            if let Some(code) = rest.strip_suffix("```") {
+                blocks.push(Block::CodeBlock {
+                    language: None,
+                    code: code.to_string(),
+                });
+                continue;
+            }
+
+            let rest = rest.trim();
+            let language = Some(rest)
+                .filter(|l| !l.is_empty() && !l.contains(char::is_whitespace))
+                .map(str::to_string);
+            let mut code = vec![];
+            if language.is_none() && !rest.is_empty() {
+                code.push(rest);
+            }
+            // an unclosed fence runs to the end of the message.
+            while i < lines.len() {
+                let l = lines[i];
+                i += 1;
+                if let Some(last) = l.trim_end().strip_suffix("```") {
+                    if !last.is_empty() {
+                        code.push(last);
+                    }
+                    break;
+                }
+                code.push(l);
+            }
+
+            blocks.push(Block::CodeBlock {
+                language,
+                code: code.join("\n"),
+            });
+        } else if depth < MAX_DEPTH && quote_body(line).is_some() {
+            flush_paragraph(&mut paragraph, &mut blocks, depth);
+            let mut quoted = vec![];
+            while let Some(body) = lines.get(i).and_then(|l| quote_body(l.trim_start())) {
+                quoted.push(body);
+                i += 1;
+            }
+
+            blocks.push(Block::Quote {
+                children: parse_blocks(&quoted, depth + 1),
+            });
+        } else if let Some((ordered, _)) = list_item(line) {
+            flush_paragraph(&mut paragraph, &mut blocks, depth);
+            let mut items = vec![];
+            while let Some((o, body)) = lines.get(i).and_then(|l| list_item(l.trim_start())) {
+                if o != ordered {
+                    break;
+                }
+                items.push(parse_inlines(body, depth));
+                i += 1;
+            }
+
+            blocks.push(Block::List { ordered, items });
+        } else if line.is_empty() {
+            flush_paragraph(&mut paragraph, &mut blocks, depth);
+            i += 1;
+        } else {
+            paragraph.push(lines[i]);
+            i += 1;
+        }
+    }
+
+    flush_paragraph(&mut paragraph, &mut blocks, depth);
+    blocks
+}
+
+fn flush_paragraph(paragraph: &mut Vec<&str>, blocks: &mut Vec<Block>, depth: usize) {
+    if paragraph.is_empty() {
+        return;
+    }
+
+    let mut children = vec![];
+    for (n, line) in paragraph.drain(..).enumerate() {
+        if n > 0 {
+            children.push(Inline::LineBreak);
+        }
+        children.extend(parse_inlines(line, depth));
+    }
+    blocks.push(Block::Paragraph { children });
+}
+
+fn quote_body(line: &str) -> Option<&str> {
+    line.strip_prefix("> ").or_else(|| line.strip_prefix('>'))
+}
+
+// Returns whether the item is ordered and its text.
+fn list_item(line: &str) -> Option<(bool, &str)> {
+    for bullet in ["- ", "* ", "• "] {
+        if let Some(body) = line.strip_prefix(bullet) {
+            return Some((false, body));
+        }
+    }
+
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    if digits > 0 && digits <= 9 {
+        return line[digits..].strip_prefix(". ").map(|body| (true, body));
+    }
+    None
+}
+
+fn parse_inlines(text: &str, depth: usize) -> Vec<Inline> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut inlines = vec![];
+    let mut text = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        // markers glued to a word (snake_case, a@b.com, C#) are literal.
+        let at_boundary = i == 0 || !(chars[i - 1].is_alphanumeric() || chars[i - 1] == '_');
+        let parsed = match c {
+            '`' => code_span(&chars, i),
+            '*' | '_' | '~' if at_boundary && depth < MAX_DEPTH => styled(&chars, i, depth),
+            '<' => slack_link(&chars, i),
+            '[' => markdown_link(&chars, i),
+            '@' if at_boundary => mention(&chars, i),
+            '#' if at_boundary => channel_mention(&chars, i),
+            'h' if at_boundary => bare_url(&chars, i),
+            _ => None,
+        };
+
+        match parsed {
+            Some((inline, next)) => {
+                if !text.is_empty() {
+                    inlines.push(Inline::Text {
+                        text: std::mem::take(&mut text),
+                    });
+                }
+                inlines.push(inline);
+                i = next;
+            }
+            None => {
+                text.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    if !text.is_empty() {
+        inlines.push(Inline::Text { text });
+    }
+    inlines
+}
+
+// Each helper returns the parsed node and the index just past it.
+
+fn find(chars: &[char], from: usize, c: char) -> Option<usize> {
+    chars[from..].iter().position(|&x| x == c).map(|p| from + p)
+}
+
+fn code_span(chars: &[char], i: usize) -> Option<(Inline, usize)> {
+    let end = find(chars, i + 1, '`').filter(|&end| end > i + 1)?;
+    let text = chars[i + 1..end].iter().collect();
+    Some((Inline::Code { text }, end + 1))
+}
+
+fn styled(chars: &[char], i: usize, depth: usize) -> Option<(Inline, usize)> {
+    let marker = chars[i];
+    if chars.get(i + 1).is_none_or(|c| c.is_whitespace()) {
+        return None;
+    }
+
+    let end = (i + 2..chars.len()).find(|&j| {
+        chars[j] == marker
+            && !chars[j - 1].is_whitespace()
+            && chars.get(j + 1).is_none_or(|c| !c.is_alphanumeric())
+    })?;
+
+    let inner: String = chars[i + 1..end].iter().collect();
+    let children = parse_inlines(&inner, depth + 1);
+    let inline = match marker {
+        '*' => Inline::Bold { children },
+        '_' => Inline::Italic { children },
+        _ => Inline::Strike { children },
+    };
+    Some((inline, end + 1))
+}
+
+// <https://example.com|label> or <https://example.com>
+fn slack_link(chars: &[char], i: usize) -> Option<(Inline, usize)> {
+    let end = find(chars, i + 1, '>')?;
+    let inner: String = chars[i + 1..end].iter().collect();
+    let (url, text) = match inner.split_once('|') {
+        Some((url, text)) => (url, Some(text)),
+        None => (inner.as_str(), None),
+    };
+
+    link(url, text).map(|inline| (inline, end + 1))
+}
+
+// [label](https://example.com)
+fn markdown_link(chars: &[char], i: usize) -> Option<(Inline, usize)> {
+    let close = find(chars, i + 1, ']')?;
+    if chars.get(close + 1) != Some(&'(') {
+        return None;
+    }
+    let end = find(chars, close + 2, ')')?;
+    let text: String = chars[i + 1..close].iter().collect();
+    let url: String = chars[close + 2..end].iter().collect();
+
+    link(&url, Some(&text)).map(|inline| (inline, end + 1))
+}
+
+fn bare_url(chars: &[char], i: usize) -> Option<(Inline, usize)> {
+    let word: String = chars[i..]
+        .iter()
+        .take_while(|c| !c.is_whitespace())
+        .collect();
+    if !word.starts_with("http://") && !word.starts_with("https://") {
+        return None;
+    }
+
+    // leave sentence punctuation after the link as text.
+    let url = word.trim_end_matches(['.', ',', ';', ':', '!', '?', ')', '\'', '"']);
+    let len = url.chars().count();
+    link(url, None).map(|inline| (inline, i + len))
+}
+
+fn link(url: &str, text: Option<&str>) -> Option<Inline> {
+    if !is_safe_url(url) {
+        return None;
+    }
+
+    Some(Inline::Link {
+        url: url.to_string(),
+        text: text.filter(|t| !t.is_empty()).map(str::to_string),
+    })
+}
+
+pub fn is_safe_url(url: &str) -> bool {
+    let lower = url.to_ascii_lowercase();
+    SAFE_SCHEMES
+        .iter()
+        .any(|s| lower.starts_with(s) && lower.len() > s.len())
+        && !url.chars().any(|c| c.is_whitespace() || c.is_control())
+}
+
+fn mention(chars: &[char], i: usize) -> Option<(Inline, usize)> {
+    let len = chars[i + 1..]
+        .iter()
+        .take_while(|&&c| is_name_char(c))
+        .count();
+    let name: String = chars[i + 1..i + 1 + len].iter().collect();
+    let name = name.trim_end_matches(['.', '-']);
+    if name.is_empty() {
+        return None;
+    }
+
+    let next = i + 1 + name.chars().count();
+    let inline = if name.eq_ignore_ascii_case("channel") || name.eq_ignore_ascii_case("here") {
+        Inline::Broadcast {
+            target: name.to_ascii_lowercase(),
+        }
+    } else {
+        Inline::UserMention {
+            username: name.to_string(),
+        }
+    };
+    Some((inline, next))
+}
+
+fn channel_mention(chars: &[char], i: usize) -> Option<(Inline, usize)> {
+    let len = chars[i + 1..]
+        .iter()
+        .take_while(|&&c| c.is_alphanumeric() || c == '-' || c == '_')
+        .count();
+    let name: String = chars[i + 1..i + 1 + len].iter().collect();
+    let name = name.trim_end_matches('-');
+    if name.is_empty() {
+        return None;
+    }
+
+    let next = i + 1 + name.chars().count();
+    Some((
+        Inline::ChannelMention {
+            ch_name: name.to_string(),
+        },
+        next,
+    ))
+}
+
+/// Flattens the AST for search indexing and notifications.
+pub fn to_plain_text(blocks: &[Block]) -> String {
+    blocks
+        .iter()
+        .map(|block| match block {
+            Block::Paragraph { children } => inlines_to_plain_text(children),
+            Block::CodeBlock { code, .. } => code.clone(),
+            Block::Quote { children } => to_plain_text(children),
+            Block::List { items, .. } => items
+                .iter()
+                .map(|item| inlines_to_plain_text(item))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn inlines_to_plain_text(inlines: &[Inline]) -> String {
+    let mut out = String::new();
+    for inline in inlines {
+        match inline {
+            Inline::Text { text } | Inline::Code { text } => out.push_str(text),
+            Inline::Bold { children }
+            | Inline::Italic { children }
+            | Inline::Strike { children } => out.push_str(&inlines_to_plain_text(children)),
+            Inline::Link { url, text } => out.push_str(text.as_deref().unwrap_or(url)),
+            Inline::UserMention { username } => {
+                out.push('@');
+                out.push_str(username);
+            }
+            Inline::ChannelMention { ch_name } => {
+                out.push('#');
+                out.push_str(ch_name);
+            }
+            Inline::Broadcast { target } => {
+                out.push('@');
+                out.push_str(target);
+            }
+            Inline::LineBreak => out.push('\n'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(t: &str) -> Inline {
+        Inline::Text {
+            text: t.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_inline_styles() {
+        let blocks = parse("*bold* and _it_ ~gone~ `x*y*` snake_case_name @bob see #general");
+        assert_eq!(
+            blocks,
+            vec![Block::Paragraph {
+                children: vec![
+                    Inline::Bold {
+                        children: vec![text("bold")]
+                    },
+                    text(" and "),
+                    Inline::Italic {
+                        children: vec![text("it")]
+                    },
+                    text(" "),
+                    Inline::Strike {
+                        children: vec![text("gone")]
+                    },
+                    text(" "),
+                    Inline::Code {
+                        text: "x*y*".to_string()
+                    },
+                    text(" snake_case_name "),
+                    Inline::UserMention {
+                        username: "bob".to_string()
+                    },
+                    text(" see "),
+                    Inline::ChannelMention {
+                        ch_name: "general".to_string()
+                    },
+                ]
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_blocks() {
+        let blocks = parse("> quoted\n- one\n- two\n```rust\nfn main() {}\n```\n1. first");
+        assert_eq!(
+            blocks,
+            vec![
+                Block::Quote {
+                    children: vec![Block::Paragraph {
+                        children: vec![text("quoted")]
+                    }]
+                },
+                Block::List {
+                    ordered: false,
+                    items: vec![vec![text("one")], vec![text("two")]]
+                },
+                Block::CodeBlock {
+                    language: Some("rust".to_string()),
+                    code: "fn main() {}".to_string()
+                },
+                Block::List {
+                    ordered: true,
+                    items: vec![vec![text("first")]]
+                },
+            ]
+        );
+        assert_eq!(
+            to_plain_text(&blocks),
+            "quoted\none\ntwo\nfn main() {}\nfirst"
+        );
+    }
+
+    #[test]
+    fn test_dangerous_html_and_links() {
+        let blocks = parse(
+            "<script>alert(1)</script> <javascript:alert(1)|click> [ok](https://example.com)",
+        );
+        assert_eq!(
+            blocks,
+            vec![Block::Paragraph {
+                children: vec![
+                    text("<script>alert(1)</script> <javascript:alert(1)|click> "),
+                    Inline::Link {
+                        url: "https://example.com".to_string(),
+                        text: Some("ok".to_string())
+                    },
+                ]
+            }]
+        );
+        assert!(!is_safe_url("javascript:alert(1)"));
+        assert!(is_safe_url("https://example.com/a?b=1"));
+    }
+}
@@ -0,0 +1,97 @@
+use crate::service::markdown::{self, Block, Inline};
+
+/// The `@` mentions found in a message's text.
+#[derive(Debug, Default, PartialEq)]
+pub struct ParsedMentions {
+    pub usernames: Vec<String>,
+    pub channel: bool,
+    pub here: bool,
+}
+
+impl ParsedMentions {
+    pub fn is_empty(&self) -> bool {
+        self.usernames.is_empty() && !self.channel && !self.here
+    }
+}
+
+// Usernames have no enforced charset, so accept what people commonly use and drop
+// trailing sentence punctuation ("thanks @bob.").
+pub(crate) fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.' || c == '-'
+}
+
+/// Finds `@username`, `@channel` and `@here` mentions as the Markdown parser reads them,
+/// so an `@` glued to a preceding word (e-mail addresses) and anything in code is ignored.
+pub fn parse_mentions(text: &str) -> ParsedMentions {
+    let mut parsed = ParsedMentions::default();
+    collect_blocks(&markdown::parse(text), &mut parsed);
+    parsed
+}
+
+fn collect_blocks(blocks: &[Block], parsed: &mut ParsedMentions) {
+    for block in blocks {
+        match block {
+            Block::Paragraph { children } => collect_inlines(children, parsed),
+            Block::Quote { children } => collect_blocks(children, parsed),
+            Block::List { items, .. } => {
+                for item in items {
+                    collect_inlines(item, parsed);
+                }
+            }
+            Block::CodeBlock { .. } => {}
+        }
+    }
+}
+
+fn collect_inlines(inlines: &[Inline], parsed: &mut ParsedMentions) {
+    for inline in inlines {
+        match inline {
+            Inline::Bold { children }
+            | Inline::Italic { children }
+            | Inline::Strike { children } => collect_inlines(children, parsed),
+            Inline::UserMention { username } if !parsed.usernames.contains(username) => {
+                parsed.usernames.push(username.clone());
+            }
+            Inline::Broadcast { target } if target == "channel" => parsed.channel = true,
+            Inline::Broadcast { .. } => parsed.here = true,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mentions() {
+        let parsed = parse_mentions("hey @alice_w and @bob.smith, see @alice_w's note @here.");
+        assert_eq!(
+            parsed,
+            ParsedMentions {
+                usernames: vec!["alice_w".to_string(), "bob.smith".to_string()],
+                channel: false,
+                here: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_mentions_skips_emails_and_code() {
+        let parsed = parse_mentions("mail ops@example.com, run `@channel` then ping @Channel");
+        assert_eq!(
+            parsed,
+            ParsedMentions {
+                usernames: vec![],
+                channel: true,
+                here: false,
+            }
+        );
+        assert!(parse_mentions("just an @ sign").is_empty());
+        assert!(parse_mentions("```\nping @alice\n```").is_empty());
+        assert_eq!(
+            parse_mentions("> *@alice* said\n- ask @bob").usernames,
+            vec!["alice", "bob"]
+        );
+    }
+}
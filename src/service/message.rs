@@ -1,19 +1,44 @@
+use std::collections::HashMap;
+
 use serde_json::json;
+use validator::Validate;
 
 use crate::{
-    dto::message::{ListMessagesReq, SendMessageReq},
+    dto::{
+        SimpleUser,
+        message::{
+            ListMentionsReq, ListMessagesReq, ListMessagesResp, ListPinsResp, Mention,
+            Message as MessageDto, MessageContentType as MessageCTDto, PinMessageResp,
+            PinnedMessage as PinDto, SendMessageReq, SystemEvent, UnpinMessageResp,
+            UpdateMessageReq,
+        },
+    },
     errors::AppError,
     models::{
-        channel::ChanRepository,
-        message::{CreateMessage, Message, MessageStore},
+        channel::{ChanRepository, Channel, ROLE_ADMIN},
+        message::{
+            CreateMessage, MENTION_CHANNEL, MENTION_HERE, MENTION_USER, Message,
+            MessageContentType, MessageStore, PinOutcome,
+        },
+        poll::{CreatePoll, Poll, PollStore},
         user::UserRepository,
     },
+    service::{
+        audit::{AuditAction, AuditTarget, Auditor, change},
+        markdown,
+        mention::parse_mentions,
+    },
+    unfurl::UnfurlCard,
 };
 
+const MAX_PINS_PER_CHANNEL: i64 = 50;
+const MAX_MENTIONS_PAGE_SIZE: i64 = 100;
+
 pub struct MsgService<'a> {
     chan_store: &'a ChanRepository<'a>,
     user_store: &'a UserRepository<'a>,
     msg_store: &'a MessageStore<'a>,
+    auditor: Option<&'a Auditor<'a>>,
 }
 
 impl<'a> MsgService<'a> {
@@ -26,52 +51,649 @@ impl<'a> MsgService<'a> {
             chan_store,
             user_store,
             msg_store,
+            auditor: None,
         }
     }
 
-    pub async fn get_message(&self, msg_id: i64) -> Result<Option<Message>, AppError> {
-        let message = self.msg_store.get_by_id(msg_id).await?;
-        Ok(message)
+    pub fn with_auditor(mut self, auditor: &'a Auditor<'a>) -> Self {
+        self.auditor = Some(auditor);
+        self
+    }
+
+    async fn audit(
+        &self,
+        actor_id: i64,
+        action: AuditAction,
+        msg_id: i64,
+        diff: serde_json::Value,
+    ) {
+        if let Some(auditor) = self.auditor {
+            auditor
+                .record(Some(actor_id), action, AuditTarget::Message(msg_id), diff)
+                .await;
+        }
+    }
+
+    pub async fn get_message(&self, msg_id: i64) -> Result<Option<MessageDto>, AppError> {
+        let message = match self.msg_store.get_by_id(msg_id).await? {
+            Some(msg) => msg,
+            None => return Ok(None),
+        };
+
+        Ok(self.with_mentions(vec![message]).await?.pop())
     }
 
     pub async fn list_messages(
         &self,
         chan_id: i64,
         list_req: &ListMessagesReq,
-    ) -> Result<Vec<Message>, AppError> {
+    ) -> Result<Vec<MessageDto>, AppError> {
         let messages = self
             .msg_store
             .list_by_channel(chan_id, list_req.limit, list_req.offset)
             .await?;
 
-        Ok(messages)
+        self.with_mentions(messages).await
+    }
+
+    pub async fn list_mentions(
+        &self,
+        user_id: i64,
+        req: &ListMentionsReq,
+    ) -> Result<ListMessagesResp, AppError> {
+        if req.offset < 0 || req.limit <= 0 || req.limit > MAX_MENTIONS_PAGE_SIZE {
+            return Err(AppError::InvalidArgument(format!(
+                "offset must be >= 0 and limit in 1..={}",
+                MAX_MENTIONS_PAGE_SIZE
+            )));
+        }
+
+        // fetch one extra row to learn whether another page exists.
+        let mut messages = self
+            .msg_store
+            .list_mentioning(user_id, req.limit + 1, req.offset)
+            .await?;
+        let has_more = messages.len() as i64 > req.limit;
+        messages.truncate(req.limit as usize);
+
+        Ok(ListMessagesResp {
+            msgs: self.with_mentions(messages).await?,
+            has_more,
+        })
     }
 
     pub async fn send_message(
         &self,
         chan_id: i64,
         send_req: &SendMessageReq,
-    ) -> Result<Message, AppError> {
-        let chan = self.chan_store.get_by_id(chan_id).await?;
-        if chan.is_none() {
-            return Err(AppError::NotFound("channel not found".to_string()));
-        }
+    ) -> Result<MessageDto, AppError> {
+        check_sendable(&send_req.content_type)?;
+
+        self.create_message(chan_id, send_req, json!(send_req.media_metadata))
+            .await
+    }
+
+    /// Posts the message a poll lives on, and creates the poll in the same transaction: its
+    /// text is the question and `spec`, the poll's options and settings, goes into
+    /// `media_metadata` for clients to render.
+    pub async fn send_poll_message(
+        &self,
+        poll_store: &PollStore<'_>,
+        question: &str,
+        spec: serde_json::Value,
+        poll: &CreatePoll,
+    ) -> Result<(MessageDto, Poll), AppError> {
+        let req = SendMessageReq {
+            sender_id: Some(poll.creator_id),
+            parent_msg_id: None,
+            content_type: MessageCTDto::Poll,
+            text_content: question.to_string(),
+            media_url: None,
+            media_metadata: None,
+            sender_override: None,
+            attachments: vec![],
+        };
+        let (new_message, mentions) = self.prepare_message(poll.channel_id, &req, spec).await?;
+        let (msg, poll) = poll_store
+            .create(
+                &new_message,
+                &mentions.mention_types,
+                &mentions.user_ids,
+                poll,
+            )
+            .await?;
+
+        let mut msg = MessageDto::from(msg);
+        msg.mentions = mentions.mentions;
+        Ok((msg, poll))
+    }
 
-        let media_meta = json!(send_req.media_metadata);
+    async fn create_message(
+        &self,
+        chan_id: i64,
+        send_req: &SendMessageReq,
+        media_meta: serde_json::Value,
+    ) -> Result<MessageDto, AppError> {
+        let (new_message, mentions) = self.prepare_message(chan_id, send_req, media_meta).await?;
         let msg = self
             .msg_store
-            .create(&CreateMessage {
-                sender_id: send_req.sender_id,
-                channel_id: chan_id,
-                parent_msg_id: send_req.parent_msg_id,
-                content_type: send_req.content_type.clone().into(),
-                text_content: send_req.text_content.clone(),
-                media_url: send_req.media_url.clone(),
-                media_metadata: media_meta,
-            })
+            .create(&new_message, &mentions.mention_types, &mentions.user_ids)
             .await?;
 
         //todo: send to msg queue, and broadcast to all users.
+        let mut msg = MessageDto::from(msg);
+        msg.mentions = mentions.mentions;
+        Ok(msg)
+    }
+
+    // Checks the channel takes messages and renders the text and mentions to store.
+    async fn prepare_message(
+        &self,
+        chan_id: i64,
+        send_req: &SendMessageReq,
+        media_meta: serde_json::Value,
+    ) -> Result<(CreateMessage, ResolvedMentions), AppError> {
+        send_req.validate()?;
+
+        let chan = match self.chan_store.get_by_id(chan_id).await? {
+            Some(chan) => chan,
+            None => return Err(AppError::NotFound("channel not found".to_string())),
+        };
+
+        if chan.is_archived {
+            return Err(AppError::PermissionDenied(
+                "channel is archived and read-only".to_string(),
+            ));
+        }
+
+        let (rich_text, plain_text) = render_text(&send_req.text_content);
+        let sender_override = send_req.sender_override.as_ref().map(|o| json!(o));
+        let content_type = send_req.content_type.clone().into();
+        let mentions = self
+            .resolve_mentions(&content_type, &send_req.text_content)
+            .await?;
+        let new_message = CreateMessage {
+            sender_id: send_req.sender_id,
+            channel_id: chan_id,
+            parent_msg_id: send_req.parent_msg_id,
+            content_type,
+            text_content: send_req.text_content.clone(),
+            media_url: send_req.media_url.clone(),
+            media_metadata: media_meta,
+            rich_text,
+            plain_text,
+            sender_override,
+            attachments: json!(send_req.attachments),
+        };
+        Ok((new_message, mentions))
+    }
+
+    pub async fn set_unfurls(
+        &self,
+        msg_id: i64,
+        cards: &[UnfurlCard],
+    ) -> Result<Option<MessageDto>, AppError> {
+        let msg = match self.msg_store.set_unfurls(msg_id, &json!(cards)).await? {
+            Some(msg) => msg,
+            None => return Ok(None),
+        };
+
+        Ok(self.with_mentions(vec![msg]).await?.pop())
+    }
+
+    pub async fn edit_message(
+        &self,
+        actor_id: i64,
+        req: &UpdateMessageReq,
+    ) -> Result<MessageDto, AppError> {
+        req.validate()?;
+
+        let mut msg = match self.msg_store.get_by_id(req.id).await? {
+            Some(msg) if msg.channel_id == req.chan_id => msg,
+            _ => return Err(AppError::NotFound("message not found".to_string())),
+        };
+
+        if msg.sender_id != Some(actor_id) {
+            return Err(AppError::PermissionDenied(
+                "only the sender can edit a message".to_string(),
+            ));
+        }
+        self.check_writable(msg.channel_id).await?;
+
+        let old_text = std::mem::replace(&mut msg.text_content, req.text_content.clone());
+        msg.media_url = req.media_url.clone();
+        msg.media_metadata = json!(req.media_metadata);
+        (msg.rich_text, msg.plain_text) = render_text(&msg.text_content);
+
+        let mentions = self
+            .resolve_mentions(&msg.content_type, &msg.text_content)
+            .await?;
+        let updated = self
+            .msg_store
+            .update(&msg, &mentions.mention_types, &mentions.user_ids)
+            .await?
+            .ok_or_else(|| AppError::NotFound("message not found".to_string()))?;
+
+        self.audit(
+            actor_id,
+            AuditAction::MessageEdited,
+            updated.id,
+            json!({
+                "channel_id": updated.channel_id,
+                "changes": change("text_content", old_text, updated.text_content.clone()),
+            }),
+        )
+        .await;
+
+        let mut updated = MessageDto::from(updated);
+        updated.mentions = mentions.mentions;
+        Ok(updated)
+    }
+
+    // Senders may delete their own messages, channel owners/admins and workspace admins any.
+    pub async fn delete_message(
+        &self,
+        actor_id: i64,
+        actor_is_admin: bool,
+        msg_id: i64,
+    ) -> Result<Message, AppError> {
+        let msg = match self.msg_store.get_by_id(msg_id).await? {
+            Some(msg) => msg,
+            None => return Err(AppError::NotFound("message not found".to_string())),
+        };
+
+        if msg.sender_id != Some(actor_id)
+            && !actor_is_admin
+            && !self.is_channel_admin(msg.channel_id, actor_id).await?
+        {
+            return Err(AppError::PermissionDenied(
+                "not allowed to delete this message".to_string(),
+            ));
+        }
+        self.check_writable(msg.channel_id).await?;
+
+        self.msg_store.delete(msg.id).await?;
+        self.audit(
+            actor_id,
+            AuditAction::MessageDeleted,
+            msg.id,
+            json!({
+                "channel_id": msg.channel_id,
+                "sender_id": msg.sender_id,
+                "text_content": msg.text_content,
+            }),
+        )
+        .await;
+
         Ok(msg)
     }
+
+    pub async fn list_pins(&self, user_id: i64, chan_id: i64) -> Result<ListPinsResp, AppError> {
+        let chan = self.get_channel(chan_id).await?;
+        if chan.is_private && !self.is_channel_member(&chan, user_id).await? {
+            return Err(AppError::PermissionDenied(
+                "not a member of this channel".to_string(),
+            ));
+        }
+
+        let pins = self.msg_store.list_pins(chan_id).await?;
+        let msg_ids = pins.iter().map(|p| p.message_id).collect();
+        let messages = self.msg_store.get_by_ids(msg_ids).await?;
+        let mut messages: HashMap<i64, MessageDto> = self
+            .with_mentions(messages)
+            .await?
+            .into_iter()
+            .map(|m| (m.id, m))
+            .collect();
+
+        let user_ids = pins.iter().map(|p| p.pinned_by).collect();
+        let users: HashMap<i64, SimpleUser> = self
+            .user_store
+            .get_user_by_ids(user_ids)
+            .await?
+            .into_iter()
+            .map(|u| (u.id, SimpleUser::from(u)))
+            .collect();
+
+        let pins = pins
+            .into_iter()
+            .filter_map(|p| {
+                let message = messages.remove(&p.message_id)?;
+                let pinned_by = users.get(&p.pinned_by)?.clone();
+                Some(PinDto {
+                    message,
+                    pinned_by,
+                    pinned_at: p.pinned_at,
+                })
+            })
+            .collect();
+
+        Ok(ListPinsResp { pins })
+    }
+
+    pub async fn pin_message(
+        &self,
+        actor_id: i64,
+        chan_id: i64,
+        msg_id: i64,
+    ) -> Result<PinMessageResp, AppError> {
+        let chan = self.get_channel(chan_id).await?;
+        self.check_can_pin(&chan, actor_id).await?;
+
+        let msg = match self.msg_store.get_by_id(msg_id).await? {
+            Some(msg) if msg.channel_id == chan_id => msg,
+            _ => return Err(AppError::NotFound("message not found".to_string())),
+        };
+        if msg.content_type == MessageContentType::System {
+            return Err(AppError::InvalidArgument(
+                "system messages cannot be pinned".to_string(),
+            ));
+        }
+
+        let pin = match self
+            .msg_store
+            .pin(chan_id, msg_id, actor_id, MAX_PINS_PER_CHANNEL)
+            .await?
+        {
+            PinOutcome::Pinned(pin) => pin,
+            PinOutcome::AlreadyPinned => {
+                return Err(AppError::AlreadyExists(
+                    "message is already pinned".to_string(),
+                ));
+            }
+            PinOutcome::Full => {
+                return Err(AppError::InvalidArgument(format!(
+                    "a channel can have at most {} pinned messages",
+                    MAX_PINS_PER_CHANNEL
+                )));
+            }
+        };
+
+        let pinned_by = match self.user_store.get_by_id(actor_id).await? {
+            Some(user) => SimpleUser::from(user),
+            None => return Err(AppError::NotFound(format!("user: {}", actor_id))),
+        };
+
+        let system_msg = post_system_message(
+            self.msg_store,
+            chan_id,
+            &SystemEvent::MessagePinned {
+                actor_id,
+                message_id: msg_id,
+            },
+        )
+        .await?;
+
+        let message = self.with_mentions(vec![msg]).await?.remove(0);
+        Ok(PinMessageResp {
+            pin: PinDto {
+                message,
+                pinned_by,
+                pinned_at: pin.pinned_at,
+            },
+            system_msg: system_msg.into(),
+        })
+    }
+
+    pub async fn unpin_message(
+        &self,
+        actor_id: i64,
+        chan_id: i64,
+        msg_id: i64,
+    ) -> Result<UnpinMessageResp, AppError> {
+        let chan = self.get_channel(chan_id).await?;
+        self.check_can_pin(&chan, actor_id).await?;
+
+        if self.msg_store.unpin(chan_id, msg_id).await? == 0 {
+            return Err(AppError::NotFound("pinned message".to_string()));
+        }
+
+        let system_msg = post_system_message(
+            self.msg_store,
+            chan_id,
+            &SystemEvent::MessageUnpinned {
+                actor_id,
+                message_id: msg_id,
+            },
+        )
+        .await?;
+
+        Ok(UnpinMessageResp {
+            message_id: msg_id,
+            system_msg: system_msg.into(),
+        })
+    }
+
+    // Resolves the mentioned usernames, so the mentions can be stored with the message.
+    async fn resolve_mentions(
+        &self,
+        content_type: &MessageContentType,
+        text: &str,
+    ) -> Result<ResolvedMentions, AppError> {
+        let mut resolved = ResolvedMentions::default();
+        if *content_type == MessageContentType::System {
+            return Ok(resolved);
+        }
+
+        let parsed = parse_mentions(text);
+        let users = if parsed.usernames.is_empty() {
+            vec![]
+        } else {
+            self.user_store.get_by_usernames(&parsed.usernames).await?
+        };
+
+        // keep the order the names were written in.
+        for name in &parsed.usernames {
+            if let Some(user) = users.iter().find(|u| &u.username == name) {
+                resolved.push(
+                    Mention::User {
+                        user_id: user.id,
+                        username: user.username.clone(),
+                    },
+                    MENTION_USER,
+                    Some(user.id),
+                );
+            }
+        }
+        if parsed.channel {
+            resolved.push(Mention::Channel, MENTION_CHANNEL, None);
+        }
+        if parsed.here {
+            resolved.push(Mention::Here, MENTION_HERE, None);
+        }
+        Ok(resolved)
+    }
+
+    // Converts messages to DTOs with their stored mentions resolved.
+    async fn with_mentions(&self, messages: Vec<Message>) -> Result<Vec<MessageDto>, AppError> {
+        let msg_ids = messages.iter().map(|m| m.id).collect();
+        let stored = self.msg_store.list_mentions(msg_ids).await?;
+
+        let user_ids = stored.iter().filter_map(|m| m.user_id).collect::<Vec<_>>();
+        let usernames: HashMap<i64, String> = if user_ids.is_empty() {
+            HashMap::new()
+        } else {
+            self.user_store
+                .get_user_by_ids(user_ids)
+                .await?
+                .into_iter()
+                .map(|u| (u.id, u.username))
+                .collect()
+        };
+
+        let mut by_message: HashMap<i64, Vec<Mention>> = HashMap::new();
+        for m in stored {
+            let mention = match (m.mention_type.as_str(), m.user_id) {
+                (MENTION_USER, Some(user_id)) => match usernames.get(&user_id) {
+                    Some(username) => Mention::User {
+                        user_id,
+                        username: username.clone(),
+                    },
+                    None => continue,
+                },
+                (MENTION_CHANNEL, _) => Mention::Channel,
+                (MENTION_HERE, _) => Mention::Here,
+                _ => continue,
+            };
+            by_message.entry(m.message_id).or_default().push(mention);
+        }
+
+        Ok(messages
+            .into_iter()
+            .map(|msg| {
+                let mentions = by_message.remove(&msg.id).unwrap_or_default();
+                let mut msg = MessageDto::from(msg);
+                msg.mentions = mentions;
+                msg
+            })
+            .collect())
+    }
+
+    async fn get_channel(&self, chan_id: i64) -> Result<Channel, AppError> {
+        match self.chan_store.get_by_id(chan_id).await? {
+            Some(chan) => Ok(chan),
+            None => Err(AppError::NotFound("channel not found".to_string())),
+        }
+    }
+
+    // Archived channels are read-only, their history included.
+    async fn check_writable(&self, chan_id: i64) -> Result<(), AppError> {
+        if self.get_channel(chan_id).await?.is_archived {
+            return Err(AppError::PermissionDenied(
+                "channel is archived and read-only".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    // Members may pin unless the channel restricts pinning to its owner and admins.
+    async fn check_can_pin(&self, chan: &Channel, user_id: i64) -> Result<(), AppError> {
+        if chan.is_archived {
+            return Err(AppError::PermissionDenied(
+                "channel is archived and read-only".to_string(),
+            ));
+        }
+
+        if chan.creator_id == user_id {
+            return Ok(());
+        }
+
+        let member = match self.chan_store.get_channel_member(chan.id, user_id).await? {
+            Some(member) => member,
+            None => {
+                return Err(AppError::PermissionDenied(
+                    "not a member of this channel".to_string(),
+                ));
+            }
+        };
+
+        if chan.pins_admin_only && member.member_role != ROLE_ADMIN {
+            return Err(AppError::PermissionDenied(
+                "only channel admins can pin messages".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn is_channel_member(&self, chan: &Channel, user_id: i64) -> Result<bool, AppError> {
+        if chan.creator_id == user_id {
+            return Ok(true);
+        }
+
+        let member = self.chan_store.get_channel_member(chan.id, user_id).await?;
+        Ok(member.is_some())
+    }
+
+    async fn is_channel_admin(&self, chan_id: i64, user_id: i64) -> Result<bool, AppError> {
+        let chan = self.chan_store.get_by_id(chan_id).await?;
+        if chan.is_some_and(|ch| ch.creator_id == user_id) {
+            return Ok(true);
+        }
+
+        let member = self.chan_store.get_channel_member(chan_id, user_id).await?;
+        Ok(member.is_some_and(|m| m.member_role == ROLE_ADMIN))
+    }
+}
+
+// Mentions as returned to clients, and as the store's parallel columns.
+#[derive(Default)]
+struct ResolvedMentions {
+    mentions: Vec<Mention>,
+    mention_types: Vec<&'static str>,
+    user_ids: Vec<Option<i64>>,
+}
+
+impl ResolvedMentions {
+    fn push(&mut self, mention: Mention, mention_type: &'static str, user_id: Option<i64>) {
+        self.mentions.push(mention);
+        self.mention_types.push(mention_type);
+        self.user_ids.push(user_id);
+    }
+}
+
+// System messages have no sender; the event itself goes into `media_metadata`.
+pub async fn post_system_message(
+    msg_store: &MessageStore<'_>,
+    channel_id: i64,
+    event: &SystemEvent,
+) -> Result<Message, AppError> {
+    msg_store
+        .create(
+            &CreateMessage {
+                channel_id,
+                sender_id: None,
+                parent_msg_id: None,
+                content_type: MessageContentType::System,
+                text_content: event.fallback_text(),
+                media_url: None,
+                media_metadata: json!(event),
+                rich_text: json!([]),
+                plain_text: event.fallback_text(),
+                sender_override: None,
+                attachments: json!([]),
+            },
+            &[],
+            &[],
+        )
+        .await
+}
+
+// Polls and system messages carry metadata clients trust, so only the server creates them.
+fn check_sendable(content_type: &MessageCTDto) -> Result<(), AppError> {
+    match content_type {
+        MessageCTDto::Poll => Err(AppError::InvalidArgument(
+            "polls are created through the polls endpoint".to_string(),
+        )),
+        MessageCTDto::System => Err(AppError::InvalidArgument(
+            "system messages cannot be sent".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+// Parses the Markdown subset once at write time, so reads never have to.
+fn render_text(text: &str) -> (serde_json::Value, String) {
+    let blocks = markdown::parse(text);
+    let plain_text = markdown::to_plain_text(&blocks);
+    (json!(blocks), plain_text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_sendable() {
+        assert!(check_sendable(&MessageCTDto::Text).is_ok());
+        assert!(check_sendable(&MessageCTDto::Image).is_ok());
+        assert!(matches!(
+            check_sendable(&MessageCTDto::Poll),
+            Err(AppError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            check_sendable(&MessageCTDto::System),
+            Err(AppError::InvalidArgument(_))
+        ));
+    }
 }
@@ -1,3 +1,15 @@
+pub mod admin;
+pub mod audit;
+pub mod bot;
 pub mod channel;
+pub mod command;
+pub mod markdown;
+pub mod mention;
 pub mod message;
+pub mod poll;
+pub mod remind_time;
+pub mod reminder;
+pub mod saved;
+pub mod scheduled;
 pub mod user;
+pub mod webhook;
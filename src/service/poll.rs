@@ -0,0 +1,403 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Duration, Utc};
+use serde_json::json;
+use validator::Validate;
+
+use crate::{
+    dto::{
+        message::{PollTally, SystemEvent},
+        poll::{
+            ClosePollResp, CreatePollReq, CreatePollResp, Poll as PollDto, PollOption, PollResp,
+            PollSpec,
+        },
+    },
+    errors::AppError,
+    models::{
+        channel::{ChanRepository, Channel, ROLE_ADMIN},
+        message::MessageStore,
+        poll::{CreatePoll, Poll, PollStore, PollVote, VoteOutcome},
+        user::UserRepository,
+    },
+    service::message::{MsgService, post_system_message},
+};
+
+const MAX_OPTION_LEN: usize = 100;
+const MAX_POLL_DAYS: i64 = 90;
+
+pub struct PollService<'a> {
+    chan_store: &'a ChanRepository<'a>,
+    user_store: &'a UserRepository<'a>,
+    msg_store: &'a MessageStore<'a>,
+    poll_store: &'a PollStore<'a>,
+}
+
+impl<'a> PollService<'a> {
+    pub fn new(
+        chan_store: &'a ChanRepository,
+        user_store: &'a UserRepository,
+        msg_store: &'a MessageStore,
+        poll_store: &'a PollStore,
+    ) -> Self {
+        Self {
+            chan_store,
+            user_store,
+            msg_store,
+            poll_store,
+        }
+    }
+
+    pub async fn create_poll(
+        &self,
+        user_id: i64,
+        chan_id: i64,
+        req: &CreatePollReq,
+    ) -> Result<CreatePollResp, AppError> {
+        req.validate()?;
+
+        let options = clean_options(&req.options)?;
+        if let Some(closes_at) = req.closes_at {
+            let now = Utc::now();
+            if closes_at <= now || closes_at > now + Duration::days(MAX_POLL_DAYS) {
+                return Err(AppError::InvalidArgument(format!(
+                    "closes_at must be within the next {} days",
+                    MAX_POLL_DAYS
+                )));
+            }
+        }
+
+        let chan = self.get_channel(chan_id).await?;
+        if !self.is_member(&chan, user_id).await? {
+            return Err(AppError::PermissionDenied(
+                "not a member of this channel".to_string(),
+            ));
+        }
+
+        let spec = PollSpec {
+            options: options.clone(),
+            allow_multiple: req.allow_multiple,
+            is_anonymous: req.is_anonymous,
+            closes_at: req.closes_at,
+        };
+        let new_poll = CreatePoll {
+            channel_id: chan_id,
+            creator_id: user_id,
+            options,
+            allow_multiple: req.allow_multiple,
+            is_anonymous: req.is_anonymous,
+            closes_at: req.closes_at,
+        };
+        let msg_service = MsgService::new(self.chan_store, self.user_store, self.msg_store);
+        let (message, poll) = msg_service
+            .send_poll_message(self.poll_store, req.question.trim(), json!(spec), &new_poll)
+            .await?;
+
+        let poll = tally(&poll, message.text_content.clone(), &[]);
+        Ok(CreatePollResp { message, poll })
+    }
+
+    pub async fn get_poll(&self, user_id: i64, msg_id: i64) -> Result<PollResp, AppError> {
+        let poll = self.get_visible_poll(user_id, msg_id).await?;
+        self.poll_resp(&poll, user_id).await
+    }
+
+    /// Votes for an option. A vote in a single-choice poll replaces the user's earlier one;
+    /// voting twice for the same option changes nothing.
+    pub async fn vote(
+        &self,
+        user_id: i64,
+        msg_id: i64,
+        option_index: i32,
+    ) -> Result<PollResp, AppError> {
+        let poll = self.get_votable_poll(user_id, msg_id).await?;
+        if option_index < 0 || option_index as usize >= poll.options.len() {
+            return Err(AppError::InvalidArgument(format!(
+                "option must be in 0..{}",
+                poll.options.len()
+            )));
+        }
+
+        let outcome = self
+            .poll_store
+            .vote(msg_id, option_index, user_id, !poll.allow_multiple)
+            .await?;
+        if outcome == VoteOutcome::Closed {
+            return Err(AppError::InvalidArgument("the poll is closed".to_string()));
+        }
+
+        self.poll_resp(&poll, user_id).await
+    }
+
+    // Without an option, takes back all of the user's votes.
+    pub async fn unvote(
+        &self,
+        user_id: i64,
+        msg_id: i64,
+        option_index: Option<i32>,
+    ) -> Result<PollResp, AppError> {
+        let poll = self.get_votable_poll(user_id, msg_id).await?;
+        self.poll_store
+            .unvote(msg_id, option_index, user_id)
+            .await?;
+
+        self.poll_resp(&poll, user_id).await
+    }
+
+    // The poll's creator and the channel's admins can close it early.
+    pub async fn close_poll(&self, actor_id: i64, msg_id: i64) -> Result<ClosePollResp, AppError> {
+        let poll = self.get_visible_poll(actor_id, msg_id).await?;
+        if poll.creator_id != actor_id && !self.is_channel_admin(poll.channel_id, actor_id).await? {
+            return Err(AppError::PermissionDenied(
+                "only the poll's creator or a channel admin can close it".to_string(),
+            ));
+        }
+
+        let poll =
+            self.poll_store.close(msg_id).await?.ok_or_else(|| {
+                AppError::InvalidArgument("the poll is already closed".to_string())
+            })?;
+        self.finish(&poll, Some(actor_id)).await
+    }
+
+    /// Closes the polls whose close time has passed, posting each one's result.
+    pub async fn close_due(&self, limit: i64) -> Result<Vec<ClosePollResp>, AppError> {
+        let mut closed = vec![];
+        for poll in self.poll_store.close_due(limit).await? {
+            closed.push(self.finish(&poll, None).await?);
+        }
+        Ok(closed)
+    }
+
+    // Posts the final result of a closed poll as a system message.
+    async fn finish(&self, poll: &Poll, actor_id: Option<i64>) -> Result<ClosePollResp, AppError> {
+        let votes = self.poll_store.list_votes(poll.message_id).await?;
+        let poll = tally(poll, self.question(poll.message_id).await?, &votes);
+
+        let event = SystemEvent::PollClosed {
+            actor_id,
+            message_id: poll.message_id,
+            question: poll.question.clone(),
+            options: poll
+                .options
+                .iter()
+                .map(|o| PollTally {
+                    text: o.text.clone(),
+                    votes: o.votes,
+                })
+                .collect(),
+        };
+        let system_msg = post_system_message(self.msg_store, poll.channel_id, &event).await?;
+
+        Ok(ClosePollResp {
+            poll,
+            system_msg: system_msg.into(),
+        })
+    }
+
+    async fn poll_resp(&self, poll: &Poll, user_id: i64) -> Result<PollResp, AppError> {
+        let votes = self.poll_store.list_votes(poll.message_id).await?;
+        let my_votes = votes
+            .iter()
+            .filter(|v| v.user_id == user_id)
+            .map(|v| v.option_index)
+            .collect();
+        let poll = tally(poll, self.question(poll.message_id).await?, &votes);
+
+        Ok(PollResp { poll, my_votes })
+    }
+
+    async fn question(&self, msg_id: i64) -> Result<String, AppError> {
+        match self.msg_store.get_by_id(msg_id).await? {
+            Some(msg) => Ok(msg.text_content),
+            None => Err(AppError::NotFound("poll".to_string())),
+        }
+    }
+
+    // Polls in private channels are only visible to members, like their messages.
+    async fn get_visible_poll(&self, user_id: i64, msg_id: i64) -> Result<Poll, AppError> {
+        let poll = match self.poll_store.get_by_message_id(msg_id).await? {
+            Some(poll) => poll,
+            None => return Err(AppError::NotFound("poll".to_string())),
+        };
+
+        let chan = self.get_channel(poll.channel_id).await?;
+        if chan.is_private && !self.is_member(&chan, user_id).await? {
+            return Err(AppError::NotFound("poll".to_string()));
+        }
+        Ok(poll)
+    }
+
+    // Only members of a channel that is not archived can vote.
+    async fn get_votable_poll(&self, user_id: i64, msg_id: i64) -> Result<Poll, AppError> {
+        let poll = self.get_visible_poll(user_id, msg_id).await?;
+        let chan = self.get_channel(poll.channel_id).await?;
+        if chan.is_archived {
+            return Err(AppError::PermissionDenied(
+                "channel is archived and read-only".to_string(),
+            ));
+        }
+        if !self.is_member(&chan, user_id).await? {
+            return Err(AppError::PermissionDenied(
+                "not a member of this channel".to_string(),
+            ));
+        }
+        if poll.is_closed() {
+            return Err(AppError::InvalidArgument("the poll is closed".to_string()));
+        }
+        Ok(poll)
+    }
+
+    async fn get_channel(&self, chan_id: i64) -> Result<Channel, AppError> {
+        match self.chan_store.get_by_id(chan_id).await? {
+            Some(chan) => Ok(chan),
+            None => Err(AppError::NotFound("channel".to_string())),
+        }
+    }
+
+    async fn is_member(&self, chan: &Channel, user_id: i64) -> Result<bool, AppError> {
+        if chan.creator_id == user_id {
+            return Ok(true);
+        }
+
+        let member = self.chan_store.get_channel_member(chan.id, user_id).await?;
+        Ok(member.is_some())
+    }
+
+    async fn is_channel_admin(&self, chan_id: i64, user_id: i64) -> Result<bool, AppError> {
+        let chan = self.get_channel(chan_id).await?;
+        if chan.creator_id == user_id {
+            return Ok(true);
+        }
+
+        let member = self.chan_store.get_channel_member(chan_id, user_id).await?;
+        Ok(member.is_some_and(|m| m.member_role == ROLE_ADMIN))
+    }
+}
+
+// Options are trimmed and must be non-empty and distinct, ignoring case.
+fn clean_options(options: &[String]) -> Result<Vec<String>, AppError> {
+    let mut seen = HashSet::new();
+    let mut cleaned = vec![];
+    for option in options {
+        let option = option.trim();
+        if option.is_empty() || option.chars().count() > MAX_OPTION_LEN {
+            return Err(AppError::InvalidArgument(format!(
+                "options must be 1 to {} characters",
+                MAX_OPTION_LEN
+            )));
+        }
+        if !seen.insert(option.to_lowercase()) {
+            return Err(AppError::InvalidArgument(format!(
+                "duplicate option: {}",
+                option
+            )));
+        }
+        cleaned.push(option.to_string());
+    }
+    Ok(cleaned)
+}
+
+fn tally(poll: &Poll, question: String, votes: &[PollVote]) -> PollDto {
+    let mut voters: HashMap<i32, Vec<i64>> = HashMap::new();
+    for vote in votes {
+        voters
+            .entry(vote.option_index)
+            .or_default()
+            .push(vote.user_id);
+    }
+    let total_voters = votes
+        .iter()
+        .map(|v| v.user_id)
+        .collect::<HashSet<_>>()
+        .len() as i64;
+
+    let options = poll
+        .options
+        .iter()
+        .enumerate()
+        .map(|(i, text)| {
+            let option_voters = voters.remove(&(i as i32)).unwrap_or_default();
+            PollOption {
+                index: i as i32,
+                text: text.clone(),
+                votes: option_voters.len() as i64,
+                voters: if poll.is_anonymous {
+                    vec![]
+                } else {
+                    option_voters
+                },
+            }
+        })
+        .collect();
+
+    PollDto {
+        message_id: poll.message_id,
+        channel_id: poll.channel_id,
+        creator_id: poll.creator_id,
+        question,
+        options,
+        allow_multiple: poll.allow_multiple,
+        is_anonymous: poll.is_anonymous,
+        closes_at: poll.closes_at,
+        closed_at: poll.closed_at,
+        is_closed: poll.is_closed(),
+        total_voters,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vote(option_index: i32, user_id: i64) -> PollVote {
+        PollVote {
+            id: 0,
+            message_id: 1,
+            option_index,
+            user_id,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_tally() {
+        let mut poll = Poll {
+            message_id: 1,
+            channel_id: 2,
+            creator_id: 3,
+            options: vec!["Tea".to_string(), "Coffee".to_string(), "Water".to_string()],
+            allow_multiple: true,
+            is_anonymous: false,
+            closes_at: None,
+            closed_at: None,
+            created_at: Utc::now(),
+        };
+        let votes = [vote(0, 7), vote(1, 7), vote(1, 8)];
+
+        let named = tally(&poll, "Drinks?".to_string(), &votes);
+        assert_eq!(named.total_voters, 2);
+        assert_eq!(
+            named.options.iter().map(|o| o.votes).collect::<Vec<_>>(),
+            vec![1, 2, 0]
+        );
+        assert_eq!(named.options[1].voters, vec![7, 8]);
+        assert!(!named.is_closed);
+
+        poll.is_anonymous = true;
+        poll.closes_at = Some(Utc::now() - Duration::minutes(1));
+        let anonymous = tally(&poll, "Drinks?".to_string(), &votes);
+        assert!(anonymous.options.iter().all(|o| o.voters.is_empty()));
+        assert_eq!(anonymous.options[1].votes, 2);
+        assert!(anonymous.is_closed);
+    }
+
+    #[test]
+    fn test_clean_options() {
+        assert_eq!(
+            clean_options(&[" Yes ".to_string(), "No".to_string()]).unwrap(),
+            vec!["Yes", "No"]
+        );
+        assert!(clean_options(&["Yes".to_string(), "yes".to_string()]).is_err());
+        assert!(clean_options(&["Yes".to_string(), "  ".to_string()]).is_err());
+    }
+}
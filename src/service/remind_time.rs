@@ -0,0 +1,307 @@
+use chrono::{
+    DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday,
+};
+use chrono_tz::Tz;
+
+// The time of day a reminder for a bare date such as "tomorrow" goes off.
+const DEFAULT_HOUR: u32 = 9;
+
+/// Reads when a reminder is due, relative to `now` and in the user's timezone `tz`:
+///
+/// - `in 20 minutes`, `in 2h`, `in 1 hour 30 min`, `in a week`
+/// - `at 5pm`, `at 17:30` (today, or tomorrow once that time has passed)
+/// - `today at 4pm`, `tomorrow`, `tomorrow at 9`, `friday at 10:30`, `next week`
+/// - `2025-06-10`, `2025-06-10 14:00`
+///
+/// Times that are not in the future are rejected.
+pub fn parse_when(input: &str, now: DateTime<Utc>, tz: Tz) -> Option<DateTime<Utc>> {
+    let input = input.trim().to_lowercase();
+    let words: Vec<&str> = input.split_whitespace().collect();
+
+    let at = match words.split_first() {
+        Some((&"in", rest)) => now.checked_add_signed(parse_duration(rest)?)?,
+        _ => {
+            let local = parse_moment(&words, now.with_timezone(&tz))?;
+            to_utc(local, tz)?
+        }
+    };
+    (at > now).then_some(at)
+}
+
+/// Splits the arguments of `/remind` into what to remind about and when, e.g.
+/// `me to call mom tomorrow at 9` or `in 10 minutes check the oven`.
+pub fn split_reminder(args: &str, now: DateTime<Utc>, tz: Tz) -> Option<(String, DateTime<Utc>)> {
+    let mut words: Vec<&str> = args.split_whitespace().collect();
+    if words.first().is_some_and(|w| w.eq_ignore_ascii_case("me")) {
+        words.remove(0);
+    }
+
+    // the longest phrase that reads as a time wins, at the end first.
+    for len in (1..words.len()).rev() {
+        let (what, when) = words.split_at(words.len() - len);
+        if let Some(at) = parse_when(&when.join(" "), now, tz) {
+            return Some((reminder_text(what), at));
+        }
+    }
+    for len in (1..words.len()).rev() {
+        let (when, what) = words.split_at(len);
+        if let Some(at) = parse_when(&when.join(" "), now, tz) {
+            return Some((reminder_text(what), at));
+        }
+    }
+    None
+}
+
+fn reminder_text(words: &[&str]) -> String {
+    let words = match words.split_first() {
+        Some((first, rest)) if ["to", "about", "that"].contains(&first.to_lowercase().as_str()) => {
+            rest
+        }
+        _ => words,
+    };
+    words.join(" ")
+}
+
+fn parse_duration(words: &[&str]) -> Option<Duration> {
+    let mut total = Duration::zero();
+    let mut count: Option<i64> = None;
+
+    for word in words {
+        if let Some(n) = parse_count(word) {
+            if count.replace(n).is_some() {
+                return None;
+            }
+            continue;
+        }
+
+        // `2h` or `90min`, or a unit after a separate count.
+        let split = word.find(|c: char| !c.is_ascii_digit()).unwrap_or(0);
+        let (digits, unit) = word.split_at(split);
+        let n = match (digits.parse::<i64>().ok(), count.take()) {
+            (Some(n), None) => n,
+            (None, Some(n)) => n,
+            _ => return None,
+        };
+        let step = unit_duration(unit)?.checked_mul(n.try_into().ok()?)?;
+        total = total.checked_add(&step)?;
+    }
+
+    (count.is_none() && total > Duration::zero()).then_some(total)
+}
+
+fn parse_count(word: &str) -> Option<i64> {
+    match word {
+        "a" | "an" => Some(1),
+        _ => word.parse().ok(),
+    }
+}
+
+fn unit_duration(unit: &str) -> Option<Duration> {
+    match unit.trim_end_matches(',') {
+        "m" | "min" | "mins" | "minute" | "minutes" => Some(Duration::minutes(1)),
+        "h" | "hr" | "hrs" | "hour" | "hours" => Some(Duration::hours(1)),
+        "d" | "day" | "days" => Some(Duration::days(1)),
+        "w" | "week" | "weeks" => Some(Duration::weeks(1)),
+        _ => None,
+    }
+}
+
+// A local date and time from a day and/or a time of day.
+fn parse_moment(words: &[&str], now: DateTime<Tz>) -> Option<NaiveDateTime> {
+    let (day, time) = match words.iter().position(|w| *w == "at") {
+        Some(i) => (&words[..i], Some(parse_time(&words[i + 1..].concat())?)),
+        None => match words.split_last() {
+            // a time without `at` needs a colon or am/pm, so a bare number isn't one.
+            Some((last, day)) if is_explicit_time(last) => (day, Some(parse_time(last)?)),
+            _ => (words, None),
+        },
+    };
+
+    let today = now.date_naive();
+    let date = match day {
+        [] => {
+            let time = time?;
+            return Some(if time > now.time() {
+                today.and_time(time)
+            } else {
+                today.succ_opt()?.and_time(time)
+            });
+        }
+        ["today"] => today,
+        ["tomorrow"] => today.succ_opt()?,
+        ["next", "week"] => next_weekday(today, Weekday::Mon),
+        [day] | ["on", day] | ["next", day] => match parse_weekday(day) {
+            Some(weekday) => next_weekday(today, weekday),
+            None => NaiveDate::parse_from_str(day, "%Y-%m-%d").ok()?,
+        },
+        _ => return None,
+    };
+
+    let time = time.unwrap_or(NaiveTime::from_hms_opt(DEFAULT_HOUR, 0, 0)?);
+    Some(date.and_time(time))
+}
+
+fn is_explicit_time(word: &str) -> bool {
+    word.contains(':')
+        || word.ends_with("am")
+        || word.ends_with("pm")
+        || matches!(word, "noon" | "midnight")
+}
+
+// `9`, `9am`, `9:30pm`, `17:30`, `noon`, `midnight`
+fn parse_time(text: &str) -> Option<NaiveTime> {
+    match text {
+        "noon" => return NaiveTime::from_hms_opt(12, 0, 0),
+        "midnight" => return NaiveTime::from_hms_opt(0, 0, 0),
+        _ => {}
+    }
+
+    let (clock, meridiem) = match text.strip_suffix("am") {
+        Some(clock) => (clock, Some(false)),
+        None => match text.strip_suffix("pm") {
+            Some(clock) => (clock, Some(true)),
+            None => (text, None),
+        },
+    };
+    let (hour, minute) = match clock.split_once(':') {
+        Some((h, m)) if m.len() == 2 => (h.parse::<u32>().ok()?, m.parse::<u32>().ok()?),
+        Some(_) => return None,
+        None => (clock.parse::<u32>().ok()?, 0),
+    };
+
+    let hour = match meridiem {
+        Some(_) if hour == 0 || hour > 12 => return None,
+        Some(false) => hour % 12,
+        Some(true) => hour % 12 + 12,
+        None => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+fn parse_weekday(word: &str) -> Option<Weekday> {
+    let weekday = match word {
+        "monday" | "mon" => Weekday::Mon,
+        "tuesday" | "tue" | "tues" => Weekday::Tue,
+        "wednesday" | "wed" => Weekday::Wed,
+        "thursday" | "thu" | "thurs" => Weekday::Thu,
+        "friday" | "fri" => Weekday::Fri,
+        "saturday" | "sat" => Weekday::Sat,
+        "sunday" | "sun" => Weekday::Sun,
+        _ => return None,
+    };
+    Some(weekday)
+}
+
+// The next such day after today; "friday" on a Friday means a week from now.
+fn next_weekday(today: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let ahead = (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+    let ahead = if ahead == 0 { 7 } else { ahead };
+    today + Duration::days(ahead as i64)
+}
+
+// A local time skipped by a DST change is moved past the gap; a repeated one takes the first.
+fn to_utc(local: NaiveDateTime, tz: Tz) -> Option<DateTime<Utc>> {
+    tz.from_local_datetime(&local)
+        .earliest()
+        .or_else(|| {
+            tz.from_local_datetime(&(local + Duration::hours(1)))
+                .earliest()
+        })
+        .map(|at| at.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Monday 2025-06-09 10:00 in Shanghai.
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 6, 9, 2, 0, 0).unwrap()
+    }
+
+    fn at(local: &str) -> Option<DateTime<Utc>> {
+        let local = NaiveDateTime::parse_from_str(local, "%Y-%m-%d %H:%M").unwrap();
+        to_utc(local, chrono_tz::Asia::Shanghai)
+    }
+
+    #[test]
+    fn test_parse_when() {
+        let tz = chrono_tz::Asia::Shanghai;
+        let now = now();
+
+        assert_eq!(
+            parse_when("in 20 minutes", now, tz),
+            Some(now + Duration::minutes(20))
+        );
+        assert_eq!(
+            parse_when("in 1 hour 30 min", now, tz),
+            Some(now + Duration::minutes(90))
+        );
+        assert_eq!(parse_when("in 2h", now, tz), Some(now + Duration::hours(2)));
+        assert_eq!(
+            parse_when("in a week", now, tz),
+            Some(now + Duration::weeks(1))
+        );
+        assert_eq!(parse_when("tomorrow", now, tz), at("2025-06-10 09:00"));
+        assert_eq!(
+            parse_when("Tomorrow at 9pm", now, tz),
+            at("2025-06-10 21:00")
+        );
+        assert_eq!(parse_when("at 17:30", now, tz), at("2025-06-09 17:30"));
+        // 9am has passed today.
+        assert_eq!(parse_when("9am", now, tz), at("2025-06-10 09:00"));
+        assert_eq!(
+            parse_when("friday at 10:30", now, tz),
+            at("2025-06-13 10:30")
+        );
+        assert_eq!(parse_when("monday", now, tz), at("2025-06-16 09:00"));
+        assert_eq!(parse_when("next week", now, tz), at("2025-06-16 09:00"));
+        assert_eq!(
+            parse_when("2025-07-01 14:00", now, tz),
+            at("2025-07-01 14:00")
+        );
+
+        assert_eq!(parse_when("today at 8am", now, tz), None);
+        assert_eq!(parse_when("in 20", now, tz), None);
+        assert_eq!(parse_when("at 13pm", now, tz), None);
+        assert_eq!(parse_when("someday", now, tz), None);
+        // too far out to represent, rather than a panic.
+        assert_eq!(parse_when("in 2147483647 weeks", now, tz), None);
+        assert_eq!(
+            parse_when("in 2147483647 weeks 2147483647 weeks", now, tz),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_when_across_dst() {
+        let tz = chrono_tz::America::New_York;
+        // 2025-03-08 12:00 in New York; clocks skip 02:00-03:00 the next night.
+        let now = Utc.with_ymd_and_hms(2025, 3, 8, 17, 0, 0).unwrap();
+
+        assert_eq!(
+            parse_when("tomorrow at 2:30am", now, tz),
+            Some(Utc.with_ymd_and_hms(2025, 3, 9, 7, 30, 0).unwrap())
+        );
+        assert_eq!(
+            parse_when("tomorrow at noon", now, tz),
+            Some(Utc.with_ymd_and_hms(2025, 3, 9, 16, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_split_reminder() {
+        let tz = chrono_tz::Asia::Shanghai;
+        let now = now();
+
+        assert_eq!(
+            split_reminder("me to call mom tomorrow at 9", now, tz),
+            Some(("call mom".to_string(), at("2025-06-10 09:00").unwrap()))
+        );
+        assert_eq!(
+            split_reminder("in 10 minutes check the oven", now, tz),
+            Some(("check the oven".to_string(), now + Duration::minutes(10)))
+        );
+        assert_eq!(split_reminder("me to call mom", now, tz), None);
+    }
+}
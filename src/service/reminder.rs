@@ -0,0 +1,299 @@
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use validator::Validate;
+
+use crate::{
+    dto::{
+        message::{Attachment, Message as MessageDto, MessageContentType, SendMessageReq},
+        reminder::{
+            CreateReminderReq, ListRemindersReq, ListRemindersResp, Reminder as ReminderDto,
+            SnoozeReminderReq,
+        },
+        user::User as UserDto,
+    },
+    errors::AppError,
+    models::{
+        channel::ChanRepository,
+        message::{Message, MessageStore},
+        reminder::{
+            CreateReminder, REMINDER_COMPLETED, REMINDER_DELIVERED, REMINDER_PENDING, Reminder,
+            ReminderStore,
+        },
+        user::UserRepository,
+    },
+    service::{message::MsgService, remind_time::parse_when},
+};
+
+/// The bot that delivers reminders, created by the reminders migration.
+pub const SYSTEM_BOT_USERNAME: &str = "slacbot";
+
+const MAX_PAGE_SIZE: i64 = 100;
+const DEFAULT_SNOOZE_MINUTES: i64 = 20;
+const MAX_REMIND_DAYS: i64 = 366;
+const SNIPPET_LEN: usize = 300;
+
+pub struct ReminderService<'a> {
+    chan_store: &'a ChanRepository<'a>,
+    user_store: &'a UserRepository<'a>,
+    msg_store: &'a MessageStore<'a>,
+    reminder_store: &'a ReminderStore<'a>,
+}
+
+impl<'a> ReminderService<'a> {
+    pub fn new(
+        chan_store: &'a ChanRepository,
+        user_store: &'a UserRepository,
+        msg_store: &'a MessageStore,
+        reminder_store: &'a ReminderStore,
+    ) -> Self {
+        Self {
+            chan_store,
+            user_store,
+            msg_store,
+            reminder_store,
+        }
+    }
+
+    pub async fn create_reminder(
+        &self,
+        user: &UserDto,
+        req: &CreateReminderReq,
+    ) -> Result<ReminderDto, AppError> {
+        req.validate()?;
+
+        let remind_at =
+            resolve_time(user, req.when.as_deref(), req.remind_at)?.ok_or_else(|| {
+                AppError::InvalidArgument("when or remind_at is required".to_string())
+            })?;
+        let text = req.text.trim().to_string();
+        if text.is_empty() && req.message_id.is_none() {
+            return Err(AppError::InvalidArgument(
+                "text or message_id is required".to_string(),
+            ));
+        }
+        if let Some(msg_id) = req.message_id {
+            self.get_visible_message(user.id, msg_id).await?;
+        }
+
+        let reminder = self
+            .reminder_store
+            .create(&CreateReminder {
+                user_id: user.id,
+                message_id: req.message_id,
+                text,
+                remind_at,
+            })
+            .await?;
+
+        Ok(reminder.into())
+    }
+
+    pub async fn list_reminders(
+        &self,
+        user_id: i64,
+        req: &ListRemindersReq,
+    ) -> Result<ListRemindersResp, AppError> {
+        if req.offset < 0 || req.limit <= 0 || req.limit > MAX_PAGE_SIZE {
+            return Err(AppError::InvalidArgument(format!(
+                "offset must be >= 0 and limit in 1..={}",
+                MAX_PAGE_SIZE
+            )));
+        }
+        if let Some(status) = req.status.as_deref()
+            && ![REMINDER_PENDING, REMINDER_DELIVERED, REMINDER_COMPLETED].contains(&status)
+        {
+            return Err(AppError::InvalidArgument(format!(
+                "unknown status: {}",
+                status
+            )));
+        }
+
+        // fetch one extra row to learn whether another page exists.
+        let mut reminders = self
+            .reminder_store
+            .list_by_user(user_id, req.status.as_deref(), req.limit + 1, req.offset)
+            .await?;
+        let has_more = reminders.len() as i64 > req.limit;
+        reminders.truncate(req.limit as usize);
+
+        Ok(ListRemindersResp {
+            reminders: reminders.into_iter().map(ReminderDto::from).collect(),
+            has_more,
+        })
+    }
+
+    pub async fn snooze_reminder(
+        &self,
+        user: &UserDto,
+        id: i64,
+        req: &SnoozeReminderReq,
+    ) -> Result<ReminderDto, AppError> {
+        req.validate()?;
+
+        let remind_at = resolve_time(user, req.when.as_deref(), req.remind_at)?
+            .unwrap_or_else(|| Utc::now() + Duration::minutes(DEFAULT_SNOOZE_MINUTES));
+        let reminder = self.get_own(user.id, id).await?;
+        match self.reminder_store.snooze(reminder.id, remind_at).await? {
+            Some(reminder) => Ok(reminder.into()),
+            None => Err(completed(&reminder)),
+        }
+    }
+
+    pub async fn complete_reminder(&self, user_id: i64, id: i64) -> Result<ReminderDto, AppError> {
+        let reminder = self.get_own(user_id, id).await?;
+        match self.reminder_store.complete(reminder.id).await? {
+            Some(reminder) => Ok(reminder.into()),
+            None => Err(completed(&reminder)),
+        }
+    }
+
+    pub async fn delete_reminder(&self, user_id: i64, id: i64) -> Result<(), AppError> {
+        let reminder = self.get_own(user_id, id).await?;
+        self.reminder_store.delete(reminder.id).await?;
+        Ok(())
+    }
+
+    /// Delivers a claimed reminder as a direct message from the system bot, quoting and
+    /// linking the message it is about.
+    pub async fn deliver(&self, reminder: &Reminder) -> Result<MessageDto, AppError> {
+        let bot = self
+            .user_store
+            .get_by_username(&SYSTEM_BOT_USERNAME.to_string())
+            .await?
+            .filter(|user| user.is_bot)
+            .ok_or_else(|| AppError::NotFound(format!("user: {}", SYSTEM_BOT_USERNAME)))?;
+        let chan = self
+            .chan_store
+            .get_or_create_direct(bot.id, reminder.user_id)
+            .await?;
+
+        // the user may have lost access to the message since.
+        let original = match reminder.message_id {
+            Some(msg_id) => self
+                .get_visible_message(reminder.user_id, msg_id)
+                .await
+                .ok(),
+            None => None,
+        };
+
+        let req = SendMessageReq {
+            sender_id: Some(bot.id),
+            parent_msg_id: None,
+            content_type: MessageContentType::Text,
+            text_content: reminder_text(reminder, original.as_ref()),
+            media_url: None,
+            media_metadata: None,
+            sender_override: None,
+            attachments: original.iter().map(quote).collect(),
+        };
+        let msg_service = MsgService::new(self.chan_store, self.user_store, self.msg_store);
+        let msg = msg_service.send_message(chan.id, &req).await?;
+        self.reminder_store
+            .set_dm_message(reminder.id, msg.id)
+            .await?;
+        Ok(msg)
+    }
+
+    async fn get_own(&self, user_id: i64, id: i64) -> Result<Reminder, AppError> {
+        match self.reminder_store.get_by_id(id).await? {
+            Some(reminder) if reminder.user_id == user_id => Ok(reminder),
+            _ => Err(AppError::NotFound("reminder".to_string())),
+        }
+    }
+
+    async fn get_visible_message(&self, user_id: i64, msg_id: i64) -> Result<Message, AppError> {
+        let msg = match self.msg_store.get_by_id(msg_id).await? {
+            Some(msg) => msg,
+            None => return Err(AppError::NotFound("message".to_string())),
+        };
+
+        let chan = self.chan_store.get_by_id(msg.channel_id).await?;
+        if chan.is_some_and(|ch| ch.creator_id == user_id) {
+            return Ok(msg);
+        }
+
+        match self
+            .chan_store
+            .get_channel_member(msg.channel_id, user_id)
+            .await?
+        {
+            Some(_) => Ok(msg),
+            None => Err(AppError::PermissionDenied(
+                "not a member of this channel".to_string(),
+            )),
+        }
+    }
+}
+
+/// The reminder time from an explicit `remind_at` or a `when` phrase read in the user's
+/// timezone; None when neither was given.
+pub fn resolve_time(
+    user: &UserDto,
+    when: Option<&str>,
+    remind_at: Option<DateTime<Utc>>,
+) -> Result<Option<DateTime<Utc>>, AppError> {
+    let now = Utc::now();
+    let at = match (when, remind_at) {
+        (Some(_), Some(_)) => {
+            return Err(AppError::InvalidArgument(
+                "give either when or remind_at".to_string(),
+            ));
+        }
+        (Some(when), None) => parse_when(when, now, user_tz(user)).ok_or_else(|| {
+            AppError::InvalidArgument(format!("cannot tell when \"{}\" is", when))
+        })?,
+        (None, Some(at)) if at > now => at,
+        (None, Some(_)) => {
+            return Err(AppError::InvalidArgument(
+                "remind_at must be in the future".to_string(),
+            ));
+        }
+        (None, None) => return Ok(None),
+    };
+
+    if at > now + Duration::days(MAX_REMIND_DAYS) {
+        return Err(AppError::InvalidArgument(format!(
+            "reminders can be set at most {} days ahead",
+            MAX_REMIND_DAYS
+        )));
+    }
+    Ok(Some(at))
+}
+
+// Profiles hold validated timezones, but older rows default to UTC.
+pub fn user_tz(user: &UserDto) -> Tz {
+    user.timezone.parse().unwrap_or(Tz::UTC)
+}
+
+fn reminder_text(reminder: &Reminder, original: Option<&Message>) -> String {
+    match (reminder.text.as_str(), original) {
+        ("", Some(_)) => "Reminder: the message below".to_string(),
+        ("", None) => "Reminder about a message you can no longer see".to_string(),
+        (text, _) => format!("Reminder: {}", text),
+    }
+}
+
+fn quote(msg: &Message) -> Attachment {
+    let snippet = match msg.plain_text.char_indices().nth(SNIPPET_LEN) {
+        Some((i, _)) => format!("{}…", &msg.plain_text[..i]),
+        None => msg.plain_text.clone(),
+    };
+
+    Attachment {
+        title: Some("Original message".to_string()),
+        title_link: Some(message_link(msg)),
+        text: Some(snippet),
+        color: None,
+        image_url: None,
+        fields: vec![],
+    }
+}
+
+// Where clients fetch a message from.
+fn message_link(msg: &Message) -> String {
+    format!("/api/v1/messages/{}", msg.id)
+}
+
+fn completed(reminder: &Reminder) -> AppError {
+    AppError::InvalidArgument(format!("reminder {} is already completed", reminder.id))
+}
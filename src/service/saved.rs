@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use crate::{
+    dto::saved::{
+        ListSavedItemsReq, ListSavedItemsResp, SaveItemReq, SavedItem as SavedItemDto,
+        UpdateSavedItemReq,
+    },
+    errors::AppError,
+    models::{
+        channel::ChanRepository,
+        message::{Message, MessageStore},
+        saved::{SavedItem, SavedItemStore},
+    },
+};
+
+const MAX_PAGE_SIZE: i64 = 100;
+
+pub struct SavedService<'a> {
+    chan_store: &'a ChanRepository<'a>,
+    msg_store: &'a MessageStore<'a>,
+    saved_store: &'a SavedItemStore<'a>,
+}
+
+impl<'a> SavedService<'a> {
+    pub fn new(
+        chan_store: &'a ChanRepository,
+        msg_store: &'a MessageStore,
+        saved_store: &'a SavedItemStore,
+    ) -> Self {
+        Self {
+            chan_store,
+            msg_store,
+            saved_store,
+        }
+    }
+
+    pub async fn save_item(
+        &self,
+        user_id: i64,
+        req: &SaveItemReq,
+    ) -> Result<SavedItemDto, AppError> {
+        let msg = self.get_visible_message(user_id, req.message_id).await?;
+        let item = self
+            .saved_store
+            .upsert(user_id, msg.id, req.remind_at)
+            .await?;
+
+        Ok(to_dto(item, msg))
+    }
+
+    pub async fn update_item(
+        &self,
+        user_id: i64,
+        msg_id: i64,
+        req: &UpdateSavedItemReq,
+    ) -> Result<SavedItemDto, AppError> {
+        let msg = self.get_visible_message(user_id, msg_id).await?;
+        let item = self
+            .saved_store
+            .update(user_id, msg_id, req.remind_at, req.is_completed)
+            .await?
+            .ok_or_else(|| AppError::NotFound("saved item".to_string()))?;
+
+        Ok(to_dto(item, msg))
+    }
+
+    pub async fn remove_item(&self, user_id: i64, msg_id: i64) -> Result<(), AppError> {
+        if self.saved_store.delete(user_id, msg_id).await? == 0 {
+            return Err(AppError::NotFound("saved item".to_string()));
+        }
+
+        Ok(())
+    }
+
+    pub async fn list_items(
+        &self,
+        user_id: i64,
+        req: &ListSavedItemsReq,
+    ) -> Result<ListSavedItemsResp, AppError> {
+        if req.offset < 0 || req.limit <= 0 || req.limit > MAX_PAGE_SIZE {
+            return Err(AppError::InvalidArgument(format!(
+                "offset must be >= 0 and limit in 1..={}",
+                MAX_PAGE_SIZE
+            )));
+        }
+
+        // fetch one extra row to learn whether another page exists.
+        let mut items = self
+            .saved_store
+            .list_visible(user_id, req.is_completed, req.limit + 1, req.offset)
+            .await?;
+        let has_more = items.len() as i64 > req.limit;
+        items.truncate(req.limit as usize);
+
+        let msg_ids = items.iter().map(|i| i.message_id).collect();
+        let mut messages: HashMap<i64, Message> = self
+            .msg_store
+            .get_by_ids(msg_ids)
+            .await?
+            .into_iter()
+            .map(|m| (m.id, m))
+            .collect();
+
+        let items = items
+            .into_iter()
+            .filter_map(|item| {
+                let msg = messages.remove(&item.message_id)?;
+                Some(to_dto(item, msg))
+            })
+            .collect();
+
+        Ok(ListSavedItemsResp { items, has_more })
+    }
+
+    // A message can only be saved or updated while the user is a member of its channel,
+    // the same rule `list_visible` applies.
+    async fn get_visible_message(&self, user_id: i64, msg_id: i64) -> Result<Message, AppError> {
+        let msg = match self.msg_store.get_by_id(msg_id).await? {
+            Some(msg) => msg,
+            None => return Err(AppError::NotFound("message".to_string())),
+        };
+
+        match self
+            .chan_store
+            .get_channel_member(msg.channel_id, user_id)
+            .await?
+        {
+            Some(_) => Ok(msg),
+            None => Err(AppError::PermissionDenied(
+                "not a member of this channel".to_string(),
+            )),
+        }
+    }
+}
+
+fn to_dto(item: SavedItem, msg: Message) -> SavedItemDto {
+    SavedItemDto {
+        message: msg.into(),
+        remind_at: item.remind_at,
+        is_completed: item.is_completed,
+        completed_at: item.completed_at,
+        saved_at: item.created_at,
+    }
+}
@@ -0,0 +1,267 @@
+use chrono::{Duration, Utc};
+use serde_json::json;
+use validator::Validate;
+
+use crate::{
+    dto::{
+        message::{Message as MessageDto, MessageContentType, SendMessageReq},
+        scheduled::{
+            ListScheduledMessagesReq, ListScheduledMessagesResp, ScheduleMessageReq,
+            ScheduledMessage as ScheduledDto, UpdateScheduledMessageReq,
+        },
+    },
+    errors::AppError,
+    models::{
+        channel::ChanRepository,
+        message::{MessageContentType as MessageCTDao, MessageStore},
+        scheduled::{
+            CreateScheduledMessage, SCHEDULED_CANCELLED, SCHEDULED_FAILED, SCHEDULED_PENDING,
+            SCHEDULED_SENT, ScheduledMessage, ScheduledMessageStore,
+        },
+        user::UserRepository,
+    },
+    service::message::MsgService,
+};
+
+const MAX_PAGE_SIZE: i64 = 100;
+// How far ahead a message can be scheduled.
+const MAX_SCHEDULE_DAYS: i64 = 120;
+
+pub struct ScheduledService<'a> {
+    chan_store: &'a ChanRepository<'a>,
+    user_store: &'a UserRepository<'a>,
+    msg_store: &'a MessageStore<'a>,
+    scheduled_store: &'a ScheduledMessageStore<'a>,
+}
+
+impl<'a> ScheduledService<'a> {
+    pub fn new(
+        chan_store: &'a ChanRepository,
+        user_store: &'a UserRepository,
+        msg_store: &'a MessageStore,
+        scheduled_store: &'a ScheduledMessageStore,
+    ) -> Self {
+        Self {
+            chan_store,
+            user_store,
+            msg_store,
+            scheduled_store,
+        }
+    }
+
+    pub async fn schedule_message(
+        &self,
+        user_id: i64,
+        req: &ScheduleMessageReq,
+    ) -> Result<ScheduledDto, AppError> {
+        req.validate()?;
+        check_send_at(req.send_at)?;
+
+        match req.content_type {
+            MessageContentType::System => {
+                return Err(AppError::InvalidArgument(
+                    "system messages cannot be scheduled".to_string(),
+                ));
+            }
+            MessageContentType::Text if req.text_content.trim().is_empty() => {
+                return Err(AppError::InvalidArgument(
+                    "text_content is required".to_string(),
+                ));
+            }
+            _ => {}
+        }
+
+        self.check_can_post(user_id, req.channel_id).await?;
+        if let Some(parent_id) = req.parent_msg_id {
+            match self.msg_store.get_by_id(parent_id).await? {
+                Some(parent) if parent.channel_id == req.channel_id => {}
+                _ => return Err(AppError::NotFound("parent message".to_string())),
+            }
+        }
+
+        let msg = self
+            .scheduled_store
+            .create(&CreateScheduledMessage {
+                channel_id: req.channel_id,
+                sender_id: user_id,
+                parent_msg_id: req.parent_msg_id,
+                content_type: req.content_type.clone().into(),
+                text_content: req.text_content.clone(),
+                media_url: req.media_url.clone(),
+                media_metadata: json!(req.media_metadata),
+                send_at: req.send_at,
+            })
+            .await?;
+
+        Ok(msg.into())
+    }
+
+    pub async fn list_messages(
+        &self,
+        user_id: i64,
+        req: &ListScheduledMessagesReq,
+    ) -> Result<ListScheduledMessagesResp, AppError> {
+        if req.offset < 0 || req.limit <= 0 || req.limit > MAX_PAGE_SIZE {
+            return Err(AppError::InvalidArgument(format!(
+                "offset must be >= 0 and limit in 1..={}",
+                MAX_PAGE_SIZE
+            )));
+        }
+
+        let status = req.status.as_deref().unwrap_or(SCHEDULED_PENDING);
+        if ![
+            SCHEDULED_PENDING,
+            SCHEDULED_SENT,
+            SCHEDULED_FAILED,
+            SCHEDULED_CANCELLED,
+        ]
+        .contains(&status)
+        {
+            return Err(AppError::InvalidArgument(format!(
+                "unknown status: {}",
+                status
+            )));
+        }
+
+        // fetch one extra row to learn whether another page exists.
+        let mut msgs = self
+            .scheduled_store
+            .list_by_sender(user_id, req.channel_id, status, req.limit + 1, req.offset)
+            .await?;
+        let has_more = msgs.len() as i64 > req.limit;
+        msgs.truncate(req.limit as usize);
+
+        Ok(ListScheduledMessagesResp {
+            messages: msgs.into_iter().map(ScheduledDto::from).collect(),
+            has_more,
+        })
+    }
+
+    pub async fn update_message(
+        &self,
+        user_id: i64,
+        id: i64,
+        req: &UpdateScheduledMessageReq,
+    ) -> Result<ScheduledDto, AppError> {
+        req.validate()?;
+
+        let msg = self.get_own(user_id, id).await?;
+        let send_at = req.send_at.unwrap_or(msg.send_at);
+        if req.send_at.is_some() {
+            check_send_at(send_at)?;
+        }
+        let text_content = req.text_content.as_ref().unwrap_or(&msg.text_content);
+        if msg.content_type == MessageCTDao::Text && text_content.trim().is_empty() {
+            return Err(AppError::InvalidArgument(
+                "text_content is required".to_string(),
+            ));
+        }
+
+        match self
+            .scheduled_store
+            .update_pending(id, text_content, send_at)
+            .await?
+        {
+            Some(msg) => Ok(msg.into()),
+            None => Err(not_pending(&msg)),
+        }
+    }
+
+    pub async fn cancel_message(&self, user_id: i64, id: i64) -> Result<ScheduledDto, AppError> {
+        let msg = self.get_own(user_id, id).await?;
+        match self.scheduled_store.cancel_pending(id).await? {
+            Some(msg) => Ok(msg.into()),
+            None => Err(not_pending(&msg)),
+        }
+    }
+
+    /// Posts a message the scheduler claimed and records the outcome. The sender must still be
+    /// allowed to post there; if not, the message is marked failed with the reason.
+    pub async fn send_scheduled(&self, msg: &ScheduledMessage) -> Result<MessageDto, AppError> {
+        match self.post(msg).await {
+            Ok(sent) => {
+                self.scheduled_store
+                    .finish(msg.id, SCHEDULED_SENT, Some(sent.id), None)
+                    .await?;
+                Ok(sent)
+            }
+            Err(e) => {
+                let error = e.to_string();
+                self.scheduled_store
+                    .finish(msg.id, SCHEDULED_FAILED, None, Some(&error))
+                    .await?;
+                Err(e)
+            }
+        }
+    }
+
+    async fn post(&self, msg: &ScheduledMessage) -> Result<MessageDto, AppError> {
+        match self.user_store.get_by_id(msg.sender_id).await? {
+            Some(user) if user.is_active => {}
+            _ => return Err(AppError::NotFound(format!("user: {}", msg.sender_id))),
+        }
+        self.check_can_post(msg.sender_id, msg.channel_id).await?;
+
+        let req = SendMessageReq {
+            sender_id: Some(msg.sender_id),
+            parent_msg_id: msg.parent_msg_id,
+            content_type: msg.content_type.clone().into(),
+            text_content: msg.text_content.clone(),
+            media_url: msg.media_url.clone(),
+            media_metadata: serde_json::from_value(msg.media_metadata.clone()).ok(),
+            sender_override: None,
+            attachments: vec![],
+        };
+        let msg_service = MsgService::new(self.chan_store, self.user_store, self.msg_store);
+        msg_service.send_message(msg.channel_id, &req).await
+    }
+
+    async fn get_own(&self, user_id: i64, id: i64) -> Result<ScheduledMessage, AppError> {
+        match self.scheduled_store.get_by_id(id).await? {
+            Some(msg) if msg.sender_id == user_id => Ok(msg),
+            _ => Err(AppError::NotFound("scheduled message".to_string())),
+        }
+    }
+
+    async fn check_can_post(&self, user_id: i64, chan_id: i64) -> Result<(), AppError> {
+        let chan = match self.chan_store.get_by_id(chan_id).await? {
+            Some(chan) => chan,
+            None => return Err(AppError::NotFound("channel".to_string())),
+        };
+        if chan.is_archived {
+            return Err(AppError::PermissionDenied(
+                "channel is archived and read-only".to_string(),
+            ));
+        }
+        if chan.creator_id == user_id {
+            return Ok(());
+        }
+
+        match self.chan_store.get_channel_member(chan_id, user_id).await? {
+            Some(_) => Ok(()),
+            None => Err(AppError::PermissionDenied(
+                "not a member of this channel".to_string(),
+            )),
+        }
+    }
+}
+
+fn check_send_at(send_at: chrono::DateTime<Utc>) -> Result<(), AppError> {
+    let now = Utc::now();
+    if send_at <= now {
+        return Err(AppError::InvalidArgument(
+            "send_at must be in the future".to_string(),
+        ));
+    }
+    if send_at > now + Duration::days(MAX_SCHEDULE_DAYS) {
+        return Err(AppError::InvalidArgument(format!(
+            "send_at must be within {} days",
+            MAX_SCHEDULE_DAYS
+        )));
+    }
+    Ok(())
+}
+
+fn not_pending(msg: &ScheduledMessage) -> AppError {
+    AppError::InvalidArgument(format!("scheduled message {} is no longer pending", msg.id))
+}
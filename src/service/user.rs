@@ -3,11 +3,20 @@ use argon2::{
     password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
 };
 
+use lazy_static::lazy_static;
+use serde_json::json;
+use validator::Validate;
+
 use crate::{
     auth::{DecodingKey, EncodingKey},
-    dto::user::{LoginReq, LoginResp, RegisterRequest, RegisterResponse, User as UserDto},
+    dto::user::{
+        ChangePasswordReq, LoginReq, LoginResp, RegisterRequest, RegisterResponse,
+        UpdateProfileReq, User as UserDto,
+    },
     errors::AppError,
-    models::user::{CreateUser, UserRepository},
+    models::user::{CreateUser, UpdateUser, User as UserDao, UserRepository},
+    ratelimit::LoginGuard,
+    service::audit::{AuditAction, AuditTarget, Auditor},
 };
 
 const MIN_NAME_LEN: usize = 6;
@@ -20,11 +29,50 @@ pub struct UserService<'a> {
     user_store: &'a UserRepository<'a>,
     ek: &'a EncodingKey,
     dk: &'a DecodingKey,
+    auditor: Option<&'a Auditor<'a>>,
+    // the guard and the address logins come from.
+    login_guard: Option<(&'a LoginGuard, String)>,
+    // the shortest and longest password accepted.
+    password_len: (usize, usize),
 }
 
 impl<'a> UserService<'a> {
     pub fn new(user_store: &'a UserRepository, ek: &'a EncodingKey, dk: &'a DecodingKey) -> Self {
-        Self { user_store, ek, dk }
+        Self {
+            user_store,
+            ek,
+            dk,
+            auditor: None,
+            login_guard: None,
+            password_len: (MIN_PWD_LEN, MAX_PWD_LEN),
+        }
+    }
+
+    pub fn with_auditor(mut self, auditor: &'a Auditor<'a>) -> Self {
+        self.auditor = Some(auditor);
+        self
+    }
+
+    pub fn with_password_len(mut self, min: usize, max: usize) -> Self {
+        self.password_len = (min, max);
+        self
+    }
+
+    pub fn with_login_guard(mut self, guard: &'a LoginGuard, client_ip: String) -> Self {
+        self.login_guard = Some((guard, client_ip));
+        self
+    }
+
+    async fn audit(
+        &self,
+        actor_id: Option<i64>,
+        action: AuditAction,
+        target: AuditTarget,
+        diff: serde_json::Value,
+    ) {
+        if let Some(auditor) = self.auditor {
+            auditor.record(actor_id, action, target, diff).await;
+        }
     }
 
     pub async fn create_user(&self, req: &RegisterRequest) -> Result<RegisterResponse, AppError> {
@@ -49,7 +97,8 @@ impl<'a> UserService<'a> {
             ));
         }
 
-        let _ = validate_password(&req.password, MIN_PWD_LEN, MAX_PWD_LEN)?;
+        let (min_len, max_len) = self.password_len;
+        let _ = validate_password(&req.password, min_len, max_len)?;
 
         let pwd_hash = hash_password(&req.password)?;
         let user = self
@@ -60,6 +109,8 @@ impl<'a> UserService<'a> {
                 password_hash: pwd_hash,
                 display_name: req.display_name.clone(),
                 is_active: true,
+                is_bot: false,
+                owner_id: None,
             })
             .await?;
 
@@ -69,22 +120,62 @@ impl<'a> UserService<'a> {
     }
 
     pub async fn login(&self, req: &LoginReq) -> Result<LoginResp, AppError> {
+        let Some((guard, ip)) = &self.login_guard else {
+            return self.authenticate(req).await;
+        };
+
+        guard.check(ip, &req.username)?;
+        let result = self.authenticate(req).await;
+        match &result {
+            Ok(_) => guard.record_success(ip, &req.username),
+            Err(AppError::Unauthorized(_)) => guard.record_failure(ip, &req.username),
+            Err(_) => {}
+        }
+        result
+    }
+
+    async fn authenticate(&self, req: &LoginReq) -> Result<LoginResp, AppError> {
         let user_res = self.user_store.get_by_username(&req.username).await?;
         match user_res {
             Some(user) => {
-                let verified = verify_password(&req.password, &user.password_hash)?;
-                if !verified {
-                    return Err(AppError::Unauthorized("password is correct".to_string()));
+                let target = AuditTarget::User(user.id);
+                if let Some((reason, message)) = login_refusal(&user, &req.password) {
+                    self.audit(
+                        Some(user.id),
+                        AuditAction::LoginFailed,
+                        target,
+                        json!({ "reason": reason }),
+                    )
+                    .await;
+                    return Err(AppError::Unauthorized(message.to_string()));
                 }
 
                 let user_info = UserDto::from(user);
                 let tk = self.ek.sign(user_info.clone())?;
+                self.audit(
+                    Some(user_info.id),
+                    AuditAction::LoginSucceeded,
+                    target,
+                    json!({}),
+                )
+                .await;
                 Ok(LoginResp {
                     user: user_info,
                     token: tk,
                 })
             }
-            None => Err(AppError::NotFound("user not found".to_string())),
+            None => {
+                // hash anyway, so an unknown name takes as long to refuse as a wrong password.
+                let _ = verify_password(&req.password, &DUMMY_HASH);
+                self.audit(
+                    None,
+                    AuditAction::LoginFailed,
+                    AuditTarget::None,
+                    json!({ "reason": "unknown_user", "username": req.username }),
+                )
+                .await;
+                Err(AppError::Unauthorized(INVALID_LOGIN.to_string()))
+            }
         }
     }
 
@@ -95,6 +186,122 @@ impl<'a> UserService<'a> {
             None => Err(AppError::NotFound("user not found".to_string())),
         }
     }
+
+    pub async fn update_profile(
+        &self,
+        user_id: i64,
+        req: &UpdateProfileReq,
+    ) -> Result<UserDto, AppError> {
+        req.validate()?;
+
+        if let Some(tz) = &req.timezone {
+            validate_timezone(tz)?;
+        }
+
+        let updated = self
+            .user_store
+            .update(&UpdateUser {
+                id: user_id,
+                avatar_url: req.avatar.clone(),
+                display_name: req.display_name.clone(),
+                title: req.title.clone(),
+                status_text: req.status_text.clone(),
+                timezone: req.timezone.clone(),
+                ..Default::default()
+            })
+            .await?;
+
+        match updated {
+            Some(user) => Ok(UserDto::from(user)),
+            None => Err(AppError::NotFound("user not found".to_string())),
+        }
+    }
+
+    pub async fn change_password(
+        &self,
+        user_id: i64,
+        req: &ChangePasswordReq,
+    ) -> Result<(), AppError> {
+        let user = match self.user_store.get_by_id(user_id).await? {
+            Some(user) => user,
+            None => return Err(AppError::NotFound("user not found".to_string())),
+        };
+
+        if !verify_password(&req.current_password, &user.password_hash)? {
+            return Err(AppError::Unauthorized(
+                "current password is incorrect".to_string(),
+            ));
+        }
+
+        let (min_len, max_len) = self.password_len;
+        validate_password(&req.new_password, min_len, max_len)?;
+
+        let pwd_hash = hash_password(&req.new_password)?;
+        self.user_store
+            .update(&UpdateUser {
+                id: user_id,
+                password_hash: Some(pwd_hash),
+                must_reset_password: Some(false),
+                ..Default::default()
+            })
+            .await?;
+
+        self.audit(
+            Some(user_id),
+            AuditAction::PasswordChanged,
+            AuditTarget::User(user_id),
+            json!({}),
+        )
+        .await;
+
+        Ok(())
+    }
+
+    pub async fn deactivate(&self, user_id: i64) -> Result<UserDto, AppError> {
+        let updated = self
+            .user_store
+            .update(&UpdateUser {
+                id: user_id,
+                is_active: Some(false),
+                ..Default::default()
+            })
+            .await?;
+
+        match updated {
+            Some(user) => Ok(UserDto::from(user)),
+            None => Err(AppError::NotFound("user not found".to_string())),
+        }
+    }
+}
+
+// Why a login is refused, for the audit log and the caller. The password is checked first so
+// only someone who knows it learns the account is deactivated or a bot.
+// Unknown names and wrong passwords get the same answer, so logins don't reveal who exists.
+const INVALID_LOGIN: &str = "invalid username or password";
+
+lazy_static! {
+    static ref DUMMY_HASH: String =
+        hash_password(&nanoid::nanoid!(32)).expect("hash the dummy password");
+}
+
+fn login_refusal(user: &UserDao, password: &str) -> Option<(&'static str, &'static str)> {
+    // bots made without a password store a hash that doesn't parse; no password matches it.
+    if !verify_password(password, &user.password_hash).unwrap_or(false) {
+        return Some(("wrong_password", INVALID_LOGIN));
+    }
+    if !user.is_active {
+        return Some(("deactivated", "user is deactivated"));
+    }
+    if user.is_bot {
+        return Some(("bot", "bots authenticate with api tokens"));
+    }
+    None
+}
+
+fn validate_timezone(tz: &str) -> Result<(), AppError> {
+    tz.parse::<chrono_tz::Tz>()
+        .map(|_| ())
+        .map_err(|_| AppError::InvalidArgument(format!("unknown timezone: {}", tz)))
 }
 
 fn validate_password(password: &str, min_len: usize, max_len: usize) -> Result<(), AppError> {
@@ -149,7 +356,7 @@ fn validate_password(password: &str, min_len: usize, max_len: usize) -> Result<(
     Ok(())
 }
 
-fn hash_password(password: &str) -> Result<String, AppError> {
+pub(crate) fn hash_password(password: &str) -> Result<String, AppError> {
     let salt = SaltString::generate(&mut OsRng);
 
     // Argon2 with default params (Argon2id v19)
@@ -168,3 +375,112 @@ fn verify_password(password: &str, hash: &str) -> Result<bool, AppError> {
         .verify_password(password.as_bytes(), &parsed_hash)
         .is_ok())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(password: &str) -> UserDao {
+        UserDao {
+            id: 1,
+            username: "johnny".to_string(),
+            avatar_url: "".to_string(),
+            password_hash: hash_password(password).unwrap(),
+            display_name: "Johnny".to_string(),
+            title: "".to_string(),
+            status_text: "".to_string(),
+            timezone: "UTC".to_string(),
+            is_active: true,
+            is_admin: false,
+            must_reset_password: false,
+            is_bot: false,
+            owner_id: None,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_login_refusal() {
+        let mut john = user("Secr3t!pw");
+        assert_eq!(login_refusal(&john, "Secr3t!pw"), None);
+        assert_eq!(
+            login_refusal(&john, "wrong"),
+            Some(("wrong_password", "invalid username or password"))
+        );
+
+        // a deactivated account only says so to someone with its password.
+        john.is_active = false;
+        assert_eq!(
+            login_refusal(&john, "wrong"),
+            Some(("wrong_password", "invalid username or password"))
+        );
+        assert_eq!(
+            login_refusal(&john, "Secr3t!pw"),
+            Some(("deactivated", "user is deactivated"))
+        );
+
+        let mut bot = user("Secr3t!pw");
+        bot.is_bot = true;
+        assert_eq!(
+            login_refusal(&bot, "Secr3t!pw"),
+            Some(("bot", "bots authenticate with api tokens"))
+        );
+        bot.password_hash = "!".to_string();
+        assert_eq!(
+            login_refusal(&bot, "!"),
+            Some(("wrong_password", "invalid username or password"))
+        );
+    }
+
+    #[test]
+    fn test_dummy_hash() {
+        // it has to parse, or unknown names would skip the hashing and answer faster.
+        assert!(PasswordHash::new(&DUMMY_HASH).is_ok());
+        assert!(!verify_password("Secr3t!pw", &DUMMY_HASH).unwrap());
+    }
+
+    #[test]
+    fn test_change_password_rules() {
+        assert!(validate_password("Secr3t!pw", MIN_PWD_LEN, MAX_PWD_LEN).is_ok());
+        for weak in [
+            "S3t!",
+            "secr3t!pw",
+            "SECR3T!PW",
+            "Secret!pw",
+            "Secr3tpw1",
+            "Sécr3t!pw",
+        ] {
+            assert!(
+                validate_password(weak, MIN_PWD_LEN, MAX_PWD_LEN).is_err(),
+                "{}",
+                weak
+            );
+        }
+        // the configured bounds replace the defaults.
+        assert!(validate_password("Secr3t!pw", 10, 20).is_err());
+
+        let hash = hash_password("Secr3t!pw").unwrap();
+        assert!(verify_password("Secr3t!pw", &hash).unwrap());
+        assert!(!verify_password("Secr3t!pW", &hash).unwrap());
+    }
+
+    #[test]
+    fn test_profile_timezone() {
+        assert!(validate_timezone("Europe/Berlin").is_ok());
+        assert!(validate_timezone("UTC").is_ok());
+        assert!(matches!(
+            validate_timezone("Mars/Olympus"),
+            Err(AppError::InvalidArgument(_))
+        ));
+
+        let req = UpdateProfileReq {
+            avatar: None,
+            display_name: Some("".to_string()),
+            title: None,
+            status_text: None,
+            timezone: None,
+        };
+        assert!(req.validate().is_err());
+    }
+}
@@ -0,0 +1,500 @@
+use std::collections::HashMap;
+
+use validator::Validate;
+
+use crate::{
+    auth::api_token::hash_token,
+    dto::{
+        message::{Message as MessageDto, MessageContentType, SendMessageReq, SenderOverride},
+        webhook::{
+            CreateIncomingWebhookReq, CreateIncomingWebhookResp, CreateWebhookReq,
+            CreateWebhookResp, IncomingWebhook, IncomingWebhookReq, ListDeliveriesReq,
+            ListDeliveriesResp, ListIncomingWebhooksResp, ListWebhooksResp, WEBHOOK_EVENT_TYPES,
+            Webhook, WebhookDelivery, WebhookEvent, WebhookPayload,
+        },
+    },
+    errors::AppError,
+    models::{
+        channel::{ChanRepository, ROLE_ADMIN},
+        message::MessageStore,
+        user::{CreateUser, UpdateUser, UserRepository},
+        webhook::{
+            CreateIncomingWebhook, CreateWebhook, IncomingWebhook as IncomingDao, OutgoingWebhook,
+            WebhookStore,
+        },
+    },
+    service::{markdown::is_safe_url, message::MsgService, user::hash_password},
+};
+
+const MAX_PAGE_SIZE: i64 = 100;
+const MAX_WEBHOOKS_PER_CHANNEL: usize = 10;
+const SECRET_LEN: usize = 32;
+const TOKEN_LEN: usize = 40;
+
+pub struct WebhookService<'a> {
+    chan_store: &'a ChanRepository<'a>,
+    webhook_store: &'a WebhookStore<'a>,
+}
+
+impl<'a> WebhookService<'a> {
+    pub fn new(chan_store: &'a ChanRepository, webhook_store: &'a WebhookStore<'a>) -> Self {
+        Self {
+            chan_store,
+            webhook_store,
+        }
+    }
+
+    pub async fn create_webhook(
+        &self,
+        actor_id: i64,
+        actor_is_admin: bool,
+        chan_id: i64,
+        req: &CreateWebhookReq,
+    ) -> Result<CreateWebhookResp, AppError> {
+        req.validate()?;
+        check_can_manage(self.chan_store, actor_id, actor_is_admin, chan_id).await?;
+
+        let lower = req.url.to_ascii_lowercase();
+        if !lower.starts_with("http://") && !lower.starts_with("https://") {
+            return Err(AppError::InvalidArgument(
+                "webhook url must be http or https".to_string(),
+            ));
+        }
+
+        let mut event_types: Vec<String> = vec![];
+        for event_type in &req.event_types {
+            if !WEBHOOK_EVENT_TYPES.contains(&event_type.as_str()) {
+                return Err(AppError::InvalidArgument(format!(
+                    "unknown event type: {}",
+                    event_type
+                )));
+            }
+            if !event_types.contains(event_type) {
+                event_types.push(event_type.clone());
+            }
+        }
+
+        if self.webhook_store.list_by_channel(chan_id).await?.len() >= MAX_WEBHOOKS_PER_CHANNEL {
+            return Err(AppError::InvalidArgument(format!(
+                "a channel can have at most {} webhooks",
+                MAX_WEBHOOKS_PER_CHANNEL
+            )));
+        }
+
+        let secret = nanoid::nanoid!(SECRET_LEN);
+        let hook = self
+            .webhook_store
+            .create(&CreateWebhook {
+                channel_id: chan_id,
+                creator_id: actor_id,
+                url: req.url.clone(),
+                secret: secret.clone(),
+                event_types,
+            })
+            .await?;
+
+        Ok(CreateWebhookResp {
+            webhook: hook.into(),
+            secret,
+        })
+    }
+
+    pub async fn list_webhooks(
+        &self,
+        actor_id: i64,
+        actor_is_admin: bool,
+        chan_id: i64,
+    ) -> Result<ListWebhooksResp, AppError> {
+        check_can_manage(self.chan_store, actor_id, actor_is_admin, chan_id).await?;
+
+        let webhooks = self.webhook_store.list_by_channel(chan_id).await?;
+        Ok(ListWebhooksResp {
+            webhooks: webhooks.into_iter().map(Webhook::from).collect(),
+        })
+    }
+
+    pub async fn delete_webhook(
+        &self,
+        actor_id: i64,
+        actor_is_admin: bool,
+        chan_id: i64,
+        hook_id: i64,
+    ) -> Result<(), AppError> {
+        check_can_manage(self.chan_store, actor_id, actor_is_admin, chan_id).await?;
+        let hook = self.get_webhook(chan_id, hook_id).await?;
+
+        self.webhook_store.delete(hook.id).await?;
+        Ok(())
+    }
+
+    // Turns a webhook back on after it was disabled for failing too often.
+    pub async fn enable_webhook(
+        &self,
+        actor_id: i64,
+        actor_is_admin: bool,
+        chan_id: i64,
+        hook_id: i64,
+    ) -> Result<Webhook, AppError> {
+        check_can_manage(self.chan_store, actor_id, actor_is_admin, chan_id).await?;
+        let hook = self.get_webhook(chan_id, hook_id).await?;
+
+        match self.webhook_store.set_active(hook.id, true).await? {
+            Some(hook) => Ok(hook.into()),
+            None => Err(AppError::NotFound("webhook".to_string())),
+        }
+    }
+
+    pub async fn list_deliveries(
+        &self,
+        actor_id: i64,
+        actor_is_admin: bool,
+        chan_id: i64,
+        hook_id: i64,
+        req: &ListDeliveriesReq,
+    ) -> Result<ListDeliveriesResp, AppError> {
+        if req.offset < 0 || req.limit <= 0 || req.limit > MAX_PAGE_SIZE {
+            return Err(AppError::InvalidArgument(format!(
+                "offset must be >= 0 and limit in 1..={}",
+                MAX_PAGE_SIZE
+            )));
+        }
+        check_can_manage(self.chan_store, actor_id, actor_is_admin, chan_id).await?;
+        let hook = self.get_webhook(chan_id, hook_id).await?;
+
+        // fetch one extra row to learn whether another page exists.
+        let mut deliveries = self
+            .webhook_store
+            .list_deliveries(hook.id, req.limit + 1, req.offset)
+            .await?;
+        let has_more = deliveries.len() as i64 > req.limit;
+        deliveries.truncate(req.limit as usize);
+
+        let ids = deliveries.iter().map(|d| d.id).collect();
+        let mut logs: HashMap<i64, Vec<_>> = HashMap::new();
+        for attempt in self.webhook_store.list_attempts(ids).await? {
+            logs.entry(attempt.delivery_id).or_default().push(attempt);
+        }
+
+        let deliveries = deliveries
+            .into_iter()
+            .map(|delivery| WebhookDelivery {
+                log: logs.remove(&delivery.id).unwrap_or_default(),
+                delivery,
+            })
+            .collect();
+
+        Ok(ListDeliveriesResp {
+            deliveries,
+            has_more,
+        })
+    }
+
+    // Queues the event for the channel's subscribed webhooks; the delivery worker sends it.
+    pub async fn enqueue(&self, chan_id: i64, event: &WebhookEvent) -> Result<u64, AppError> {
+        let payload = WebhookPayload {
+            event,
+            channel_id: chan_id,
+            occurred_at: chrono::Utc::now(),
+        };
+
+        self.webhook_store
+            .enqueue(chan_id, event.kind(), &serde_json::json!(payload))
+            .await
+    }
+
+    async fn get_webhook(&self, chan_id: i64, hook_id: i64) -> Result<OutgoingWebhook, AppError> {
+        match self.webhook_store.get_by_id(hook_id).await? {
+            Some(hook) if hook.channel_id == chan_id => Ok(hook),
+            _ => Err(AppError::NotFound("webhook".to_string())),
+        }
+    }
+}
+
+/// Incoming webhooks let integrations post into one channel by token. Each webhook posts as
+/// its own bot user, which nobody can log in as.
+pub struct IncomingWebhookService<'a> {
+    chan_store: &'a ChanRepository<'a>,
+    user_store: &'a UserRepository<'a>,
+    msg_store: &'a MessageStore<'a>,
+    webhook_store: &'a WebhookStore<'a>,
+}
+
+impl<'a> IncomingWebhookService<'a> {
+    pub fn new(
+        chan_store: &'a ChanRepository,
+        user_store: &'a UserRepository,
+        msg_store: &'a MessageStore,
+        webhook_store: &'a WebhookStore<'a>,
+    ) -> Self {
+        Self {
+            chan_store,
+            user_store,
+            msg_store,
+            webhook_store,
+        }
+    }
+
+    pub async fn create_webhook(
+        &self,
+        actor_id: i64,
+        actor_is_admin: bool,
+        chan_id: i64,
+        req: &CreateIncomingWebhookReq,
+    ) -> Result<CreateIncomingWebhookResp, AppError> {
+        req.validate()?;
+        check_can_manage(self.chan_store, actor_id, actor_is_admin, chan_id).await?;
+        if self
+            .webhook_store
+            .list_incoming_by_channel(chan_id)
+            .await?
+            .len()
+            >= MAX_WEBHOOKS_PER_CHANNEL
+        {
+            return Err(AppError::InvalidArgument(format!(
+                "a channel can have at most {} incoming webhooks",
+                MAX_WEBHOOKS_PER_CHANNEL
+            )));
+        }
+
+        let bot = self
+            .user_store
+            .create(&new_bot_user(actor_id, req)?)
+            .await?;
+
+        let (token, token_hash) = new_incoming_token();
+        let hook = self
+            .webhook_store
+            .create_incoming(&CreateIncomingWebhook {
+                channel_id: chan_id,
+                creator_id: actor_id,
+                bot_user_id: bot.id,
+                name: req.name.clone(),
+                token_hash,
+            })
+            .await?;
+
+        Ok(CreateIncomingWebhookResp {
+            webhook: hook.into(),
+            path: format!("/hooks/{}", token),
+            token,
+        })
+    }
+
+    pub async fn list_webhooks(
+        &self,
+        actor_id: i64,
+        actor_is_admin: bool,
+        chan_id: i64,
+    ) -> Result<ListIncomingWebhooksResp, AppError> {
+        check_can_manage(self.chan_store, actor_id, actor_is_admin, chan_id).await?;
+
+        let webhooks = self.webhook_store.list_incoming_by_channel(chan_id).await?;
+        Ok(ListIncomingWebhooksResp {
+            webhooks: webhooks.into_iter().map(IncomingWebhook::from).collect(),
+        })
+    }
+
+    // The bot user is kept, deactivated, so the messages it posted still have a sender.
+    pub async fn revoke_webhook(
+        &self,
+        actor_id: i64,
+        actor_is_admin: bool,
+        chan_id: i64,
+        hook_id: i64,
+    ) -> Result<(), AppError> {
+        check_can_manage(self.chan_store, actor_id, actor_is_admin, chan_id).await?;
+        let hook = match self.webhook_store.get_incoming_by_id(hook_id).await? {
+            Some(hook) if hook.channel_id == chan_id && hook.revoked_at.is_none() => hook,
+            _ => return Err(AppError::NotFound("incoming webhook".to_string())),
+        };
+
+        self.webhook_store.revoke_incoming(hook.id).await?;
+        self.user_store
+            .update(&retired_bot_user(hook.bot_user_id))
+            .await?;
+        Ok(())
+    }
+
+    /// Posts an integration's payload to the channel the token belongs to.
+    pub async fn post_message(
+        &self,
+        token: &str,
+        req: &IncomingWebhookReq,
+    ) -> Result<MessageDto, AppError> {
+        let hook = self.authenticate(token).await?;
+        req.validate()?;
+
+        if req.text.trim().is_empty() && req.attachments.is_empty() {
+            return Err(AppError::InvalidArgument(
+                "text or attachments are required".to_string(),
+            ));
+        }
+        let urls = req.avatar_url.iter().chain(
+            req.attachments
+                .iter()
+                .flat_map(|a| a.title_link.iter().chain(a.image_url.iter())),
+        );
+        for url in urls {
+            if !is_safe_url(url) {
+                return Err(AppError::InvalidArgument(format!(
+                    "url not allowed: {}",
+                    url
+                )));
+            }
+        }
+
+        let sender_override =
+            (req.username.is_some() || req.avatar_url.is_some()).then(|| SenderOverride {
+                username: req.username.clone(),
+                avatar_url: req.avatar_url.clone(),
+            });
+        let send_req = SendMessageReq {
+            sender_id: Some(hook.bot_user_id),
+            parent_msg_id: None,
+            content_type: MessageContentType::Text,
+            text_content: req.text.clone(),
+            media_url: None,
+            media_metadata: None,
+            sender_override,
+            attachments: req.attachments.clone(),
+        };
+
+        let msg_service = MsgService::new(self.chan_store, self.user_store, self.msg_store);
+        let msg = msg_service.send_message(hook.channel_id, &send_req).await?;
+        self.webhook_store.touch_incoming(hook.id).await?;
+        Ok(msg)
+    }
+
+    async fn authenticate(&self, token: &str) -> Result<IncomingDao, AppError> {
+        let hook = self
+            .webhook_store
+            .get_incoming_by_token_hash(&hash_token(token))
+            .await?;
+        active_hook(hook)
+    }
+}
+
+// Only the hash is stored, the token itself is shown once to the creator.
+fn new_incoming_token() -> (String, String) {
+    let token = nanoid::nanoid!(TOKEN_LEN);
+    let token_hash = hash_token(&token);
+    (token, token_hash)
+}
+
+// bots cannot log in, the password only fills the column.
+fn new_bot_user(actor_id: i64, req: &CreateIncomingWebhookReq) -> Result<CreateUser, AppError> {
+    Ok(CreateUser {
+        username: format!("hook_{}", nanoid::nanoid!(12)),
+        avatar_url: req.avatar_url.clone().unwrap_or_default(),
+        password_hash: hash_password(&nanoid::nanoid!(SECRET_LEN))?,
+        display_name: req.name.clone(),
+        is_active: true,
+        is_bot: true,
+        owner_id: Some(actor_id),
+    })
+}
+
+fn retired_bot_user(bot_user_id: i64) -> UpdateUser {
+    UpdateUser {
+        id: bot_user_id,
+        is_active: Some(false),
+        ..Default::default()
+    }
+}
+
+// The store already skips revoked webhooks; checking again keeps a revoked token from
+// posting even if a lookup ever returns one.
+fn active_hook(hook: Option<IncomingDao>) -> Result<IncomingDao, AppError> {
+    match hook {
+        Some(hook) if hook.revoked_at.is_none() => Ok(hook),
+        _ => Err(AppError::NotFound("incoming webhook".to_string())),
+    }
+}
+
+// Webhooks see or speak for a channel, so only its owner and admins manage them.
+async fn check_can_manage(
+    chan_store: &ChanRepository<'_>,
+    actor_id: i64,
+    actor_is_admin: bool,
+    chan_id: i64,
+) -> Result<(), AppError> {
+    let chan = match chan_store.get_by_id(chan_id).await? {
+        Some(chan) => chan,
+        None => return Err(AppError::NotFound("channel".to_string())),
+    };
+    if actor_is_admin || chan.creator_id == actor_id {
+        return Ok(());
+    }
+
+    let member = chan_store.get_channel_member(chan_id, actor_id).await?;
+    if member.is_some_and(|m| m.member_role == ROLE_ADMIN) {
+        return Ok(());
+    }
+    Err(AppError::PermissionDenied(
+        "only channel owners and admins can manage webhooks".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn incoming(revoked: bool) -> IncomingDao {
+        IncomingDao {
+            id: 1,
+            channel_id: 2,
+            creator_id: 3,
+            bot_user_id: 4,
+            name: "ci".to_string(),
+            token_hash: hash_token("token"),
+            revoked_at: revoked.then(chrono::Utc::now),
+            last_used_at: None,
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_new_incoming_token() {
+        let (token, token_hash) = new_incoming_token();
+        assert_eq!(token.len(), TOKEN_LEN);
+        assert_eq!(token_hash, hash_token(&token));
+        assert_eq!(token_hash.len(), 64);
+        assert!(!token_hash.contains(&token));
+
+        let (other, other_hash) = new_incoming_token();
+        assert_ne!(token, other);
+        assert_ne!(token_hash, other_hash);
+    }
+
+    #[test]
+    fn test_active_hook() {
+        assert_eq!(active_hook(Some(incoming(false))).unwrap().id, 1);
+        assert!(matches!(
+            active_hook(Some(incoming(true))),
+            Err(AppError::NotFound(_))
+        ));
+        assert!(matches!(active_hook(None), Err(AppError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_bot_user_lifecycle() {
+        let req = CreateIncomingWebhookReq {
+            name: "CI".to_string(),
+            avatar_url: None,
+        };
+        let bot = new_bot_user(7, &req).unwrap();
+        assert!(bot.is_bot);
+        assert!(bot.is_active);
+        assert_eq!(bot.owner_id, Some(7));
+        assert_eq!(bot.display_name, "CI");
+        assert!(bot.username.starts_with("hook_"));
+        assert_ne!(bot.username, new_bot_user(7, &req).unwrap().username);
+
+        // revoking keeps the bot, so its messages still have a sender, but deactivates it.
+        let retired = retired_bot_user(4);
+        assert_eq!(retired.id, 4);
+        assert_eq!(retired.is_active, Some(false));
+        assert!(retired.display_name.is_none());
+        assert!(retired.password_hash.is_none());
+    }
+}
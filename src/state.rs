@@ -1,31 +1,189 @@
 use sqlx::PgPool;
-use std::{collections::HashMap, ops::Deref, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Deref,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
 use tokio::sync::{RwLock, broadcast};
+use tracing::{info, warn};
 
 use crate::{
     auth::{DecodingKey, EncodingKey},
+    config::{Config, ConfigError},
+    dto::{
+        event::ServerEvent,
+        message::{Mention, Message},
+    },
     errors::AppError,
+    metrics::Metrics,
+    models::channel::ChanRepository,
+    ratelimit::{LoginGuard, RateLimiter},
+    unfurl::{FetchLimits, HttpFetcher, LinkFetcher},
+    webhook::WebhookSender,
 };
 
+/// What a socket's send loop forwards to its client.
+#[derive(Debug, Clone)]
+pub enum Outbound {
+    // a serialized `ServerEvent`, and what a socket on the unversioned path gets instead:
+    // the bare `WebSocketMessage` of a `message` event, nothing for any other event.
+    Event {
+        tagged: String,
+        legacy: Option<String>,
+    },
+    // the server is shutting down; the socket sends a close frame and ends.
+    GoingAway,
+}
+
 #[derive(Clone)]
 pub struct AppState {
     pub inner: Arc<AppStateInner>,
 }
 
 impl AppState {
-    pub fn new(pool: PgPool) -> Result<Self, AppError> {
-        let ek = EncodingKey::load(include_str!("../private_key.pem"))?;
-        let dk = DecodingKey::load(include_str!("../public_key.pem"))?;
+    pub fn new(pool: PgPool, config: Config) -> Result<Self, ConfigError> {
+        let (ek, dk) = config.auth.load_keys()?;
         let tx_set = Arc::new(RwLock::new(HashMap::new()));
+        let fetcher = Arc::new(HttpFetcher::new(FetchLimits::default()));
         let inner = Arc::new(AppStateInner {
             pool,
             ek,
             dk,
             tx_set,
+            fetcher,
+            webhook_sender: WebhookSender::default(),
+            rate_limiter: Arc::new(RateLimiter::new(config.rate_limits.api)),
+            login_guard: Arc::new(LoginGuard::new(config.rate_limits.login)),
+            metrics: Arc::new(Metrics::new()),
+            shutting_down: Arc::new(AtomicBool::new(false)),
+            config: Arc::new(config),
         });
 
         Ok(Self { inner })
     }
+
+    // Dropping a user's broadcast sender ends their socket's send loop, which closes the socket.
+    pub async fn disconnect_user(&self, user_id: i64) {
+        self.tx_set.write().await.remove(&user_id);
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::Relaxed)
+    }
+
+    // Fails readiness, refuses new sockets and tells every open socket to close.
+    pub async fn begin_shutdown(&self) {
+        self.shutting_down.store(true, Ordering::Relaxed);
+        let tx_set = self.tx_set.read().await;
+        info!("closing {} sockets", tx_set.len());
+        for tx in tx_set.values() {
+            let _ = tx.send(Outbound::GoingAway);
+        }
+    }
+
+    // Sockets live outside the HTTP server, so its graceful shutdown doesn't wait for them.
+    pub async fn wait_for_sockets(&self, timeout: Duration) {
+        let deadline = tokio::time::Instant::now() + timeout;
+        while self.metrics.ws_connections.get() > 0 {
+            if tokio::time::Instant::now() >= deadline {
+                warn!(
+                    "{} sockets still open at shutdown",
+                    self.metrics.ws_connections.get()
+                );
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    // Pushes an event to whichever of the users currently have a socket open.
+    pub async fn send_to_users(&self, user_ids: &[i64], event: &ServerEvent) {
+        let data = match serde_json::to_string(event) {
+            Ok(data) => data,
+            Err(e) => {
+                warn!("serialize server event error: {}", e);
+                return;
+            }
+        };
+        let legacy = match event {
+            ServerEvent::Message(msg) => serde_json::to_string(msg).ok(),
+            _ => None,
+        };
+
+        let tx_set = self.tx_set.read().await;
+        for user_id in user_ids {
+            if let Some(tx) = tx_set.get(user_id) {
+                let _ = tx.send(Outbound::Event {
+                    tagged: data.clone(),
+                    legacy: legacy.clone(),
+                });
+            }
+        }
+    }
+
+    pub async fn send_to_channel(
+        &self,
+        channel_id: i64,
+        event: &ServerEvent,
+    ) -> Result<(), AppError> {
+        let chan_repo = ChanRepository::new(&self.pool);
+        let member_ids: Vec<i64> = chan_repo
+            .list_channel_members(channel_id)
+            .await?
+            .iter()
+            .map(|m| m.user_id)
+            .collect();
+
+        self.send_to_users(&member_ids, event).await;
+        Ok(())
+    }
+
+    pub async fn send_system_message(&self, message: &Message) -> Result<(), AppError> {
+        let event = ServerEvent::SystemMessage {
+            message: message.clone(),
+        };
+        self.send_to_channel(message.channel_id, &event).await
+    }
+
+    // Only channel members are notified, never the sender. Events reach connected users
+    // only, which is exactly what `@here` asks for, so it is handled like `@channel`.
+    pub async fn notify_mentions(&self, message: &Message) -> Result<(), AppError> {
+        if message.mentions.is_empty() {
+            return Ok(());
+        }
+
+        let everyone = message
+            .mentions
+            .iter()
+            .any(|m| matches!(m, Mention::Channel | Mention::Here));
+        let named: HashSet<i64> = message
+            .mentions
+            .iter()
+            .filter_map(|m| match m {
+                Mention::User { user_id, .. } => Some(*user_id),
+                _ => None,
+            })
+            .collect();
+
+        let chan_repo = ChanRepository::new(&self.pool);
+        let recipients: Vec<i64> = chan_repo
+            .list_channel_members(message.channel_id)
+            .await?
+            .iter()
+            .map(|m| m.user_id)
+            .filter(|id| Some(*id) != message.sender_id && (everyone || named.contains(id)))
+            .collect();
+
+        let event = ServerEvent::Mentioned {
+            message: message.clone(),
+        };
+        self.send_to_users(&recipients, &event).await;
+        Ok(())
+    }
 }
 
 impl Deref for AppState {
@@ -40,5 +198,13 @@ pub struct AppStateInner {
     pub pool: PgPool,
     pub ek: EncodingKey,
     pub dk: DecodingKey,
-    pub tx_set: Arc<RwLock<HashMap<i64, broadcast::Sender<String>>>>,
+    pub tx_set: Arc<RwLock<HashMap<i64, broadcast::Sender<Outbound>>>>,
+    pub fetcher: Arc<dyn LinkFetcher>,
+    // Posts outgoing webhooks and external slash commands.
+    pub webhook_sender: WebhookSender,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub login_guard: Arc<LoginGuard>,
+    pub metrics: Arc<Metrics>,
+    pub shutting_down: Arc<AtomicBool>,
+    pub config: Arc<Config>,
 }
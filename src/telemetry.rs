@@ -0,0 +1,68 @@
+use axum::{body::Body, extract::MatchedPath, http::Request};
+use tracing::Span;
+use tracing_subscriber::{EnvFilter, fmt};
+
+use crate::request_id::REQUEST_ID_HEADER;
+
+const DEFAULT_FILTER: &str = "slac=info,tower_http=info";
+
+/// What debug logs print instead of a password, token or signing secret.
+pub const REDACTED: &str = "[redacted]";
+
+/// Installs the global subscriber. `RUST_LOG` picks what is logged, e.g.
+/// `RUST_LOG=slac=debug,sqlx=warn`, and `json` switches to one JSON object per line for
+/// log collectors.
+pub fn init(json: bool) {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let builder = fmt().with_env_filter(filter);
+    if json {
+        builder.json().with_current_span(true).init();
+    } else {
+        builder.init();
+    }
+}
+
+/// The span of one HTTP request. It names the matched route rather than the uri, so
+/// tokens in paths like `/hooks/{token}` and query strings stay out of the logs.
+pub fn http_span(req: &Request<Body>) -> Span {
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unmatched", |path| path.as_str());
+    let request_id = req
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("-");
+
+    tracing::info_span!(
+        "http",
+        method = %req.method(),
+        route,
+        request_id,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dto::user::{ChangePasswordReq, LoginReq};
+
+    #[test]
+    fn test_secrets_are_redacted() {
+        let login = LoginReq {
+            username: "alice".to_string(),
+            password: "hunter2-secret".to_string(),
+        };
+        let change = ChangePasswordReq {
+            current_password: "hunter2-secret".to_string(),
+            new_password: "hunter3-secret".to_string(),
+        };
+
+        for logged in [format!("{:?}", login), format!("{:?}", change)] {
+            assert!(!logged.contains("secret"), "{}", logged);
+            assert!(logged.contains("[redacted]"));
+        }
+        assert!(format!("{:?}", login).contains("alice"));
+    }
+}
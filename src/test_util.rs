@@ -0,0 +1,12 @@
+use std::net::SocketAddr;
+
+use axum::Router;
+
+/// Serves `app` on a free local port for the rest of the test and returns its address.
+/// Clients pointed at it must be built with `allow_private_ips`.
+pub async fn serve_local(app: Router) -> SocketAddr {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    addr
+}
@@ -0,0 +1,339 @@
+use std::{
+    error::Error as _,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use futures_util::future::BoxFuture;
+use reqwest::{
+    Client, Url,
+    dns::{Addrs, Name, Resolve, Resolving},
+    header, redirect,
+};
+
+use crate::unfurl::{FetchedPage, LinkFetcher, UnfurlError};
+
+const MAX_REDIRECTS: usize = 3;
+const USER_AGENT: &str = "slac-unfurl/0.1";
+
+#[derive(Debug, Clone)]
+pub struct FetchLimits {
+    pub timeout: Duration,
+    pub max_bytes: usize,
+    // lifts the address checks, see `guarded_client`.
+    pub allow_private_ips: bool,
+}
+
+impl Default for FetchLimits {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(5),
+            max_bytes: 512 * 1024,
+            allow_private_ips: false,
+        }
+    }
+}
+
+/// Fetches over HTTP(S) while refusing to reach private networks: every host, including
+/// each redirect target, is looked up through [`guarded_client`]'s resolver, so the
+/// addresses that were checked are the ones connected to.
+pub struct HttpFetcher {
+    limits: FetchLimits,
+    client: Client,
+}
+
+impl HttpFetcher {
+    pub fn new(limits: FetchLimits) -> Self {
+        let client = guarded_client(USER_AGENT, limits.allow_private_ips);
+        Self { limits, client }
+    }
+
+    async fn fetch_page(&self, url: &str) -> Result<FetchedPage, UnfurlError> {
+        let mut url = Url::parse(url).map_err(|e| UnfurlError::Blocked(e.to_string()))?;
+
+        for _ in 0..=MAX_REDIRECTS {
+            let resp = self.get(&url).await?;
+
+            if resp.status().is_redirection() {
+                let location = resp
+                    .headers()
+                    .get(header::LOCATION)
+                    .and_then(|v| v.to_str().ok())
+                    .ok_or_else(|| UnfurlError::Fetch("redirect without location".to_string()))?;
+                url = url
+                    .join(location)
+                    .map_err(|e| UnfurlError::Blocked(e.to_string()))?;
+                continue;
+            }
+
+            if !resp.status().is_success() {
+                return Err(UnfurlError::Fetch(format!("status {}", resp.status())));
+            }
+
+            let content_type = resp
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("")
+                .to_ascii_lowercase();
+            let body = self.read_capped(resp).await?;
+
+            return Ok(FetchedPage {
+                url: url.to_string(),
+                content_type,
+                body,
+            });
+        }
+
+        Err(UnfurlError::Fetch("too many redirects".to_string()))
+    }
+
+    async fn get(&self, url: &Url) -> Result<reqwest::Response, UnfurlError> {
+        check_url(url, self.limits.allow_private_ips)?;
+        self.client
+            .get(url.clone())
+            .header(header::ACCEPT, "text/html, application/json")
+            .timeout(self.limits.timeout)
+            .send()
+            .await
+            .map_err(send_error)
+    }
+
+    // Reads at most `max_bytes`; the rest of the page is dropped, which is fine since the
+    // tags an unfurl needs live in `<head>`.
+    async fn read_capped(&self, mut resp: reqwest::Response) -> Result<Vec<u8>, UnfurlError> {
+        let mut body = Vec::new();
+        while let Some(chunk) = resp
+            .chunk()
+            .await
+            .map_err(|e| UnfurlError::Fetch(e.to_string()))?
+        {
+            body.extend_from_slice(&chunk);
+            if body.len() >= self.limits.max_bytes {
+                body.truncate(self.limits.max_bytes);
+                break;
+            }
+        }
+        Ok(body)
+    }
+}
+
+impl LinkFetcher for HttpFetcher {
+    fn fetch<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<FetchedPage, UnfurlError>> {
+        Box::pin(self.fetch_page(url))
+    }
+}
+
+/// A client for requests to user-supplied urls. It ignores proxy settings, follows no
+/// redirects, and answers its DNS lookups with [`resolve_checked`], so a connection only
+/// ever goes to an address that passed the check. IP literals skip the lookup: check those
+/// with [`check_url`] before sending. `allow_private_ips` turns both checks off, which only
+/// tests against a local server want.
+pub fn guarded_client(user_agent: &str, allow_private_ips: bool) -> Client {
+    Client::builder()
+        .redirect(redirect::Policy::none())
+        .no_proxy()
+        .user_agent(user_agent)
+        .dns_resolver(Arc::new(CheckedResolver { allow_private_ips }))
+        .build()
+        .expect("http client")
+}
+
+struct CheckedResolver {
+    allow_private_ips: bool,
+}
+
+impl Resolve for CheckedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let allow_private_ips = self.allow_private_ips;
+        Box::pin(async move {
+            // the connector fills in the url's port.
+            let addrs = resolve_checked(name.as_str(), 0, allow_private_ips).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Refuses urls that aren't http(s), and hosts given as private IP literals.
+pub fn check_url(url: &Url, allow_private_ips: bool) -> Result<(), UnfurlError> {
+    if !matches!(url.scheme(), "http" | "https") || url.port_or_known_default().is_none() {
+        return Err(UnfurlError::Blocked(url.to_string()));
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| UnfurlError::Blocked(url.to_string()))?;
+    let literal = host.trim_start_matches('[').trim_end_matches(']');
+    match literal.parse::<IpAddr>() {
+        Ok(ip) if !allow_private_ips && !is_public_ip(ip) => Err(UnfurlError::Blocked(format!(
+            "{} is a private address",
+            host
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Maps a failed send, keeping a refusal from [`guarded_client`]'s resolver a refusal.
+pub fn send_error(e: reqwest::Error) -> UnfurlError {
+    let mut source = e.source();
+    while let Some(err) = source {
+        if let Some(UnfurlError::Blocked(msg)) = err.downcast_ref::<UnfurlError>() {
+            return UnfurlError::Blocked(msg.clone());
+        }
+        source = err.source();
+    }
+    UnfurlError::Fetch(e.to_string())
+}
+
+/// Resolves `host` and refuses it if any of its addresses is private. Callers should pin
+/// their request to the returned addresses.
+pub async fn resolve_checked(
+    host: &str,
+    port: u16,
+    allow_private_ips: bool,
+) -> Result<Vec<SocketAddr>, UnfurlError> {
+    // `host_str` keeps the brackets of IPv6 literals.
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| UnfurlError::Fetch(e.to_string()))?
+        .collect();
+
+    if addrs.is_empty() {
+        return Err(UnfurlError::Fetch(format!("{} did not resolve", host)));
+    }
+    // one private address is enough to refuse, or a host could mix them.
+    if !allow_private_ips && addrs.iter().any(|a| !is_public_ip(a.ip())) {
+        return Err(UnfurlError::Blocked(format!(
+            "{} resolves to a private address",
+            host
+        )));
+    }
+    Ok(addrs)
+}
+
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                || v4.is_unspecified()
+                || v4.is_multicast()
+                // 0.0.0.0/8, 100.64.0.0/10 (carrier-grade NAT) and 240.0.0.0/4
+                || a == 0
+                || (a == 100 && (b & 0xc0) == 64)
+                || a >= 240)
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(v4));
+            }
+            let seg = v6.segments();
+            // 64:ff9b::/96 (NAT64) and 2002::/16 (6to4) reach the IPv4 address they embed.
+            if seg[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                return is_public_ip(IpAddr::V4(embedded_v4(seg[6], seg[7])));
+            }
+            if seg[0] == 0x2002 {
+                return is_public_ip(IpAddr::V4(embedded_v4(seg[1], seg[2])));
+            }
+            let first = seg[0];
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                // fc00::/7 unique local and fe80::/10 link-local
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+fn embedded_v4(high: u16, low: u16) -> Ipv4Addr {
+    Ipv4Addr::from((u32::from(high) << 16) | u32::from(low))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::serve_local;
+    use crate::unfurl::unfurl;
+    use axum::{Router, response::Redirect, routing::get};
+
+    async fn stand_in() -> SocketAddr {
+        let app = Router::new()
+            .route(
+                "/page",
+                get(|| async {
+                    (
+                        [(header::CONTENT_TYPE, "text/html; charset=utf-8")],
+                        r#"<head><meta property="og:title" content="Stand-in"></head>"#,
+                    )
+                }),
+            )
+            .route("/moved", get(|| async { Redirect::temporary("/page") }));
+
+        serve_local(app).await
+    }
+
+    #[tokio::test]
+    async fn test_fetch_local_stand_in() {
+        let addr = stand_in().await;
+        let fetcher = HttpFetcher::new(FetchLimits {
+            allow_private_ips: true,
+            ..Default::default()
+        });
+
+        let card = unfurl(&fetcher, &format!("http://{}/moved", addr))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(card.title.as_deref(), Some("Stand-in"));
+        assert_eq!(card.url, format!("http://{}/moved", addr));
+
+        let capped = HttpFetcher::new(FetchLimits {
+            max_bytes: 10,
+            allow_private_ips: true,
+            ..Default::default()
+        });
+        let page = capped
+            .fetch(&format!("http://{}/page", addr))
+            .await
+            .unwrap();
+        assert_eq!(page.body.len(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_private_addresses_are_blocked() {
+        let addr = stand_in().await;
+        let fetcher = HttpFetcher::new(FetchLimits::default());
+
+        let err = fetcher
+            .fetch(&format!("http://{}/page", addr))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, UnfurlError::Blocked(_)));
+        // names are checked when the client looks them up.
+        let err = fetcher
+            .fetch(&format!("http://localhost:{}/page", addr.port()))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, UnfurlError::Blocked(_)));
+        assert!(matches!(
+            fetcher.fetch("file:///etc/passwd").await,
+            Err(UnfurlError::Blocked(_))
+        ));
+
+        assert!(!is_public_ip("10.1.2.3".parse().unwrap()));
+        assert!(!is_public_ip("169.254.169.254".parse().unwrap()));
+        assert!(!is_public_ip("100.100.0.1".parse().unwrap()));
+        assert!(!is_public_ip("::ffff:192.168.0.1".parse().unwrap()));
+        assert!(!is_public_ip("fd00::1".parse().unwrap()));
+        assert!(!is_public_ip("64:ff9b::a9fe:a9fe".parse().unwrap()));
+        assert!(!is_public_ip("2002:7f00:1::".parse().unwrap()));
+        assert!(is_public_ip("64:ff9b::5db8:d822".parse().unwrap()));
+        assert!(is_public_ip("93.184.216.34".parse().unwrap()));
+    }
+}
@@ -0,0 +1,353 @@
+use futures_util::future::BoxFuture;
+use lazy_static::lazy_static;
+use regex::Regex;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::warn;
+
+use crate::{
+    models::unfurl::UnfurlStore,
+    service::markdown::{self, Block, Inline},
+};
+
+pub mod http;
+
+pub use http::{FetchLimits, HttpFetcher};
+
+const MAX_UNFURLS_PER_MESSAGE: usize = 3;
+// How long a fetched card, or a failure to fetch one, is reused.
+const CARD_TTL_HOURS: i64 = 24;
+const FAILURE_TTL_HOURS: i64 = 1;
+
+#[derive(Error, Debug)]
+pub enum UnfurlError {
+    #[error("url not allowed: {0}")]
+    Blocked(String),
+
+    #[error("fetch failed: {0}")]
+    Fetch(String),
+
+    #[error("unsupported content type: {0}")]
+    Unsupported(String),
+}
+
+/// A page as returned by a [`LinkFetcher`], with the body cut at the fetcher's size cap.
+#[derive(Debug)]
+pub struct FetchedPage {
+    pub url: String,
+    pub content_type: String,
+    pub body: Vec<u8>,
+}
+
+/// Fetches pages to unfurl. `HttpFetcher` is the real one; tests can point it at a local
+/// server or swap in their own.
+pub trait LinkFetcher: Send + Sync {
+    fn fetch<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<FetchedPage, UnfurlError>>;
+}
+
+/// The preview card attached to a message for one of its links.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UnfurlCard {
+    pub url: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub image_url: Option<String>,
+    pub site_name: Option<String>,
+}
+
+/// The http(s) links of a message worth unfurling, in order of appearance.
+pub fn message_links(text: &str) -> Vec<String> {
+    let mut links = vec![];
+    collect_links(&markdown::parse(text), &mut links);
+    links.truncate(MAX_UNFURLS_PER_MESSAGE);
+    links
+}
+
+fn collect_links(blocks: &[Block], links: &mut Vec<String>) {
+    for block in blocks {
+        match block {
+            Block::Paragraph { children } => collect_inline_links(children, links),
+            Block::Quote { children } => collect_links(children, links),
+            Block::List { items, .. } => {
+                for item in items {
+                    collect_inline_links(item, links);
+                }
+            }
+            Block::CodeBlock { .. } => {}
+        }
+    }
+}
+
+fn collect_inline_links(inlines: &[Inline], links: &mut Vec<String>) {
+    for inline in inlines {
+        match inline {
+            Inline::Link { url, .. } => {
+                let lower = url.to_ascii_lowercase();
+                if (lower.starts_with("http://") || lower.starts_with("https://"))
+                    && !links.contains(url)
+                {
+                    links.push(url.clone());
+                }
+            }
+            Inline::Bold { children }
+            | Inline::Italic { children }
+            | Inline::Strike { children } => collect_inline_links(children, links),
+            _ => {}
+        }
+    }
+}
+
+pub struct Unfurler<'a> {
+    fetcher: &'a dyn LinkFetcher,
+    store: &'a UnfurlStore<'a>,
+}
+
+impl<'a> Unfurler<'a> {
+    pub fn new(fetcher: &'a dyn LinkFetcher, store: &'a UnfurlStore<'a>) -> Self {
+        Self { fetcher, store }
+    }
+
+    /// Cards for the urls that could be unfurled; failures are logged and skipped.
+    pub async fn unfurl_all(&self, urls: &[String]) -> Vec<UnfurlCard> {
+        let mut cards = vec![];
+        for url in urls {
+            match self.unfurl_cached(url).await {
+                Ok(Some(card)) => cards.push(card),
+                Ok(None) => {}
+                Err(e) => warn!("unfurl {} error: {}", url, e),
+            }
+        }
+        cards
+    }
+
+    async fn unfurl_cached(
+        &self,
+        url: &str,
+    ) -> Result<Option<UnfurlCard>, crate::errors::AppError> {
+        if let Some(cached) = self.store.get(url).await? {
+            let ttl = if cached.card.is_some() {
+                CARD_TTL_HOURS
+            } else {
+                FAILURE_TTL_HOURS
+            };
+            if cached.fetched_at > chrono::Utc::now() - chrono::Duration::hours(ttl) {
+                return Ok(cached
+                    .card
+                    .and_then(|card| serde_json::from_value(card).ok()));
+            }
+        }
+
+        let card = match unfurl(self.fetcher, url).await {
+            Ok(card) => card,
+            Err(e) => {
+                warn!("unfurl {} failed: {}", url, e);
+                None
+            }
+        };
+
+        let value = card.as_ref().map(|c| serde_json::json!(c));
+        self.store.put(url, value).await?;
+        Ok(card)
+    }
+}
+
+/// Fetches a page and builds its card from OpenGraph tags, falling back to the `<title>` and
+/// description meta tags, and to the page's oEmbed endpoint for what is still missing.
+pub async fn unfurl(
+    fetcher: &dyn LinkFetcher,
+    url: &str,
+) -> Result<Option<UnfurlCard>, UnfurlError> {
+    let page = fetcher.fetch(url).await?;
+    if !page.content_type.starts_with("text/html") {
+        return Err(UnfurlError::Unsupported(page.content_type));
+    }
+
+    let html = String::from_utf8_lossy(&page.body);
+    let (mut card, oembed_url) = parse_html(url, &page.url, &html);
+
+    if let Some(oembed_url) =
+        oembed_url.filter(|_| card.title.is_none() || card.image_url.is_none())
+    {
+        match fetcher.fetch(&oembed_url).await {
+            Ok(oembed) => merge_oembed(&mut card, &oembed.body),
+            Err(e) => warn!("fetch oembed {} error: {}", oembed_url, e),
+        }
+    }
+
+    if card.title.is_none() && card.description.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(card))
+}
+
+lazy_static! {
+    static ref META_TAG: Regex = Regex::new(r"(?is)<meta\s[^>]*>").unwrap();
+    static ref LINK_TAG: Regex = Regex::new(r"(?is)<link\s[^>]*>").unwrap();
+    static ref ATTR: Regex =
+        Regex::new(r#"(?is)([a-z:_-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap();
+    static ref TITLE_TAG: Regex = Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap();
+}
+
+const MAX_TITLE_LEN: usize = 200;
+const MAX_DESCRIPTION_LEN: usize = 500;
+
+fn attrs(tag: &str) -> Vec<(String, String)> {
+    ATTR.captures_iter(tag)
+        .map(|c| {
+            let value = c.get(2).or(c.get(3)).map_or("", |m| m.as_str());
+            (c[1].to_ascii_lowercase(), decode_entities(value))
+        })
+        .collect()
+}
+
+// `requested_url` is what the message links to; `page_url` is where redirects ended, which
+// relative image and oEmbed urls resolve against.
+fn parse_html(requested_url: &str, page_url: &str, html: &str) -> (UnfurlCard, Option<String>) {
+    let base = Url::parse(page_url).ok();
+    let mut og = std::collections::HashMap::new();
+
+    for tag in META_TAG.find_iter(html) {
+        let attrs = attrs(tag.as_str());
+        let key = attrs
+            .iter()
+            .find(|(k, _)| k == "property" || k == "name")
+            .map(|(_, v)| v.to_ascii_lowercase());
+        let content = attrs.iter().find(|(k, _)| k == "content").map(|(_, v)| v);
+        if let (Some(key), Some(content)) = (key, content) {
+            og.entry(key).or_insert_with(|| content.trim().to_string());
+        }
+    }
+
+    let mut oembed_url = None;
+    for tag in LINK_TAG.find_iter(html) {
+        let attrs = attrs(tag.as_str());
+        let is_oembed = attrs
+            .iter()
+            .any(|(k, v)| k == "type" && v.eq_ignore_ascii_case("application/json+oembed"));
+        if let (true, Some((_, href))) = (is_oembed, attrs.iter().find(|(k, _)| k == "href")) {
+            oembed_url = resolve_url(base.as_ref(), href);
+            break;
+        }
+    }
+
+    let title = og.get("og:title").cloned().or_else(|| {
+        TITLE_TAG
+            .captures(html)
+            .map(|c| decode_entities(c[1].trim()))
+    });
+    let description = og
+        .get("og:description")
+        .or_else(|| og.get("description"))
+        .cloned();
+    let image_url = og
+        .get("og:image")
+        .and_then(|img| resolve_url(base.as_ref(), img));
+
+    let card = UnfurlCard {
+        url: requested_url.to_string(),
+        title: title
+            .map(|t| truncate(&t, MAX_TITLE_LEN))
+            .filter(|t| !t.is_empty()),
+        description: description
+            .map(|d| truncate(&d, MAX_DESCRIPTION_LEN))
+            .filter(|d| !d.is_empty()),
+        image_url,
+        site_name: og.get("og:site_name").cloned().filter(|s| !s.is_empty()),
+    };
+    (card, oembed_url)
+}
+
+#[derive(Deserialize)]
+struct OEmbed {
+    title: Option<String>,
+    author_name: Option<String>,
+    provider_name: Option<String>,
+    thumbnail_url: Option<String>,
+}
+
+fn merge_oembed(card: &mut UnfurlCard, body: &[u8]) {
+    let oembed: OEmbed = match serde_json::from_slice(body) {
+        Ok(oembed) => oembed,
+        Err(e) => {
+            warn!("parse oembed error: {}", e);
+            return;
+        }
+    };
+
+    card.title = card.title.take().or(oembed.title);
+    card.description = card.description.take().or(oembed.author_name);
+    card.site_name = card.site_name.take().or(oembed.provider_name);
+    card.image_url = card
+        .image_url
+        .take()
+        .or_else(|| oembed.thumbnail_url.and_then(|u| resolve_url(None, &u)));
+}
+
+// Only http(s) urls are kept, so a card can never carry a `javascript:` image.
+fn resolve_url(base: Option<&Url>, href: &str) -> Option<String> {
+    let url = match base {
+        Some(base) => base.join(href).ok()?,
+        None => Url::parse(href).ok()?,
+    };
+    matches!(url.scheme(), "http" | "https").then(|| url.to_string())
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((i, _)) => format!("{}…", &text[..i]),
+        None => text.to_string(),
+    }
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_html_card() {
+        let html = r#"<html><head>
+            <title>Fallback</title>
+            <meta content="Rust &amp; Axum" property="og:title">
+            <meta name="description" content='A web framework'>
+            <meta property="og:image" content="/img/card.png">
+            <meta property="og:site_name" content="docs.rs">
+            <link rel="alternate" type="application/json+oembed" href="/oembed?url=x">
+            </head></html>"#;
+
+        let (card, oembed) =
+            parse_html("https://example.com/a", "https://www.example.com/b/", html);
+        assert_eq!(
+            card,
+            UnfurlCard {
+                url: "https://example.com/a".to_string(),
+                title: Some("Rust & Axum".to_string()),
+                description: Some("A web framework".to_string()),
+                image_url: Some("https://www.example.com/img/card.png".to_string()),
+                site_name: Some("docs.rs".to_string()),
+            }
+        );
+        assert_eq!(
+            oembed.as_deref(),
+            Some("https://www.example.com/oembed?url=x")
+        );
+    }
+
+    #[test]
+    fn test_message_links() {
+        let links = message_links(
+            "see https://a.example/x, <https://b.example|b> and `https://c.example` \
+             https://a.example/x mailto:me@example.com",
+        );
+        assert_eq!(links, vec!["https://a.example/x", "https://b.example"]);
+    }
+}
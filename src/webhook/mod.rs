@@ -0,0 +1,426 @@
+use std::time::Duration;
+use tracing::warn;
+
+use futures_util::future::join_all;
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Url, header};
+use sha2::Sha256;
+use sqlx::PgPool;
+use thiserror::Error;
+
+use crate::{
+    errors::AppError,
+    models::webhook::{
+        DELIVERY_DELIVERED, DELIVERY_FAILED, DELIVERY_PENDING, WebhookDelivery, WebhookStore,
+    },
+    unfurl::{
+        UnfurlError,
+        http::{check_url, guarded_client, send_error},
+    },
+};
+
+pub const SIGNATURE_HEADER: &str = "X-Slac-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-Slac-Timestamp";
+pub const EVENT_HEADER: &str = "X-Slac-Event";
+pub const DELIVERY_HEADER: &str = "X-Slac-Delivery";
+
+const USER_AGENT: &str = "slac-webhook/0.1";
+// A delivery is given up after this many attempts.
+pub const MAX_ATTEMPTS: i32 = 8;
+// A webhook is disabled after this many failed attempts in a row, across deliveries.
+pub const AUTO_DISABLE_AFTER: i32 = 20;
+const BASE_BACKOFF_SECS: u64 = 10;
+const MAX_BACKOFF_SECS: u64 = 60 * 60;
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const BATCH_SIZE: i64 = 20;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Error, Debug)]
+pub enum DeliveryError {
+    #[error("url not allowed: {0}")]
+    Blocked(String),
+
+    #[error("send failed: {0}")]
+    Send(String),
+
+    #[error("receiver answered {0}")]
+    Status(u16),
+}
+
+impl From<UnfurlError> for DeliveryError {
+    fn from(e: UnfurlError) -> Self {
+        match e {
+            UnfurlError::Blocked(msg) => DeliveryError::Blocked(msg),
+            other => DeliveryError::Send(other.to_string()),
+        }
+    }
+}
+
+/// The `X-Slac-Signature` of a delivery: `v1=` and the hex HMAC-SHA256, keyed with the
+/// webhook secret, of `"{timestamp}.{body}"`. Receivers should also reject stale timestamps.
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mac = signing_mac(secret, timestamp, body);
+    format!("v1={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Checks a signature made by [`sign`] in constant time.
+pub fn verify(secret: &str, timestamp: i64, body: &[u8], signature: &str) -> bool {
+    let Some(sig) = signature
+        .strip_prefix("v1=")
+        .and_then(|hex_sig| hex::decode(hex_sig).ok())
+    else {
+        return false;
+    };
+    signing_mac(secret, timestamp, body)
+        .verify_slice(&sig)
+        .is_ok()
+}
+
+fn signing_mac(secret: &str, timestamp: i64, body: &[u8]) -> HmacSha256 {
+    // HMAC takes keys of any length.
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("hmac key");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+/// The wait before retrying after the `attempt`th failure: 10s, 20s, 40s, .. up to an hour.
+pub fn backoff(attempt: i32) -> Duration {
+    let exp = attempt.clamp(1, 16) as u32 - 1;
+    Duration::from_secs((BASE_BACKOFF_SECS << exp).min(MAX_BACKOFF_SECS))
+}
+
+/// Posts signed payloads with the same private-network guard as link unfurling. Redirects
+/// are not followed: a receiver has to answer at the url it was registered with.
+#[derive(Debug, Clone)]
+pub struct WebhookSender {
+    pub timeout: Duration,
+    allow_private_ips: bool,
+    client: Client,
+}
+
+impl Default for WebhookSender {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+/// What a receiver is sent; the body is signed as is.
+#[derive(Debug)]
+pub struct Delivery<'a> {
+    // None for requests that are not queued, such as slash commands.
+    pub id: Option<i64>,
+    pub event_type: &'a str,
+    pub body: &'a [u8],
+}
+
+impl WebhookSender {
+    pub fn new(allow_private_ips: bool) -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            allow_private_ips,
+            client: guarded_client(USER_AGENT, allow_private_ips),
+        }
+    }
+
+    /// The same sender, sharing its connections, with another timeout.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sends one delivery and returns the receiver's status code if it was a 2xx.
+    pub async fn send(
+        &self,
+        url: &str,
+        secret: &str,
+        delivery: &Delivery<'_>,
+    ) -> Result<u16, DeliveryError> {
+        let resp = self.post(url, secret, delivery).await?;
+        Ok(resp.status().as_u16())
+    }
+
+    /// Sends a request that expects an answer and returns the answer's body, cut at
+    /// `max_bytes`.
+    pub async fn call(
+        &self,
+        url: &str,
+        secret: &str,
+        delivery: &Delivery<'_>,
+        max_bytes: usize,
+    ) -> Result<Vec<u8>, DeliveryError> {
+        let mut resp = self.post(url, secret, delivery).await?;
+
+        let mut body = Vec::new();
+        while let Some(chunk) = resp
+            .chunk()
+            .await
+            .map_err(|e| DeliveryError::Send(e.to_string()))?
+        {
+            body.extend_from_slice(&chunk);
+            if body.len() > max_bytes {
+                return Err(DeliveryError::Send(format!(
+                    "answer is larger than {} bytes",
+                    max_bytes
+                )));
+            }
+        }
+        Ok(body)
+    }
+
+    async fn post(
+        &self,
+        url: &str,
+        secret: &str,
+        delivery: &Delivery<'_>,
+    ) -> Result<reqwest::Response, DeliveryError> {
+        let url = Url::parse(url).map_err(|e| DeliveryError::Blocked(e.to_string()))?;
+        check_url(&url, self.allow_private_ips)?;
+
+        let timestamp = chrono::Utc::now().timestamp();
+        let mut req = self
+            .client
+            .post(url)
+            .timeout(self.timeout)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, delivery.event_type);
+        if let Some(id) = delivery.id {
+            req = req.header(DELIVERY_HEADER, id.to_string());
+        }
+        let resp = req
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(SIGNATURE_HEADER, sign(secret, timestamp, delivery.body))
+            .body(delivery.body.to_vec())
+            .send()
+            .await
+            .map_err(send_error)?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            return Err(DeliveryError::Status(status.as_u16()));
+        }
+        Ok(resp)
+    }
+}
+
+/// Polls the delivery queue until the process exits. Several workers may run against one
+/// database; each claims its own rows.
+pub async fn run_delivery_worker(pool: PgPool, sender: WebhookSender) {
+    loop {
+        match deliver_due(&pool, &sender).await {
+            // a full batch likely means more are waiting.
+            Ok(n) if n as i64 == BATCH_SIZE => continue,
+            Ok(_) => {}
+            Err(e) => warn!("deliver webhooks error: {}", e),
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Sends one batch of due deliveries and returns how many were attempted.
+pub async fn deliver_due(pool: &PgPool, sender: &WebhookSender) -> Result<usize, AppError> {
+    let store = WebhookStore::new(pool);
+    // the lease outlasts a send, so a delivery is only re-claimed if this worker died.
+    let lease_secs = sender.timeout.as_secs_f64() * 3.0;
+    let due = store.claim_due(BATCH_SIZE, lease_secs).await?;
+    let count = due.len();
+
+    let results = join_all(due.iter().map(|d| attempt(&store, sender, d))).await;
+    for (delivery, result) in due.iter().zip(results) {
+        if let Err(e) = result {
+            warn!("webhook delivery {} error: {}", delivery.id, e);
+        }
+    }
+    Ok(count)
+}
+
+async fn attempt(
+    store: &WebhookStore<'_>,
+    sender: &WebhookSender,
+    delivery: &WebhookDelivery,
+) -> Result<(), AppError> {
+    let hook = match store.get_by_id(delivery.webhook_id).await? {
+        Some(hook) if hook.is_active => hook,
+        _ => {
+            return store
+                .finish_attempt(
+                    delivery.id,
+                    DELIVERY_FAILED,
+                    None,
+                    Some("webhook disabled"),
+                    None,
+                )
+                .await;
+        }
+    };
+
+    let body = serde_json::to_vec(&delivery.payload).unwrap_or_default();
+    let started = std::time::Instant::now();
+    let result = sender
+        .send(
+            &hook.url,
+            &hook.secret,
+            &Delivery {
+                id: Some(delivery.id),
+                event_type: &delivery.event_type,
+                body: &body,
+            },
+        )
+        .await;
+    let duration_ms = started.elapsed().as_millis().min(i32::MAX as u128) as i32;
+
+    let (status_code, error) = match &result {
+        Ok(code) => (Some(*code as i32), None),
+        Err(DeliveryError::Status(code)) => (Some(*code as i32), result_error(&result)),
+        Err(_) => (None, result_error(&result)),
+    };
+    store
+        .log_attempt(delivery, status_code, error.as_deref(), duration_ms)
+        .await?;
+
+    if result.is_ok() {
+        store.reset_failures(hook.id).await?;
+        return store
+            .finish_attempt(delivery.id, DELIVERY_DELIVERED, status_code, None, None)
+            .await;
+    }
+
+    if store.record_failure(hook.id, AUTO_DISABLE_AFTER).await? {
+        warn!(
+            "webhook {} disabled after {} failures in a row",
+            hook.id, AUTO_DISABLE_AFTER
+        );
+        store
+            .finish_attempt(
+                delivery.id,
+                DELIVERY_FAILED,
+                status_code,
+                error.as_deref(),
+                None,
+            )
+            .await?;
+        store.fail_pending(hook.id, "webhook disabled").await?;
+        return Ok(());
+    }
+
+    if delivery.attempts >= MAX_ATTEMPTS {
+        return store
+            .finish_attempt(
+                delivery.id,
+                DELIVERY_FAILED,
+                status_code,
+                error.as_deref(),
+                None,
+            )
+            .await;
+    }
+    let retry_in = backoff(delivery.attempts).as_secs_f64();
+    store
+        .finish_attempt(
+            delivery.id,
+            DELIVERY_PENDING,
+            status_code,
+            error.as_deref(),
+            Some(retry_in),
+        )
+        .await
+}
+
+fn result_error(result: &Result<u16, DeliveryError>) -> Option<String> {
+    result.as_ref().err().map(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::serve_local;
+    use axum::{Router, body::Bytes, http::HeaderMap, http::StatusCode, routing::post};
+    use std::net::SocketAddr;
+    use tokio::sync::mpsc;
+
+    const SECRET: &str = "s3cret";
+
+    // A receiver that checks the signature and reports what it got.
+    async fn receiver(tx: mpsc::UnboundedSender<(String, Bytes)>) -> SocketAddr {
+        let app = Router::new()
+            .route(
+                "/hook",
+                post(move |headers: HeaderMap, body: Bytes| async move {
+                    let get = |name: &str| {
+                        headers
+                            .get(name)
+                            .and_then(|v| v.to_str().ok())
+                            .unwrap_or("")
+                            .to_string()
+                    };
+                    let timestamp = get(TIMESTAMP_HEADER).parse().unwrap_or(0);
+                    if !verify(SECRET, timestamp, &body, &get(SIGNATURE_HEADER)) {
+                        return StatusCode::UNAUTHORIZED;
+                    }
+                    tx.send((get(EVENT_HEADER), body)).unwrap();
+                    StatusCode::NO_CONTENT
+                }),
+            )
+            .route("/down", post(|| async { StatusCode::SERVICE_UNAVAILABLE }));
+
+        serve_local(app).await
+    }
+
+    #[tokio::test]
+    async fn test_send_to_local_receiver() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let addr = receiver(tx).await;
+        let sender = WebhookSender::new(true);
+        let body = br#"{"event":"member_joined","data":{"user_id":7},"channel_id":1}"#;
+        let delivery = Delivery {
+            id: Some(1),
+            event_type: "member_joined",
+            body,
+        };
+
+        let code = sender
+            .send(&format!("http://{}/hook", addr), SECRET, &delivery)
+            .await
+            .unwrap();
+        assert_eq!(code, 204);
+        let (event, received) = rx.recv().await.unwrap();
+        assert_eq!(event, "member_joined");
+        assert_eq!(&received[..], body);
+
+        let err = sender
+            .send(&format!("http://{}/hook", addr), "wrong", &delivery)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DeliveryError::Status(401)));
+        let err = sender
+            .send(&format!("http://{}/down", addr), SECRET, &delivery)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DeliveryError::Status(503)));
+
+        let guarded = WebhookSender::default();
+        let err = guarded
+            .send(&format!("http://{}/hook", addr), SECRET, &delivery)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DeliveryError::Blocked(_)));
+    }
+
+    #[test]
+    fn test_sign_and_backoff() {
+        let sig = sign(SECRET, 1700000000, b"{}");
+        assert!(sig.starts_with("v1=") && sig.len() == 3 + 64);
+        assert!(verify(SECRET, 1700000000, b"{}", &sig));
+        assert!(!verify(SECRET, 1700000001, b"{}", &sig));
+        assert!(!verify(SECRET, 1700000000, b"{ }", &sig));
+
+        assert_eq!(backoff(1), Duration::from_secs(10));
+        assert_eq!(backoff(3), Duration::from_secs(40));
+        assert_eq!(
+            backoff(MAX_ATTEMPTS + 10),
+            Duration::from_secs(MAX_BACKOFF_SECS)
+        );
+    }
+}